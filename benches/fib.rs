@@ -0,0 +1,25 @@
+//! Times the book's recursive Fibonacci, the benchmark the interpreter's variable lookups are
+//!   tuned against. Run it with `cargo bench`.
+
+use std::time::Instant;
+
+use rlox::interpreter::Interpreter;
+use rlox::parse_program;
+use rlox::resolver::resolve;
+
+const SOURCE: &str = "fun fib(n) {
+  if (n < 2) return n;
+  return fib(n - 1) + fib(n - 2);
+}
+var result = fib(30);";
+
+fn main() {
+    let program = parse_program(SOURCE).ast;
+    let locals = resolve(&program).expect("benchmark resolves");
+    let interpreter = Interpreter::default();
+    let start = Instant::now();
+    interpreter
+        .interpret(&program, locals)
+        .expect("benchmark runs");
+    println!("fib(30): {:?}", start.elapsed());
+}
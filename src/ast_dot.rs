@@ -0,0 +1,219 @@
+use std::cell::{Cell, RefCell};
+
+use crate::expr::{Expr, ExprVisitor, LiteralExpr};
+use crate::stmt::{FunctionStmt, Stmt, StmtVisitor};
+use crate::token::Token;
+
+/**
+ * Renders the AST as a Graphviz DOT digraph, e.g. `rlox ast --dot script.lox | dot -Tpng > ast.png`.
+ *   Visiting an expression emits its node and edges and returns the node's id.
+ */
+pub struct AstDotPrinter {
+    lines: RefCell<Vec<String>>,
+    node_count: Cell<usize>,
+}
+
+impl ExprVisitor<String> for AstDotPrinter {
+    fn visit_expr(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Assign(expr) => self.node_with_children(
+                &format!("Assign {}", expr.name().lexeme()),
+                &[("value", expr.value())],
+            ),
+            Expr::Binary(expr) => self.node_with_children(
+                expr.operator().lexeme(),
+                &[("lhs", expr.lhs()), ("rhs", expr.rhs())],
+            ),
+            Expr::Call(expr) => {
+                let id = self.node_with_children("Call", &[("callee", expr.callee())]);
+                for (i, argument) in expr.arguments().iter().enumerate() {
+                    let child = argument.accept::<String>(self);
+                    self.edge(&id, &child, &format!("arg {}", i));
+                }
+                id
+            }
+            Expr::Function(expr) => {
+                let id = self.node(&format!("fun ({})", Self::param_list(expr.params())));
+                self.stmts(&id, expr.body());
+                id
+            }
+            Expr::Get(expr) => self.node_with_children(
+                &format!(". {}", expr.name().lexeme()),
+                &[("object", expr.object())],
+            ),
+            Expr::Grouping(expr) => self.node_with_children("Grouping", &[("", expr.expression())]),
+            Expr::Literal(expr) => match expr.as_ref() {
+                LiteralExpr::Nil => self.node("nil"),
+                LiteralExpr::String(s) => self.node(&format!("\"{}\"", s)),
+                LiteralExpr::Float(f) => self.node(&f.to_string()),
+                LiteralExpr::Bool(b) => self.node(&b.to_string()),
+            },
+            Expr::Logical(expr) => self.node_with_children(
+                expr.operator().lexeme(),
+                &[("lhs", expr.lhs()), ("rhs", expr.rhs())],
+            ),
+            Expr::Set(expr) => self.node_with_children(
+                &format!("Set {}", expr.name().lexeme()),
+                &[("object", expr.object()), ("value", expr.value())],
+            ),
+            Expr::Super(expr) => self.node(&format!("super.{}", expr.method().lexeme())),
+            Expr::This(_) => self.node("this"),
+            Expr::Unary(expr) => {
+                self.node_with_children(expr.operator().lexeme(), &[("", expr.rhs())])
+            }
+            Expr::Variable(expr) => self.node(expr.name().lexeme()),
+        }
+    }
+}
+
+impl StmtVisitor<String> for AstDotPrinter {
+    fn visit_stmt(&self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Block(stmt) => {
+                let id = self.node("Block");
+                self.stmts(&id, stmt.statements());
+                id
+            }
+            Stmt::Break(_) => self.node("Break"),
+            Stmt::Continue(_) => self.node("Continue"),
+            Stmt::Class(stmt) => {
+                let id = self.node(&format!("Class {}", stmt.name().lexeme()));
+                if let Some(superclass) = stmt.superclass() {
+                    let child = superclass.accept::<String>(self);
+                    self.edge(&id, &child, "superclass");
+                }
+                for method in stmt.methods() {
+                    let child = self.function(method);
+                    self.edge(&id, &child, "method");
+                }
+                id
+            }
+            Stmt::Expression(stmt) => {
+                self.node_with_children("Expression", &[("", stmt.expression())])
+            }
+            Stmt::Function(stmt) => self.function(stmt),
+            Stmt::If(stmt) => {
+                let id = self.node_with_children("If", &[("condition", stmt.condition())]);
+                let then_branch = self.stmt(stmt.then_branch());
+                self.edge(&id, &then_branch, "then");
+                if let Some(else_branch) = stmt.else_branch() {
+                    let else_branch = self.stmt(else_branch);
+                    self.edge(&id, &else_branch, "else");
+                }
+                id
+            }
+            Stmt::Print(stmt) => self.node_with_children("Print", &[("", stmt.expression())]),
+            Stmt::Return(stmt) => match stmt.value() {
+                Some(value) => self.node_with_children("Return", &[("", value)]),
+                None => self.node("Return"),
+            },
+            Stmt::Var(stmt) => {
+                let label = format!("Var {}", stmt.name().lexeme());
+                match stmt.initializer() {
+                    Some(initializer) => self.node_with_children(&label, &[("", initializer)]),
+                    None => self.node(&label),
+                }
+            }
+            Stmt::While(stmt) => {
+                let id = self.node_with_children("While", &[("condition", stmt.condition())]);
+                let body = self.stmt(stmt.body());
+                self.edge(&id, &body, "body");
+                if let Some(increment) = stmt.increment() {
+                    let increment = increment.accept::<String>(self);
+                    self.edge(&id, &increment, "increment");
+                }
+                id
+            }
+        }
+    }
+}
+
+impl AstDotPrinter {
+    pub fn print_program(&self, statements: &[Stmt]) -> String {
+        self.lines.borrow_mut().clear();
+        self.node_count.set(0);
+        let root = self.node("Program");
+        self.stmts(&root, statements);
+
+        let mut s = String::from("digraph ast {\n    node [shape=box];\n");
+        for line in self.lines.borrow().iter() {
+            s.push_str("    ");
+            s.push_str(line);
+            s.push('\n');
+        }
+        s.push_str("}\n");
+        s
+    }
+
+    // how many nodes the last program printed had, its root included
+    pub fn node_count(&self) -> usize {
+        self.node_count.get()
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        AstDotPrinter {
+            lines: RefCell::new(Vec::new()),
+            node_count: Cell::new(0),
+        }
+    }
+
+    fn stmt(&self, stmt: &Stmt) -> String {
+        stmt.accept::<String>(self)
+    }
+
+    fn function(&self, stmt: &FunctionStmt) -> String {
+        let params = Self::param_list(stmt.params());
+        let id = self.node(&format!("Function {}({})", stmt.name().lexeme(), params));
+        self.stmts(&id, stmt.body());
+        id
+    }
+
+    fn param_list(params: &[Token]) -> String {
+        params
+            .iter()
+            .map(|p| p.lexeme())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn stmts(&self, parent: &str, statements: &[Stmt]) {
+        for stmt in statements {
+            let child = self.stmt(stmt);
+            self.edge(parent, &child, "");
+        }
+    }
+
+    fn node_with_children(&self, label: &str, children: &[(&str, &Expr)]) -> String {
+        let id = self.node(label);
+        for (role, child) in children {
+            let child = child.accept::<String>(self);
+            self.edge(&id, &child, role);
+        }
+        id
+    }
+
+    fn node(&self, label: &str) -> String {
+        let id = format!("n{}", self.node_count.get());
+        self.node_count.set(self.node_count.get() + 1);
+        let line = format!("{} [label=\"{}\"];", id, escape(label));
+        self.lines.borrow_mut().push(line);
+        id
+    }
+
+    fn edge(&self, from: &str, to: &str, role: &str) {
+        let line = match role.is_empty() {
+            true => format!("{} -> {};", from, to),
+            false => format!("{} -> {} [label=\"{}\"];", from, to, escape(role)),
+        };
+        self.lines.borrow_mut().push(line);
+    }
+}
+
+// DOT quoted strings only need quotes, backslashes and line breaks escaped
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,306 @@
+use crate::expr::{
+    AssignExpr, BinaryExpr, CallExpr, Expr, ExprVisitor, FunctionExpr, GetExpr, GroupingExpr,
+    LiteralExpr, LogicalExpr, SetExpr, SuperExpr, ThisExpr, UnaryExpr, VariableExpr,
+};
+use crate::stmt::{FunctionStmt, Stmt, StmtVisitor};
+use crate::token::Token;
+
+/**
+ * Renders the AST as compact JSON for external tooling and parser snapshot tests.
+ *   Every node is an object with a "kind"; nodes built around a token carry its lexeme and "line".
+ */
+pub struct AstJsonPrinter;
+
+impl ExprVisitor<String> for AstJsonPrinter {
+    fn visit_expr(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Assign(expr) => self.visit_assign_expr(expr),
+            Expr::Binary(expr) => self.visit_binary_expr(expr),
+            Expr::Call(expr) => self.visit_call_expr(expr),
+            Expr::Function(expr) => self.visit_function_expr(expr),
+            Expr::Get(expr) => self.visit_get_expr(expr),
+            Expr::Grouping(expr) => self.visit_grouping_expr(expr),
+            Expr::Literal(expr) => self.visit_literal_expr(expr),
+            Expr::Logical(expr) => self.visit_logical_expr(expr),
+            Expr::Set(expr) => self.visit_set_expr(expr),
+            Expr::Super(expr) => self.visit_super_expr(expr),
+            Expr::This(expr) => self.visit_this_expr(expr),
+            Expr::Unary(expr) => self.visit_unary_expr(expr),
+            Expr::Variable(expr) => self.visit_variable_expr(expr),
+        }
+    }
+}
+
+impl StmtVisitor<String> for AstJsonPrinter {
+    fn visit_stmt(&self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Block(stmt) => {
+                self.node("Block", &[("statements", self.stmts(stmt.statements()))])
+            }
+            Stmt::Break(stmt) => self.token_node("Break", "keyword", stmt.keyword(), &[]),
+            Stmt::Continue(stmt) => self.token_node("Continue", "keyword", stmt.keyword(), &[]),
+            Stmt::Class(stmt) => self.token_node(
+                "Class",
+                "name",
+                stmt.name(),
+                &[
+                    ("superclass", self.optional_expr(stmt.superclass())),
+                    (
+                        "methods",
+                        self.array(stmt.methods().iter().map(|m| self.function(m))),
+                    ),
+                ],
+            ),
+            Stmt::Expression(stmt) => self.node(
+                "Expression",
+                &[("expression", self.expr(stmt.expression()))],
+            ),
+            Stmt::Function(stmt) => self.function(stmt),
+            Stmt::If(stmt) => self.token_node(
+                "If",
+                "keyword",
+                stmt.keyword(),
+                &[
+                    ("condition", self.expr(stmt.condition())),
+                    ("then", self.stmt(stmt.then_branch())),
+                    (
+                        "else",
+                        stmt.else_branch()
+                            .as_ref()
+                            .map_or("null".to_string(), |s| self.stmt(s)),
+                    ),
+                ],
+            ),
+            Stmt::Print(stmt) => {
+                self.node("Print", &[("expression", self.expr(stmt.expression()))])
+            }
+            Stmt::Return(stmt) => self.token_node(
+                "Return",
+                "keyword",
+                stmt.keyword(),
+                &[("value", self.optional_expr(stmt.value()))],
+            ),
+            Stmt::Var(stmt) => self.token_node(
+                "Var",
+                "name",
+                stmt.name(),
+                &[("initializer", self.optional_expr(stmt.initializer()))],
+            ),
+            Stmt::While(stmt) => self.token_node(
+                "While",
+                "keyword",
+                stmt.keyword(),
+                &[
+                    ("condition", self.expr(stmt.condition())),
+                    ("body", self.stmt(stmt.body())),
+                    ("increment", self.optional_expr(stmt.increment())),
+                ],
+            ),
+        }
+    }
+}
+
+impl AstJsonPrinter {
+    pub fn print(&self, expr: Expr) -> String {
+        expr.accept::<String>(self)
+    }
+
+    pub fn print_program(&self, statements: &[Stmt]) -> String {
+        self.array(statements.iter().map(|stmt| self.stmt(stmt)))
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        AstJsonPrinter {}
+    }
+
+    fn stmt(&self, stmt: &Stmt) -> String {
+        stmt.accept::<String>(self)
+    }
+
+    fn function(&self, stmt: &FunctionStmt) -> String {
+        let params = self.array(stmt.params().iter().map(|p| string(p.lexeme())));
+        self.token_node(
+            "Function",
+            "name",
+            stmt.name(),
+            &[("params", params), ("body", self.stmts(stmt.body()))],
+        )
+    }
+
+    fn stmts(&self, statements: &[Stmt]) -> String {
+        self.array(statements.iter().map(|stmt| self.stmt(stmt)))
+    }
+
+    fn expr(&self, expr: &Expr) -> String {
+        expr.accept::<String>(self)
+    }
+
+    fn optional_expr(&self, expr: &Option<Expr>) -> String {
+        expr.as_ref().map_or("null".to_string(), |e| self.expr(e))
+    }
+
+    fn node(&self, kind: &str, fields: &[(&str, String)]) -> String {
+        let mut s = format!("{{\"kind\":{}", string(kind));
+        for (name, value) in fields {
+            s.push_str(&format!(",{}:{}", string(name), value));
+        }
+        s.push('}');
+        s
+    }
+
+    // a node named after one of its tokens, e.g. the operator of a binary expression
+    fn token_node(
+        &self,
+        kind: &str,
+        role: &str,
+        token: &Token,
+        fields: &[(&str, String)],
+    ) -> String {
+        let mut all_fields = vec![
+            (role, string(token.lexeme())),
+            ("line", token.line_number().to_string()),
+        ];
+        all_fields.extend(fields.iter().cloned());
+        self.node(kind, &all_fields)
+    }
+
+    fn array(&self, items: impl Iterator<Item = String>) -> String {
+        format!("[{}]", items.collect::<Vec<_>>().join(","))
+    }
+
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> String {
+        self.token_node(
+            "Assign",
+            "name",
+            expr.name(),
+            &[("value", self.expr(expr.value()))],
+        )
+    }
+
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> String {
+        self.token_node(
+            "Binary",
+            "operator",
+            expr.operator(),
+            &[
+                ("lhs", self.expr(expr.lhs())),
+                ("rhs", self.expr(expr.rhs())),
+            ],
+        )
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> String {
+        let arguments = self.array(expr.arguments().iter().map(|a| self.expr(a)));
+        self.token_node(
+            "Call",
+            "paren",
+            expr.paren(),
+            &[
+                ("callee", self.expr(expr.callee())),
+                ("arguments", arguments),
+            ],
+        )
+    }
+
+    fn visit_function_expr(&self, expr: &FunctionExpr) -> String {
+        let params = self.array(expr.params().iter().map(|p| string(p.lexeme())));
+        self.token_node(
+            "Lambda",
+            "keyword",
+            expr.keyword(),
+            &[("params", params), ("body", self.stmts(expr.body()))],
+        )
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> String {
+        self.token_node(
+            "Get",
+            "name",
+            expr.name(),
+            &[("object", self.expr(expr.object()))],
+        )
+    }
+
+    fn visit_grouping_expr(&self, expr: &GroupingExpr) -> String {
+        self.node("Grouping", &[("expression", self.expr(expr.expression()))])
+    }
+
+    fn visit_literal_expr(&self, expr: &LiteralExpr) -> String {
+        let value = match expr {
+            LiteralExpr::Nil => "null".to_string(),
+            LiteralExpr::String(s) => string(s),
+            LiteralExpr::Float(f) => f.to_string(),
+            LiteralExpr::Bool(b) => b.to_string(),
+        };
+        self.node("Literal", &[("value", value)])
+    }
+
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> String {
+        self.token_node(
+            "Logical",
+            "operator",
+            expr.operator(),
+            &[
+                ("lhs", self.expr(expr.lhs())),
+                ("rhs", self.expr(expr.rhs())),
+            ],
+        )
+    }
+
+    fn visit_set_expr(&self, expr: &SetExpr) -> String {
+        self.token_node(
+            "Set",
+            "name",
+            expr.name(),
+            &[
+                ("object", self.expr(expr.object())),
+                ("value", self.expr(expr.value())),
+            ],
+        )
+    }
+
+    fn visit_super_expr(&self, expr: &SuperExpr) -> String {
+        self.token_node(
+            "Super",
+            "method",
+            expr.method(),
+            &[("keyword", string(expr.keyword().lexeme()))],
+        )
+    }
+
+    fn visit_this_expr(&self, expr: &ThisExpr) -> String {
+        self.token_node("This", "keyword", expr.keyword(), &[])
+    }
+
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> String {
+        self.token_node(
+            "Unary",
+            "operator",
+            expr.operator(),
+            &[("rhs", self.expr(expr.rhs()))],
+        )
+    }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> String {
+        self.token_node("Variable", "name", expr.name(), &[])
+    }
+}
+
+// a JSON string literal, escaping quotes, backslashes and control characters
+pub(crate) fn string(s: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
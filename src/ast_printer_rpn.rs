@@ -1,14 +1,26 @@
-use crate::expr::{BinaryExpr, Expr, GroupingExpr, LiteralExpr, UnaryExpr, Visitor};
+use crate::expr::{
+    AssignExpr, BinaryExpr, CallExpr, Expr, ExprVisitor, FunctionExpr, GetExpr, GroupingExpr,
+    LiteralExpr, LogicalExpr, SetExpr, SuperExpr, ThisExpr, UnaryExpr, VariableExpr,
+};
 
 pub struct AstPrinterRpn;
 
-impl Visitor<String> for AstPrinterRpn {
+impl ExprVisitor<String> for AstPrinterRpn {
     fn visit_expr(&self, expr: &Expr) -> String {
         match expr {
-            Expr::Binary(expr) => self.visit_binary_expr(&expr),
-            Expr::Grouping(expr) => self.visit_grouping_expr(&expr),
-            Expr::Literal(expr) => self.visit_literal_expr(&expr),
-            Expr::Unary(expr) => self.visit_unary_expr(&expr),
+            Expr::Assign(expr) => self.visit_assign_expr(expr),
+            Expr::Binary(expr) => self.visit_binary_expr(expr),
+            Expr::Call(expr) => self.visit_call_expr(expr),
+            Expr::Function(expr) => self.visit_function_expr(expr),
+            Expr::Get(expr) => self.visit_get_expr(expr),
+            Expr::Grouping(expr) => self.visit_grouping_expr(expr),
+            Expr::Literal(expr) => self.visit_literal_expr(expr),
+            Expr::Logical(expr) => self.visit_logical_expr(expr),
+            Expr::Set(expr) => self.visit_set_expr(expr),
+            Expr::Super(expr) => self.visit_super_expr(expr),
+            Expr::This(expr) => self.visit_this_expr(expr),
+            Expr::Unary(expr) => self.visit_unary_expr(expr),
+            Expr::Variable(expr) => self.visit_variable_expr(expr),
         }
     }
 }
@@ -18,6 +30,7 @@ impl AstPrinterRpn {
         expr.accept::<String>(self).to_string()
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         AstPrinterRpn {}
     }
@@ -27,14 +40,46 @@ impl AstPrinterRpn {
         let mut s = String::from("");
         for expr in expressions {
             s.push_str(&expr.accept::<String>(self));
-            s.push_str(" ");
+            s.push(' ');
         }
         s.push_str(name);
-        return s.to_string();
+        s
+    }
+
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> String {
+        format!(
+            "{} {} =",
+            expr.value().accept::<String>(self),
+            expr.name().lexeme()
+        )
     }
 
     fn visit_binary_expr(&self, expr: &BinaryExpr) -> String {
-        self.format_in_rpn(&expr.operator().lexeme(), &[expr.lhs(), expr.rhs()])
+        self.format_in_rpn(expr.operator().lexeme(), &[expr.lhs(), expr.rhs()])
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> String {
+        let mut expressions = vec![expr.callee()];
+        expressions.extend(expr.arguments());
+        self.format_in_rpn("call", &expressions)
+    }
+
+    fn visit_function_expr(&self, expr: &FunctionExpr) -> String {
+        let params = expr
+            .params()
+            .iter()
+            .map(|p| p.lexeme())
+            .collect::<Vec<_>>()
+            .join(" ");
+        format!("{} fun", params)
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> String {
+        format!(
+            "{} {} .",
+            expr.object().accept::<String>(self),
+            expr.name().lexeme()
+        )
     }
 
     fn visit_grouping_expr(&self, expr: &GroupingExpr) -> String {
@@ -50,7 +95,32 @@ impl AstPrinterRpn {
         }
     }
 
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> String {
+        self.format_in_rpn(expr.operator().lexeme(), &[expr.lhs(), expr.rhs()])
+    }
+
+    fn visit_set_expr(&self, expr: &SetExpr) -> String {
+        format!(
+            "{} {} {} . =",
+            expr.value().accept::<String>(self),
+            expr.object().accept::<String>(self),
+            expr.name().lexeme()
+        )
+    }
+
+    fn visit_super_expr(&self, expr: &SuperExpr) -> String {
+        format!("super {} .", expr.method().lexeme())
+    }
+
+    fn visit_this_expr(&self, _expr: &ThisExpr) -> String {
+        "this".to_string()
+    }
+
     fn visit_unary_expr(&self, expr: &UnaryExpr) -> String {
-        self.format_in_rpn(expr.operator().lexeme(), &[&expr.rhs()])
+        self.format_in_rpn(expr.operator().lexeme(), &[expr.rhs()])
+    }
+
+    fn visit_variable_expr(&self, expr: &VariableExpr) -> String {
+        expr.name().lexeme().to_string()
     }
 }
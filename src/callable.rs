@@ -0,0 +1,157 @@
+use std::cell::RefCell;
+use std::fmt::{self, Display};
+use std::rc::Rc;
+
+use crate::class::LoxInstance;
+use crate::environment::Environment;
+use crate::error::RloxError;
+use crate::expr::FunctionExpr;
+use crate::interpreter::{Interpreter, Value};
+use crate::stmt::{FunctionStmt, Stmt};
+use crate::token::Token;
+
+/**
+ * Anything a Lox program can call. The interpreter checks the argument count against `arity`
+ *   before calling, so `call` always receives exactly that many arguments.
+ *   `Display` is how `print` shows the callable, and `frame` how a stack trace names a call to it.
+ */
+pub trait LoxCallable: Display {
+    fn arity(&self) -> usize;
+    // the name it was declared with; None for an anonymous function
+    fn name(&self) -> Option<&str>;
+    fn frame(&self) -> String;
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, RloxError>;
+}
+
+// callables have identity: two are equal only if they are the same object
+impl PartialEq for dyn LoxCallable {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(
+            self as *const Self as *const (),
+            other as *const Self as *const (),
+        )
+    }
+}
+
+impl fmt::Debug for dyn LoxCallable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+// the two places a function can be written: a `fun` declaration or an anonymous `fun` expression
+#[derive(Clone)]
+enum Declaration {
+    Named(Rc<FunctionStmt>),
+    Anonymous(Rc<FunctionExpr>),
+}
+
+/**
+ * A function written in Lox, together with the environment it was declared in.
+ */
+pub struct LoxFunction {
+    declaration: Declaration,
+    closure: Rc<RefCell<Environment>>,
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub fn declared(declaration: Rc<FunctionStmt>, closure: Rc<RefCell<Environment>>) -> Self {
+        LoxFunction {
+            declaration: Declaration::Named(declaration),
+            closure,
+            is_initializer: false,
+        }
+    }
+
+    // a method called `init` is its class's initializer
+    pub fn method(declaration: Rc<FunctionStmt>, closure: Rc<RefCell<Environment>>) -> Self {
+        LoxFunction {
+            is_initializer: declaration.name().lexeme() == "init",
+            declaration: Declaration::Named(declaration),
+            closure,
+        }
+    }
+
+    pub fn anonymous(declaration: Rc<FunctionExpr>, closure: Rc<RefCell<Environment>>) -> Self {
+        LoxFunction {
+            declaration: Declaration::Anonymous(declaration),
+            closure,
+            is_initializer: false,
+        }
+    }
+
+    /**
+     * The method bound to `instance`: a copy whose closure has `this` defined as the instance,
+     *   so the method remembers its receiver however it is called later.
+     */
+    pub fn bind(&self, instance: Rc<LoxInstance>) -> LoxFunction {
+        let mut environment = Environment::new(self.closure.clone());
+        environment.define("this", Value::Instance(instance));
+        LoxFunction {
+            declaration: self.declaration.clone(),
+            closure: Rc::new(RefCell::new(environment)),
+            is_initializer: self.is_initializer,
+        }
+    }
+
+    fn params(&self) -> &[Token] {
+        match &self.declaration {
+            Declaration::Named(declaration) => declaration.params(),
+            Declaration::Anonymous(declaration) => declaration.params(),
+        }
+    }
+
+    fn body(&self) -> &[Stmt] {
+        match &self.declaration {
+            Declaration::Named(declaration) => declaration.body(),
+            Declaration::Anonymous(declaration) => declaration.body(),
+        }
+    }
+}
+
+impl LoxCallable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.params().len()
+    }
+
+    fn name(&self) -> Option<&str> {
+        match &self.declaration {
+            Declaration::Named(declaration) => Some(declaration.name().lexeme()),
+            Declaration::Anonymous(_) => None,
+        }
+    }
+
+    fn frame(&self) -> String {
+        match &self.declaration {
+            Declaration::Named(declaration) => format!("{}()", declaration.name().lexeme()),
+            Declaration::Anonymous(_) => "fun()".to_string(),
+        }
+    }
+
+    // each call gets its own environment, so recursive calls do not share parameters
+    //   An initializer always returns its instance, even when called directly or left by `return;`.
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, RloxError> {
+        let mut environment = Environment::new(self.closure.clone());
+        for (param, argument) in self.params().iter().zip(arguments) {
+            environment.define(param.lexeme(), argument);
+        }
+        let value = interpreter.execute_body(self.body(), environment)?;
+        match (&self.declaration, self.is_initializer) {
+            // `this` is the only variable in the scope `bind` made
+            (Declaration::Named(declaration), true) => {
+                self.closure.borrow().get_at(0, 0, declaration.name())
+            }
+            _ => Ok(value),
+        }
+    }
+}
+
+impl Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.declaration {
+            Declaration::Named(declaration) => write!(f, "<fn {}>", declaration.name().lexeme()),
+            Declaration::Anonymous(_) => write!(f, "<fn>"),
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+use std::rc::{Rc, Weak};
+
+use crate::callable::{LoxCallable, LoxFunction};
+use crate::error::{RloxError, RloxRuntimeError};
+use crate::error_codes::UNDEFINED_PROPERTY;
+use crate::interpreter::{Interpreter, Value};
+use crate::token::Token;
+
+/**
+ * A class declared in Lox. Calling the class creates an instance of it and runs the class's
+ *   `init` method, if it has one, on the instance with the call's arguments.
+ */
+pub struct LoxClass {
+    name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Rc<LoxFunction>>,
+    // the class itself, for the instances it creates to point back to
+    this: Weak<LoxClass>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: &str,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Rc<LoxFunction>>,
+    ) -> Rc<Self> {
+        Rc::new_cyclic(|this| LoxClass {
+            name: name.to_string(),
+            superclass,
+            methods,
+            this: this.clone(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // a method not declared on the class itself is inherited from the nearest superclass with it
+    pub fn find_method(&self, name: &str) -> Option<&Rc<LoxFunction>> {
+        self.methods.get(name).or_else(|| {
+            self.superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_method(name))
+        })
+    }
+}
+
+impl LoxCallable for LoxClass {
+    fn arity(&self) -> usize {
+        self.find_method("init").map_or(0, |init| init.arity())
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    // the class's initializer is what runs
+    fn frame(&self) -> String {
+        format!("{}.init()", self.name)
+    }
+
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, RloxError> {
+        // a class being called is alive, so the upgrade cannot fail
+        let class = self.this.upgrade().expect("class outlives its call");
+        let instance = Rc::new(LoxInstance::new(class));
+        if let Some(init) = self.find_method("init") {
+            init.bind(instance.clone()).call(interpreter, arguments)?;
+        }
+        Ok(Value::Instance(instance))
+    }
+}
+
+impl Display for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl fmt::Debug for LoxClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+// classes and instances have identity, like all callables
+impl PartialEq for LoxClass {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+/**
+ * An object created by calling a class. Fields are not declared: assigning to a property
+ *   creates it.
+ */
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: RefCell<HashMap<String, Value>>,
+}
+
+impl LoxInstance {
+    fn new(class: Rc<LoxClass>) -> Self {
+        LoxInstance {
+            class,
+            fields: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn class(&self) -> &Rc<LoxClass> {
+        &self.class
+    }
+
+    // every field, sorted by name
+    pub fn fields(&self) -> Vec<(String, Value)> {
+        let mut fields = self
+            .fields
+            .borrow()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect::<Vec<_>>();
+        fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+        fields
+    }
+
+    // fields shadow methods; a method is returned bound to this instance
+    pub fn get(self: &Rc<Self>, name: &Token) -> Result<Value, RloxError> {
+        if let Some(value) = self.fields.borrow().get(name.lexeme()) {
+            return Ok(value.clone());
+        }
+        match self.class.find_method(name.lexeme()) {
+            Some(method) => Ok(Value::Callable(Rc::new(method.bind(self.clone())))),
+            None => Err(RloxError::RuntimeError(Box::new(RloxRuntimeError {
+                code: &UNDEFINED_PROPERTY,
+                token: name.clone(),
+                message: format!("Undefined property '{}'.", name.lexeme()),
+                calls: Vec::new(),
+                file: None,
+            }))),
+        }
+    }
+
+    pub fn set(&self, name: &Token, value: Value) {
+        self.fields
+            .borrow_mut()
+            .insert(name.lexeme().to_string(), value);
+    }
+}
+
+impl Display for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
+
+// not derived: a field may refer back to the instance that holds it
+impl fmt::Debug for LoxInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+impl PartialEq for LoxInstance {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
@@ -0,0 +1,256 @@
+use crate::interpreter::{Interpreter, MAX_CALL_DEPTH};
+
+/**
+ * Project defaults read from an `rlox.toml` (or `.rloxrc`) in the working directory, written in
+ *   the subset of TOML that plain `key = value` settings need:
+ *   # comments run to the end of the line
+ *   backend = "tree-walk"
+ *   division_by_zero_error = true
+ *   max_call_depth = 4096
+ *   tail_calls = true
+ *   string_coercion = false
+ *   uninitialized_errors = false
+ *   prelude = ["lib/strings.lox", "lib/math.lox"]
+ *   Every key is optional; one left out keeps the interpreter's own default. Command line flags
+ *   are applied on top of these.
+ */
+#[derive(Debug, Default, PartialEq)]
+pub struct Config {
+    pub division_by_zero_error: Option<bool>,
+    pub max_call_depth: Option<usize>,
+    pub tail_calls: Option<bool>,
+    pub string_coercion: Option<bool>,
+    pub uninitialized_errors: Option<bool>,
+    pub prelude: Vec<String>,
+}
+
+// the names looked for, in order; the first that exists is used
+pub const CONFIG_FILE_NAMES: [&str; 2] = ["rlox.toml", ".rloxrc"];
+
+// the only backend there is so far
+const BACKENDS: [&str; 1] = ["tree-walk"];
+
+#[derive(Debug, PartialEq)]
+enum Setting {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+    Strings(Vec<String>),
+}
+
+impl Config {
+    // the settings in `source`, or a description of the first line that is not one
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut config = Config::default();
+        for (i, line) in source.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            config
+                .set(line)
+                .map_err(|message| format!("line {}: {}", i + 1, message))?;
+        }
+        Ok(config)
+    }
+
+    fn set(&mut self, line: &str) -> Result<(), String> {
+        let (key, value) = match line.find('=') {
+            Some(at) => (line[..at].trim(), parse_setting(line[at + 1..].trim())?),
+            None => return Err(format!("expected `key = value`, found `{}`", line)),
+        };
+        match (key, value) {
+            ("backend", Setting::String(backend)) => {
+                if !BACKENDS.contains(&backend.as_str()) {
+                    return Err(format!("unknown backend \"{}\"", backend));
+                }
+            }
+            ("division_by_zero_error", Setting::Bool(b)) => self.division_by_zero_error = Some(b),
+            ("max_call_depth", Setting::Integer(n)) if n > 0 && n <= MAX_CALL_DEPTH as i64 => {
+                self.max_call_depth = Some(n as usize)
+            }
+            ("max_call_depth", Setting::Integer(n)) if n > 0 => {
+                return Err(format!(
+                    "`max_call_depth` can be at most {}, which the interpreter's stack can hold",
+                    MAX_CALL_DEPTH
+                ))
+            }
+            ("tail_calls", Setting::Bool(b)) => self.tail_calls = Some(b),
+            ("string_coercion", Setting::Bool(b)) => self.string_coercion = Some(b),
+            ("uninitialized_errors", Setting::Bool(b)) => self.uninitialized_errors = Some(b),
+            ("prelude", Setting::Strings(paths)) => self.prelude = paths,
+            (
+                "backend"
+                | "division_by_zero_error"
+                | "max_call_depth"
+                | "tail_calls"
+                | "string_coercion"
+                | "uninitialized_errors"
+                | "prelude",
+                value,
+            ) => return Err(format!("invalid value for `{}`: {:?}", key, value)),
+            _ => return Err(format!("unknown setting `{}`", key)),
+        }
+        Ok(())
+    }
+
+    // `interpreter` with every option this config sets
+    pub fn configure(&self, mut interpreter: Interpreter) -> Interpreter {
+        if let Some(enabled) = self.division_by_zero_error {
+            interpreter = interpreter.with_division_by_zero_errors(enabled);
+        }
+        if let Some(limit) = self.max_call_depth {
+            interpreter = interpreter.with_max_call_depth(limit);
+        }
+        if let Some(enabled) = self.tail_calls {
+            interpreter = interpreter.with_tail_calls(enabled);
+        }
+        if let Some(enabled) = self.string_coercion {
+            interpreter = interpreter.with_string_coercion(enabled);
+        }
+        if let Some(enabled) = self.uninitialized_errors {
+            interpreter = interpreter.with_uninitialized_errors(enabled);
+        }
+        interpreter
+    }
+}
+
+// `line` up to any `#` that is not inside a string
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            '#' if !in_string => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+// the items of a list, split at each `,` that is not inside a string
+fn split_items(items: &str) -> Vec<&str> {
+    let mut split = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in items.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            ',' if !in_string => {
+                split.push(&items[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    split.push(&items[start..]);
+    split
+}
+
+fn parse_setting(value: &str) -> Result<Setting, String> {
+    match value {
+        "true" => Ok(Setting::Bool(true)),
+        "false" => Ok(Setting::Bool(false)),
+        _ if value.starts_with('"') => parse_string(value).map(Setting::String),
+        _ if value.starts_with('[') && value.ends_with(']') => {
+            split_items(&value[1..value.len() - 1])
+                .into_iter()
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(parse_string)
+                .collect::<Result<Vec<_>, _>>()
+                .map(Setting::Strings)
+        }
+        _ => value
+            .replace('_', "")
+            .parse()
+            .map(Setting::Integer)
+            .map_err(|_| format!("invalid value `{}`", value)),
+    }
+}
+
+// a basic TOML string, which may escape quotes and backslashes
+fn parse_string(value: &str) -> Result<String, String> {
+    let invalid = || format!("invalid string `{}`", value);
+    let inner = value
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+    let mut string = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c @ ('"' | '\\')) => string.push(c),
+                Some('n') => string.push('\n'),
+                Some('t') => string.push('\t'),
+                _ => return Err(invalid()),
+            },
+            '"' => return Err(invalid()),
+            c => string.push(c),
+        }
+    }
+    Ok(string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+
+    #[test]
+    fn every_setting_is_read_and_comments_are_ignored() {
+        let source = "# project defaults\n\
+                      backend = \"tree-walk\"\n\
+                      division_by_zero_error = true  # like Python\n\
+                      max_call_depth = 4_096\n\
+                      tail_calls = false\n\
+                      prelude = [\"lib/#1.lox\", \"lib/b,\\\"c\\\".lox\",]\n";
+        assert_eq!(
+            Config::parse(source),
+            Ok(Config {
+                division_by_zero_error: Some(true),
+                max_call_depth: Some(4096),
+                tail_calls: Some(false),
+                prelude: vec!["lib/#1.lox".to_string(), "lib/b,\"c\".lox".to_string()],
+                ..Config::default()
+            })
+        );
+        assert_eq!(Config::parse(""), Ok(Config::default()));
+    }
+
+    #[test]
+    fn bad_lines_are_errors_naming_the_line() {
+        let error = |source| Config::parse(source).unwrap_err();
+        assert_eq!(
+            error("\nbackend = \"vm\""),
+            "line 2: unknown backend \"vm\""
+        );
+        assert_eq!(error("color = true"), "line 1: unknown setting `color`");
+        assert_eq!(
+            error("tail_calls = 1"),
+            "line 1: invalid value for `tail_calls`: Integer(1)"
+        );
+        assert_eq!(
+            error("max_call_depth = 0"),
+            "line 1: invalid value for `max_call_depth`: Integer(0)"
+        );
+        assert_eq!(
+            error("max_call_depth = 1_000_000"),
+            "line 1: `max_call_depth` can be at most 10000, which the interpreter's stack can hold"
+        );
+        assert_eq!(
+            error("tail_calls"),
+            "line 1: expected `key = value`, found `tail_calls`"
+        );
+        assert_eq!(
+            error("prelude = [\"a.lox]"),
+            "line 1: invalid string `\"a.lox`"
+        );
+    }
+}
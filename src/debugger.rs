@@ -0,0 +1,426 @@
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+use std::io::{BufRead, Write};
+use std::rc::{Rc, Weak};
+
+use crate::callable::LoxCallable;
+use crate::hooks::InterpreterHooks;
+use crate::interpreter::{Interpreter, Value};
+use crate::stmt::Stmt;
+
+/**
+ * An interactive debugger, built on the interpreter's hooks. It stops before the program's first
+ *   statement, and then wherever it is told to, reading commands from `input` and answering on
+ *   `output`:
+ *   step          run to the next line, in this call or any it makes
+ *   next          run to the next line in this call, or the one it returns to
+ *   continue      run to the next breakpoint
+ *   break LINE    stop whenever LINE is reached; `delete LINE` stops stopping there
+ *   print NAME    the value of the variable NAME, as the running code would see it
+ *   vars          every variable in scope, innermost scope first
+ *   backtrace     the calls in progress, innermost first
+ *   list          the source around the line stopped at
+ *   quit          end the program where it is
+ *   Each command may be shortened to its first letter, or `bt` for backtrace. At the end of
+ *   input the program runs on to its end without stopping again. Nothing stops before the
+ *   debugger is attached, so preludes run while the interpreter is set up go straight through.
+ */
+pub struct Debugger {
+    source: Vec<String>,
+    input: RefCell<Box<dyn BufRead>>,
+    output: RefCell<Box<dyn Write>>,
+    interpreter: RefCell<Weak<Interpreter>>,
+    breakpoints: RefCell<BTreeSet<usize>>,
+    mode: Cell<Mode>,
+    // each call in progress, the script itself first
+    frames: RefCell<Vec<Frame>>,
+    quit: Cell<bool>,
+}
+
+struct Frame {
+    function: String,
+    // the line it has got to, once it has run a statement
+    line: Option<usize>,
+    // the lines of its statements that have started and not yet finished, innermost last
+    open: Vec<usize>,
+}
+
+impl Frame {
+    fn new(function: String) -> Self {
+        Frame {
+            function,
+            line: None,
+            open: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Mode {
+    Step,
+    // stop once no more than this many frames are in progress
+    Next(usize),
+    Continue,
+}
+
+const HELP: &str = "\
+step, s         run to the next line, in this call or any it makes
+next, n         run to the next line in this call, or the one it returns to
+continue, c     run to the next breakpoint
+break, b LINE   stop whenever LINE is reached
+delete, d LINE  remove the breakpoint on LINE
+print, p NAME   show the value of the variable NAME
+vars, v         show every variable in scope, innermost scope first
+backtrace, bt   show the calls in progress, innermost first
+list, l         show the source around the current line
+quit, q         end the program here
+help, h         show this help";
+
+// lines of source `list` shows either side of the current one
+const LIST_CONTEXT: usize = 3;
+
+impl Debugger {
+    pub fn new(source: &str, input: Box<dyn BufRead>, output: Box<dyn Write>) -> Self {
+        Debugger {
+            source: source.lines().map(str::to_string).collect(),
+            input: RefCell::new(input),
+            output: RefCell::new(output),
+            interpreter: RefCell::new(Weak::new()),
+            breakpoints: RefCell::new(BTreeSet::new()),
+            mode: Cell::new(Mode::Step),
+            frames: RefCell::new(vec![Frame::new("script".to_string())]),
+            quit: Cell::new(false),
+        }
+    }
+
+    /**
+     * The interpreter whose variables `print` and `vars` show: the one these hooks are installed
+     *   on, which can only be handed over once it is built with them.
+     */
+    pub fn attach(&self, interpreter: &Rc<Interpreter>) {
+        self.interpreter.replace(Rc::downgrade(interpreter));
+    }
+
+    fn say(&self, text: &str) {
+        let mut output = self.output.borrow_mut();
+        // a debugger that cannot be heard has nothing better to do than carry on
+        let _ = writeln!(output, "{}", text);
+        let _ = output.flush();
+    }
+
+    // the next command, or None at the end of input
+    fn command(&self) -> Option<String> {
+        {
+            let mut output = self.output.borrow_mut();
+            let _ = write!(output, "(rlox) ");
+            let _ = output.flush();
+        }
+        let mut line = String::new();
+        match self.input.borrow_mut().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim().to_string()),
+        }
+    }
+
+    // takes commands until one of them resumes the program
+    fn pause(&self, line: usize) {
+        let function = self.frames.borrow().last().map(|f| f.function.clone());
+        self.say(&format!(
+            "line {} in {}",
+            line,
+            function.unwrap_or_default()
+        ));
+        self.say(&self.source_line(line));
+        loop {
+            let command = match self.command() {
+                Some(command) => command,
+                None => {
+                    self.breakpoints.borrow_mut().clear();
+                    self.mode.set(Mode::Continue);
+                    return;
+                }
+            };
+            let mut words = command.split_whitespace();
+            match (words.next(), words.next()) {
+                (None, _) => {}
+                (Some("s") | Some("step"), None) => return self.mode.set(Mode::Step),
+                (Some("n") | Some("next"), None) => {
+                    let depth = self.frames.borrow().len();
+                    return self.mode.set(Mode::Next(depth));
+                }
+                (Some("c") | Some("continue"), None) => return self.mode.set(Mode::Continue),
+                (Some("q") | Some("quit"), None) => return self.quit.set(true),
+                (Some("b") | Some("break"), Some(line)) => match line.parse::<usize>() {
+                    Ok(line) if line > 0 && line <= self.source.len() => {
+                        self.breakpoints.borrow_mut().insert(line);
+                        self.say(&format!("breakpoint on line {}", line));
+                    }
+                    _ => self.say(&format!("no line {} to break on", line)),
+                },
+                (Some("d") | Some("delete"), Some(line)) => {
+                    let removed = line
+                        .parse::<usize>()
+                        .is_ok_and(|line| self.breakpoints.borrow_mut().remove(&line));
+                    match removed {
+                        true => self.say(&format!("deleted the breakpoint on line {}", line)),
+                        false => self.say(&format!("no breakpoint on line {}", line)),
+                    }
+                }
+                (Some("p") | Some("print"), Some(name)) => self.print(name),
+                (Some("v") | Some("vars"), None) => self.vars(),
+                (Some("bt") | Some("backtrace"), None) => self.backtrace(),
+                (Some("l") | Some("list"), None) => self.list(line),
+                (Some("h") | Some("help"), None) => self.say(HELP),
+                _ => self.say(&format!("unknown command `{}`; try `help`", command)),
+            }
+        }
+    }
+
+    fn source_line(&self, line: usize) -> String {
+        let text = self.source.get(line - 1).map_or("", String::as_str);
+        format!("{:>4} | {}", line, text)
+    }
+
+    fn scopes(&self) -> Vec<Vec<(String, Option<Value>)>> {
+        match self.interpreter.borrow().upgrade() {
+            Some(interpreter) => interpreter.scopes(),
+            None => Vec::new(),
+        }
+    }
+
+    // the innermost variable called `name`, as a lookup from the running code would find it
+    fn print(&self, name: &str) {
+        let found = self
+            .scopes()
+            .into_iter()
+            .flatten()
+            .find(|(variable, _)| variable == name);
+        match found {
+            Some((_, value)) => self.say(&format!("{} = {}", name, show(&value))),
+            None => self.say(&format!("no variable `{}` in scope", name)),
+        }
+    }
+
+    fn vars(&self) {
+        let scopes = self.scopes();
+        let globals = scopes.len().saturating_sub(1);
+        for (depth, scope) in scopes.iter().enumerate() {
+            let variables = scope
+                .iter()
+                .filter(|(_, value)| !is_native(value))
+                .collect::<Vec<_>>();
+            if variables.is_empty() {
+                continue;
+            }
+            match depth == globals {
+                true => self.say("globals:"),
+                false => self.say(&format!("scope {}:", depth)),
+            }
+            for (name, value) in variables {
+                self.say(&format!("  {} = {}", name, show(value)));
+            }
+        }
+    }
+
+    // in the same shape as a runtime error's stack trace
+    fn backtrace(&self) {
+        for frame in self.frames.borrow().iter().rev() {
+            match frame.line {
+                Some(line) => self.say(&format!("[line {}] in {}", line, frame.function)),
+                None => self.say(&format!("in {}", frame.function)),
+            }
+        }
+    }
+
+    fn list(&self, line: usize) {
+        let first = line.saturating_sub(LIST_CONTEXT).max(1);
+        let last = (line + LIST_CONTEXT).min(self.source.len());
+        for n in first..=last {
+            let marker = if n == line { ">" } else { " " };
+            self.say(&format!("{}{}", marker, self.source_line(n)));
+        }
+    }
+}
+
+fn show(value: &Option<Value>) -> String {
+    match value {
+        Some(value) => value.pretty(),
+        None => "<uninitialized>".to_string(),
+    }
+}
+
+// the line a statement is stopped at on, if any: a block counts as the statements in it
+fn stopping_line(stmt: &Stmt) -> Option<usize> {
+    match stmt {
+        Stmt::Block(_) => None,
+        _ => stmt.line(),
+    }
+}
+
+// the globals every program starts with, which would only crowd out its own
+fn is_native(value: &Option<Value>) -> bool {
+    matches!(value, Some(value @ Value::Callable(_)) if value.to_string() == "<native fn>")
+}
+
+impl InterpreterHooks for Debugger {
+    // a statement nested in one on the same line, like the body of `if (a) print b;`, is part of
+    //   the line already stopped at
+    fn statement_enter(&self, stmt: &Stmt) {
+        if self.interpreter.borrow().strong_count() == 0 {
+            return;
+        }
+        let line = match stopping_line(stmt) {
+            Some(line) => line,
+            None => return,
+        };
+        let (depth, new_line) = {
+            let mut frames = self.frames.borrow_mut();
+            let depth = frames.len();
+            let frame = frames
+                .last_mut()
+                .expect("the script's frame is never popped");
+            let new_line = frame.open.last() != Some(&line);
+            frame.line = Some(line);
+            frame.open.push(line);
+            (depth, new_line)
+        };
+        let stop = match self.mode.get() {
+            Mode::Step => true,
+            Mode::Next(limit) => depth <= limit,
+            Mode::Continue => false,
+        };
+        if new_line && (stop || self.breakpoints.borrow().contains(&line)) {
+            self.pause(line);
+        }
+    }
+
+    fn statement_exit(&self, stmt: &Stmt) {
+        if self.interpreter.borrow().strong_count() == 0 || stopping_line(stmt).is_none() {
+            return;
+        }
+        if let Some(frame) = self.frames.borrow_mut().last_mut() {
+            frame.open.pop();
+        }
+    }
+
+    fn function_call(&self, callee: &dyn LoxCallable, _arguments: &[Value]) {
+        self.frames.borrow_mut().push(Frame::new(callee.frame()));
+    }
+
+    fn function_return(&self, _callee: &dyn LoxCallable, _value: Option<&Value>) {
+        self.frames.borrow_mut().pop();
+    }
+
+    fn interrupt(&self) -> Option<i32> {
+        match self.quit.get() {
+            true => Some(0),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::io::{self, Cursor, Write};
+    use std::rc::Rc;
+
+    use super::Debugger;
+    use crate::error::RloxError;
+    use crate::interpreter::Interpreter;
+    use crate::parse_program;
+    use crate::resolver::resolve;
+
+    // a Write whose output can still be read once the debugger has been handed it
+    #[derive(Clone, Default)]
+    struct Transcript(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Transcript {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    const SCRIPT: &str = "var total = 0;\n\
+                          fun add(n) {\n  \
+                            var sum = total + n;\n  \
+                            return sum;\n\
+                          }\n\
+                          total = add(2);\n\
+                          print total;\n";
+
+    // what the debugger said, driven by `commands`, and how the program ended
+    fn debug(commands: &str) -> (String, Result<(), RloxError>) {
+        let transcript = Transcript::default();
+        let debugger = Rc::new(Debugger::new(
+            SCRIPT,
+            Box::new(Cursor::new(commands.to_string())),
+            Box::new(transcript.clone()),
+        ));
+        let interpreter = Rc::new(Interpreter::default().with_hooks(debugger.clone()));
+        debugger.attach(&interpreter);
+        let program = parse_program(SCRIPT).ast;
+        let locals = resolve(&program).unwrap();
+        let result = interpreter.interpret(&program, locals);
+        let said = String::from_utf8(transcript.0.borrow().clone()).unwrap();
+        (said, result)
+    }
+
+    #[test]
+    fn breakpoints_show_locals_and_the_call_stack() {
+        let (said, result) = debug("break 4\ncontinue\nprint sum\nvars\nbt\nnext\nnext\n");
+        assert!(result.is_ok());
+        assert_eq!(
+            said,
+            "line 1 in script\n   1 | var total = 0;\n\
+             (rlox) breakpoint on line 4\n\
+             (rlox) line 4 in add()\n   4 |   return sum;\n\
+             (rlox) sum = 2\n\
+             (rlox) scope 0:\n  n = 2\n  sum = 2\nglobals:\n  add = <fn add/1>\n  total = 0\n\
+             (rlox) [line 4] in add()\n[line 6] in script\n\
+             (rlox) line 7 in script\n   7 | print total;\n\
+             (rlox) "
+        );
+    }
+
+    #[test]
+    fn stepping_enters_calls_and_next_steps_over_them() {
+        let (stepped, _) = debug("s\ns\ns\n");
+        let lines = |said: &str| {
+            said.lines()
+                .filter(|line| line.contains("line "))
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            lines(&stepped),
+            vec![
+                "line 1 in script",
+                "(rlox) line 2 in script",
+                "(rlox) line 6 in script",
+                "(rlox) line 3 in add()"
+            ]
+        );
+        let (nexted, _) = debug("n\nn\nn\n");
+        assert_eq!(
+            lines(&nexted),
+            vec![
+                "line 1 in script",
+                "(rlox) line 2 in script",
+                "(rlox) line 6 in script",
+                "(rlox) line 7 in script"
+            ]
+        );
+    }
+
+    #[test]
+    fn quit_ends_the_program_where_it_is() {
+        let (said, result) = debug("n\nquit\n");
+        assert!(!said.contains("line 6"));
+        assert!(matches!(result, Err(RloxError::Exit(0))));
+    }
+}
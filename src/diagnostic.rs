@@ -0,0 +1,191 @@
+use crate::ast_json::string;
+use crate::error::RloxError;
+use crate::error_codes::code_of;
+
+// how serious a diagnostic is, which decides the color it is shown in on a terminal
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+
+    // `text` in this severity's color, red, yellow or blue, as ANSI escapes
+    pub fn paint(self, text: &str) -> String {
+        let color = match self {
+            Severity::Error => "31",
+            Severity::Warning => "33",
+            Severity::Note => "34",
+        };
+        format!("\x1b[{}m{}\x1b[0m", color, text)
+    }
+}
+
+/**
+ * The error as rlox reports it, headed by its code, e.g. `error[E0203]: ...`, and in color when
+ *   `color` is set: the heading in red, as an error; the gutter locating a syntax error and each
+ *   frame of a stack trace in blue, as notes; and the carets under the fault in red again.
+ */
+pub fn render(error: &RloxError, color: bool) -> String {
+    let text = match error {
+        RloxError::SyntaxError(e) => e.to_string(),
+        RloxError::RuntimeError(e) => e.to_string(),
+        RloxError::IoError(e) => format!("cannot read script: {}", e),
+        RloxError::Exit(_) => error.to_string(),
+    };
+    let label = match code_of(error) {
+        Some(code) => format!("error[{}]", code.code),
+        None => "error".to_string(),
+    };
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| match (i, color) {
+            (0, false) => format!("{}: {}", label, line),
+            (0, true) => Severity::Error.paint(&format!("{}: {}", label, line)),
+            (_, false) => line.to_string(),
+            (_, true) => paint_context(line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/**
+ * The error as one line of JSON, for tools rather than people to read:
+ *   {"code":"E0201","severity":"error","message":"at ')': Expect expression.","file":"s.lox",
+ *    "span":{"line":1,"column":12,"length":1},"notes":[]}
+ *   `code` and `file` are null when unknown, and `span` when the error is not in a script, or
+ *   its `column` when only the line is known. A runtime error's notes are its stack trace.
+ */
+pub fn to_json(error: &RloxError) -> String {
+    let (message, file, span, notes) = match error {
+        RloxError::SyntaxError(e) => (
+            e.description().to_string(),
+            e.file(),
+            Some((e.line_number(), e.column(), e.length())),
+            Vec::new(),
+        ),
+        RloxError::RuntimeError(e) => (
+            e.message().to_string(),
+            e.file(),
+            Some((
+                e.line_number(),
+                e.token().column(),
+                e.token().lexeme().chars().count(),
+            )),
+            e.trace()
+                .iter()
+                .map(|(function, line)| format!("[line {}] in {}", line, function))
+                .collect(),
+        ),
+        RloxError::IoError(e) => (format!("cannot read script: {}", e), None, None, Vec::new()),
+        RloxError::Exit(_) => (error.to_string(), None, None, Vec::new()),
+    };
+    let or_null = |value: Option<String>| value.unwrap_or_else(|| "null".to_string());
+    let span = span.map(|(line, column, length)| {
+        let column = Some(column)
+            .filter(|column| *column > 0)
+            .map(|c| c.to_string());
+        format!(
+            "{{\"line\":{},\"column\":{},\"length\":{}}}",
+            line,
+            or_null(column),
+            length
+        )
+    });
+    let notes: Vec<_> = notes.iter().map(|note| string(note)).collect();
+    format!(
+        "{{\"code\":{},\"severity\":{},\"message\":{},\"file\":{},\"span\":{},\"notes\":[{}]}}",
+        or_null(code_of(error).map(|code| string(code.code))),
+        string(Severity::Error.name()),
+        string(&message),
+        or_null(file.map(string)),
+        or_null(span),
+        notes.join(",")
+    )
+}
+
+// a line after the first: part of a snippet of source, or a frame of a stack trace
+fn paint_context(line: &str) -> String {
+    if line.trim_start().starts_with("-->") {
+        return Severity::Note.paint(line);
+    }
+    let bar = match line.find('|') {
+        Some(bar) if line[..bar].chars().all(|c| c == ' ' || c.is_ascii_digit()) => bar,
+        _ => return Severity::Note.paint(line),
+    };
+    let (gutter, rest) = line.split_at(bar + 1);
+    // the carets' line is the only one after the header with no number in its gutter
+    let carets = gutter.trim() == "|" && !rest.trim().is_empty();
+    match carets {
+        true => format!(
+            "{}{}",
+            Severity::Note.paint(gutter),
+            Severity::Error.paint(rest)
+        ),
+        false => format!("{}{}", Severity::Note.paint(gutter), rest),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, to_json};
+    use crate::interpreter::Interpreter;
+    use crate::parse_program;
+    use crate::resolver::resolve;
+
+    #[test]
+    fn errors_are_headed_by_their_code_red_and_their_context_blue() {
+        let source = "print (1 + );";
+        let error = parse_program(source).diagnostics.remove(0);
+        let error = error.in_file("s.lox", source);
+        assert_eq!(
+            render(&error, false),
+            "error[E0201]: at ')': Expect expression.\n \
+             --> s.lox:1:12\n  \
+             |\n\
+             1 | print (1 + );\n  \
+             |            ^"
+        );
+        assert_eq!(
+            render(&error, true),
+            "\x1b[31merror[E0201]: at ')': Expect expression.\x1b[0m\n\
+             \x1b[34m --> s.lox:1:12\x1b[0m\n\
+             \x1b[34m  |\x1b[0m\n\
+             \x1b[34m1 |\x1b[0m print (1 + );\n\
+             \x1b[34m  |\x1b[0m\x1b[31m            ^\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn errors_as_json_carry_their_code_span_and_trace() {
+        let source = "print (1 + );";
+        let error = parse_program(source).diagnostics.remove(0);
+        assert_eq!(
+            to_json(&error.in_file("s.lox", source)),
+            "{\"code\":\"E0201\",\"severity\":\"error\",\"message\":\"at ')': Expect expression.\",\
+             \"file\":\"s.lox\",\"span\":{\"line\":1,\"column\":12,\"length\":1},\"notes\":[]}"
+        );
+
+        let source = "fun f() {\n  return -\"a\";\n}\nf();";
+        let program = parse_program(source).ast;
+        let locals = resolve(&program).ok().unwrap();
+        let error = Interpreter::default()
+            .interpret(&program, locals)
+            .unwrap_err();
+        assert_eq!(
+            to_json(&error),
+            "{\"code\":\"E0501\",\"severity\":\"error\",\"message\":\"Operand must be a number.\",\
+             \"file\":null,\"span\":{\"line\":2,\"column\":10,\"length\":1},\
+             \"notes\":[\"[line 2] in f()\",\"[line 4] in script\"]}"
+        );
+    }
+}
@@ -0,0 +1,310 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::{RloxError, RloxRuntimeError};
+use crate::error_codes::{ErrorCode, UNDEFINED_VARIABLE, UNINITIALIZED_VARIABLE};
+use crate::interpreter::Value;
+use crate::token::Token;
+
+/**
+ * The variables declared in one scope. The globals, which have no enclosing scope, are kept by
+ *   name, since a global may be referenced before it is declared. A local scope's variables are
+ *   kept in the slots the resolver numbered them with, in the order they are declared, and are
+ *   read by the scope distance and slot the resolver found for each reference. Their names are
+ *   only kept, for a debugger to show, in scopes enclosed by globals that are `named`.
+ */
+#[derive(Default)]
+pub struct Environment {
+    // None for a variable declared without a value, until it is assigned
+    values: HashMap<String, Option<Value>>,
+    slots: Vec<Option<Value>>,
+    // the name of the local in each slot, when `named`
+    slot_names: Vec<String>,
+    named: bool,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new(enclosing: Rc<RefCell<Environment>>) -> Self {
+        let named = enclosing.borrow().named;
+        Environment {
+            values: HashMap::new(),
+            slots: Vec::new(),
+            slot_names: Vec::new(),
+            named,
+            enclosing: Some(enclosing),
+        }
+    }
+
+    // `var` may redeclare an existing global; the new value simply replaces the old one
+    pub fn define(&mut self, name: &str, value: Value) {
+        match self.enclosing {
+            Some(_) => {
+                self.slots.push(Some(value));
+                self.name_slot(name);
+            }
+            None => {
+                self.values.insert(name.to_string(), Some(value));
+            }
+        }
+    }
+
+    // a variable that is a runtime error to read until it is first assigned
+    pub fn declare(&mut self, name: &str) {
+        match self.enclosing {
+            Some(_) => {
+                self.slots.push(None);
+                self.name_slot(name);
+            }
+            None => {
+                self.values.insert(name.to_string(), None);
+            }
+        }
+    }
+
+    // makes the scopes this one encloses from now on keep the names of their locals
+    pub fn name_locals(&mut self) {
+        self.named = true;
+    }
+
+    fn name_slot(&mut self, name: &str) {
+        if self.named {
+            self.slot_names.push(name.to_string());
+        }
+    }
+
+    pub fn enclosing(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.enclosing.clone()
+    }
+
+    /**
+     * The variables in this scope with their values, None for one not yet assigned: the globals
+     *   in sorted order, or a local scope's in the order they were declared, if it is `named`.
+     */
+    pub fn variables(&self) -> Vec<(String, Option<Value>)> {
+        match self.enclosing {
+            Some(_) => self
+                .slot_names
+                .iter()
+                .cloned()
+                .zip(self.slots.iter().cloned())
+                .collect(),
+            None => {
+                let mut variables = self
+                    .values
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect::<Vec<_>>();
+                variables.sort_by(|a, b| a.0.cmp(&b.0));
+                variables
+            }
+        }
+    }
+
+    // a global, by name
+    pub fn get(&self, name: &Token) -> Result<Value, RloxError> {
+        match (self.values.get(name.lexeme()), &self.enclosing) {
+            (Some(Some(value)), _) => Ok(value.clone()),
+            (Some(None), _) => Err(error(
+                name,
+                &UNINITIALIZED_VARIABLE,
+                "Uninitialized variable",
+            )),
+            (None, Some(enclosing)) => enclosing.borrow().get(name),
+            (None, None) => Err(error(name, &UNDEFINED_VARIABLE, "Undefined variable")),
+        }
+    }
+
+    // the names kept in this scope by name, which only a global scope has
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(|name| name.as_str())
+    }
+
+    // the local `depth` scopes out from this one, in `slot`; `name` is for the error messages
+    pub fn get_at(&self, depth: usize, slot: usize, name: &Token) -> Result<Value, RloxError> {
+        match (depth, &self.enclosing) {
+            (0, _) => match self.slots.get(slot) {
+                Some(Some(value)) => Ok(value.clone()),
+                Some(None) => Err(error(
+                    name,
+                    &UNINITIALIZED_VARIABLE,
+                    "Uninitialized variable",
+                )),
+                None => Err(error(name, &UNDEFINED_VARIABLE, "Undefined variable")),
+            },
+            (_, Some(enclosing)) => enclosing.borrow().get_at(depth - 1, slot, name),
+            (_, None) => Err(error(name, &UNDEFINED_VARIABLE, "Undefined variable")),
+        }
+    }
+
+    // unlike `define`, assignment never creates a variable
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RloxError> {
+        match (self.values.get_mut(name.lexeme()), &self.enclosing) {
+            (Some(slot), _) => {
+                *slot = Some(value);
+                Ok(())
+            }
+            (None, Some(enclosing)) => enclosing.borrow_mut().assign(name, value),
+            (None, None) => Err(error(name, &UNDEFINED_VARIABLE, "Undefined variable")),
+        }
+    }
+
+    pub fn assign_at(
+        &mut self,
+        depth: usize,
+        slot: usize,
+        name: &Token,
+        value: Value,
+    ) -> Result<(), RloxError> {
+        match (depth, &self.enclosing) {
+            (0, _) => match self.slots.get_mut(slot) {
+                Some(variable) => {
+                    *variable = Some(value);
+                    Ok(())
+                }
+                None => Err(error(name, &UNDEFINED_VARIABLE, "Undefined variable")),
+            },
+            (_, Some(enclosing)) => enclosing
+                .borrow_mut()
+                .assign_at(depth - 1, slot, name, value),
+            (_, None) => Err(error(name, &UNDEFINED_VARIABLE, "Undefined variable")),
+        }
+    }
+}
+
+fn error(name: &Token, code: &'static ErrorCode, problem: &str) -> RloxError {
+    RloxError::RuntimeError(Box::new(RloxRuntimeError {
+        code,
+        token: name.clone(),
+        message: format!("{} '{}'.", problem, name.lexeme()),
+        calls: Vec::new(),
+        file: None,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::Environment;
+    use crate::error::RloxError;
+    use crate::interpreter::Value;
+    use crate::token::{Token, TokenType};
+
+    fn name(lexeme: &str) -> Token {
+        Token::new(TokenType::Identifier, lexeme.to_string(), None, 7).unwrap()
+    }
+
+    #[test]
+    fn define_get_and_assign() {
+        let mut environment = Environment::default();
+        environment.define("a", Value::Number(1.0));
+        assert_eq!(environment.get(&name("a")).unwrap(), Value::Number(1.0));
+        environment.assign(&name("a"), Value::Nil).unwrap();
+        assert_eq!(environment.get(&name("a")).unwrap(), Value::Nil);
+        environment.define("a", Value::Bool(true));
+        assert_eq!(environment.get(&name("a")).unwrap(), Value::Bool(true));
+    }
+
+    #[test]
+    fn inner_scopes_shadow_read_and_assign_outer_variables() {
+        let globals = Rc::new(RefCell::new(Environment::default()));
+        globals.borrow_mut().define("a", Value::Number(1.0));
+        globals.borrow_mut().define("b", Value::Number(2.0));
+        let mut block = Environment::new(globals.clone());
+        block.define("a", Value::Nil);
+        assert_eq!(block.get_at(0, 0, &name("a")).unwrap(), Value::Nil);
+        // globals are found by name from any scope
+        assert_eq!(block.get(&name("a")).unwrap(), Value::Number(1.0));
+        assert_eq!(block.get(&name("b")).unwrap(), Value::Number(2.0));
+        block.assign(&name("b"), Value::Bool(true)).unwrap();
+        assert_eq!(globals.borrow().get(&name("b")).unwrap(), Value::Bool(true));
+        assert!(block.get(&name("c")).is_err());
+    }
+
+    #[test]
+    fn locals_are_read_and_assigned_by_depth_and_slot() {
+        let globals = Rc::new(RefCell::new(Environment::default()));
+        let outer = Rc::new(RefCell::new(Environment::new(globals)));
+        outer.borrow_mut().define("a", Value::Number(1.0));
+        outer.borrow_mut().declare("b");
+        let mut inner = Environment::new(outer.clone());
+        inner.define("a", Value::Number(2.0));
+        assert_eq!(inner.get_at(0, 0, &name("a")).unwrap(), Value::Number(2.0));
+        assert_eq!(inner.get_at(1, 0, &name("a")).unwrap(), Value::Number(1.0));
+        match inner.get_at(1, 1, &name("b")) {
+            Err(RloxError::RuntimeError(e)) => {
+                assert_eq!(e.message(), "Uninitialized variable 'b'.")
+            }
+            _ => panic!("expected a runtime error"),
+        }
+        inner.assign_at(1, 1, &name("b"), Value::Nil).unwrap();
+        assert_eq!(outer.borrow().get_at(0, 1, &name("b")).unwrap(), Value::Nil);
+        assert!(inner.get_at(3, 0, &name("a")).is_err());
+    }
+
+    #[test]
+    fn named_scopes_list_their_variables() {
+        let globals = Rc::new(RefCell::new(Environment::default()));
+        globals.borrow_mut().define("b", Value::Nil);
+        globals.borrow_mut().define("a", Value::Number(1.0));
+        let mut unnamed = Environment::new(globals.clone());
+        unnamed.define("x", Value::Nil);
+        assert!(unnamed.variables().is_empty());
+        globals.borrow_mut().name_locals();
+        let mut block = Environment::new(globals.clone());
+        block.define("y", Value::Bool(true));
+        block.declare("x");
+        assert_eq!(
+            block.variables(),
+            vec![
+                ("y".to_string(), Some(Value::Bool(true))),
+                ("x".to_string(), None)
+            ]
+        );
+        let enclosing = block.enclosing().unwrap();
+        assert_eq!(
+            enclosing.borrow().variables(),
+            vec![
+                ("a".to_string(), Some(Value::Number(1.0))),
+                ("b".to_string(), Some(Value::Nil))
+            ]
+        );
+    }
+
+    #[test]
+    fn declared_variables_are_errors_to_read_until_assigned() {
+        let mut environment = Environment::default();
+        environment.declare("a");
+        match environment.get(&name("a")) {
+            Err(RloxError::RuntimeError(e)) => {
+                assert_eq!(e.message(), "Uninitialized variable 'a'.")
+            }
+            _ => panic!("expected a runtime error"),
+        }
+        environment.assign(&name("a"), Value::Nil).unwrap();
+        assert_eq!(environment.get(&name("a")).unwrap(), Value::Nil);
+    }
+
+    #[test]
+    fn unknown_names_are_runtime_errors() {
+        let mut environment = Environment::default();
+        let results = [
+            environment.get(&name("x")).map(|_| ()),
+            environment.assign(&name("x"), Value::Nil),
+        ];
+        for result in results.iter() {
+            match result {
+                Err(RloxError::RuntimeError(e)) => {
+                    assert_eq!(e.message(), "Undefined variable 'x'.");
+                    assert_eq!(e.line_number(), 7);
+                }
+                _ => panic!("expected a runtime error"),
+            }
+        }
+        // a failed assignment must not have created the variable
+        assert!(environment.get(&name("x")).is_err());
+    }
+}
@@ -1,24 +1,226 @@
 use std::fmt::{self, Display, Formatter};
 
+use crate::error_codes::ErrorCode;
+use crate::token::Token;
+
 #[derive(Debug)]
 pub enum RloxError {
     IoError(std::io::Error),
     SyntaxError(RloxSyntaxError),
+    RuntimeError(Box<RloxRuntimeError>),
+    // `exit(code)` unwinding out of the program, which should end with `code` as its exit status
+    Exit(i32),
 }
 
 #[derive(Debug)]
 pub struct RloxSyntaxError {
+    pub(crate) code: &'static ErrorCode,
     pub(crate) line_number: usize,
+    // the character the error starts at on its line, counting from 1; 0 when it is not known
+    pub(crate) column: usize,
+    // how many characters from `column` are at fault
+    pub(crate) length: usize,
     pub(crate) description: String,
+    // the file the error was found in and the text of its line, once `in_file` has said
+    pub(crate) origin: Option<(String, String)>,
+}
+
+impl RloxSyntaxError {
+    // an error at `token`, for the parser and resolver, which find errors in whole tokens
+    pub(crate) fn at(token: &Token, code: &'static ErrorCode, description: String) -> Self {
+        // a string spanning lines is underlined on the line it starts on
+        let first_line = token.lexeme().split('\n').next().unwrap_or("");
+        RloxSyntaxError {
+            code,
+            line_number: *token.line_number(),
+            column: token.column(),
+            length: first_line.chars().count().max(1),
+            description,
+            origin: None,
+        }
+    }
+
+    pub fn code(&self) -> &'static ErrorCode {
+        self.code
+    }
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+    pub fn column(&self) -> usize {
+        self.column
+    }
+    pub fn length(&self) -> usize {
+        self.length
+    }
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+    pub fn file(&self) -> Option<&str> {
+        self.origin.as_ref().map(|(file, _)| file.as_str())
+    }
+
+    // the error as found in `file`, whose text is `source`, so that it is shown with its line
+    pub fn in_file(mut self, file: &str, source: &str) -> Self {
+        let line = source.lines().nth(self.line_number.saturating_sub(1));
+        self.origin = Some((file.to_string(), line.unwrap_or("").to_string()));
+        self
+    }
 }
 
+/**
+ * Where the error is and what it is; once it is known which file it is in, under a header naming
+ *   the file, with the line it is on and `^`s under the characters at fault, as wide as a terminal
+ *   shows them, so that they line up under CJK text too:
+ *   at ')': Expect expression.
+ *    --> script.lox:3:12
+ *     |
+ *   3 | print (1 + );
+ *     |            ^
+ */
 impl Display for RloxSyntaxError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (file, text) = match &self.origin {
+            Some(origin) => origin,
+            None if self.column == 0 => {
+                return write!(f, "line {}: {}", self.line_number, self.description)
+            }
+            None => {
+                return write!(
+                    f,
+                    "line {}, column {}: {}",
+                    self.line_number, self.column, self.description
+                )
+            }
+        };
+        let gutter = " ".repeat(self.line_number.to_string().len());
         write!(
             f,
-            "rlox syntax error: \nline_number: {}\n, description: {}\n",
-            self.line_number, self.description
-        )
+            "{}\n{}--> {}:{}",
+            self.description, gutter, file, self.line_number
+        )?;
+        if self.column > 0 {
+            write!(f, ":{}", self.column)?;
+        }
+        write!(f, "\n{} |\n{} | {}", gutter, self.line_number, text)?;
+        if self.column > 0 {
+            // tabs are kept so that the carets line up however wide the terminal shows them
+            let indent = text
+                .chars()
+                .take(self.column - 1)
+                .map(|c| match c {
+                    '\t' => "\t".to_string(),
+                    _ => " ".repeat(display_width(c)),
+                })
+                .collect::<String>();
+            // a span running past the end of the line gets one caret for each character beyond it
+            let mut at_fault = text.chars().skip(self.column - 1);
+            let carets = (0..self.length.max(1))
+                .map(|_| at_fault.next().map_or(1, display_width))
+                .sum::<usize>();
+            write!(f, "\n{} | {}{}", gutter, indent, "^".repeat(carets.max(1)))?;
+        }
+        Ok(())
+    }
+}
+
+// how many columns a terminal gives `c`: two for the East Asian wide and fullwidth characters,
+//   none for combining marks and zero-width characters, and one for the rest
+fn display_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/**
+ * An error raised while evaluating, e.g. adding a number to a string. It carries the token the
+ *   evaluator was working on, usually an operator, so the report can point at the right line.
+ */
+#[derive(Debug)]
+pub struct RloxRuntimeError {
+    pub(crate) code: &'static ErrorCode,
+    pub(crate) token: Token,
+    pub(crate) message: String,
+    // each call the error unwound out of, innermost first: what was called, and the call's line
+    pub(crate) calls: Vec<(String, usize)>,
+    // the script the error is in, once it is known
+    pub(crate) file: Option<String>,
+}
+
+impl RloxRuntimeError {
+    pub fn code(&self) -> &'static ErrorCode {
+        self.code
+    }
+    pub fn token(&self) -> &Token {
+        &self.token
+    }
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+    pub fn line_number(&self) -> usize {
+        *self.token.line_number()
+    }
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    /**
+     * The frames the error unwound out of, innermost first: each call's function and the line
+     *   that was running in it, ending with the top-level script. Empty when the error was not
+     *   inside any call.
+     */
+    pub fn trace(&self) -> Vec<(&str, usize)> {
+        if self.calls.is_empty() {
+            return Vec::new();
+        }
+        let functions = self.calls.iter().map(|(function, _)| function.as_str());
+        let lines =
+            std::iter::once(self.line_number()).chain(self.calls.iter().map(|(_, line)| *line));
+        functions
+            .chain(std::iter::once("script"))
+            .zip(lines)
+            .collect()
+    }
+}
+
+// an error inside a call is followed by its trace, e.g. `[line 4] in fib()`, out to `in script`
+impl Display for RloxRuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        match self.trace().as_slice() {
+            [] => write!(f, "\n[line {}]", self.line_number()),
+            trace => trace
+                .iter()
+                .try_for_each(|(function, line)| write!(f, "\n[line {}] in {}", line, function)),
+        }
+    }
+}
+
+impl RloxError {
+    // a syntax or runtime error as found in `file`, whose text is `source`; any other as it is
+    pub fn in_file(self, file: &str, source: &str) -> Self {
+        match self {
+            RloxError::SyntaxError(e) => RloxError::SyntaxError(e.in_file(file, source)),
+            RloxError::RuntimeError(mut e) => {
+                e.file = Some(file.to_string());
+                RloxError::RuntimeError(e)
+            }
+            e => e,
+        }
     }
 }
 
@@ -34,12 +236,78 @@ impl From<RloxSyntaxError> for RloxError {
     }
 }
 
+impl From<RloxRuntimeError> for RloxError {
+    fn from(e: RloxRuntimeError) -> Self {
+        Self::RuntimeError(Box::new(e))
+    }
+}
+
 impl Display for RloxError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         use RloxError::*;
         match self {
             IoError(e) => write!(f, "error reading script: {}", e),
             SyntaxError(e) => write!(f, "Syntax error: {}", e),
+            RuntimeError(e) => write!(f, "{}", e),
+            Exit(code) => write!(f, "exit({})", code),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::error::RloxError;
+    use crate::parse_program;
+
+    fn syntax_error(source: &str) -> RloxError {
+        parse_program(source).diagnostics.remove(0)
+    }
+
+    #[test]
+    fn syntax_errors_underline_the_token_at_fault_once_their_file_is_known() {
+        let source = "var a = 1;\n\tprint (a + );\n";
+        assert_eq!(
+            syntax_error(source).to_string(),
+            "Syntax error: line 2, column 13: at ')': Expect expression."
+        );
+        assert_eq!(
+            syntax_error(source).in_file("script.lox", source).to_string(),
+            "Syntax error: at ')': Expect expression.\n \
+             --> script.lox:2:13\n  \
+             |\n\
+             2 | \tprint (a + );\n  \
+             | \t           ^"
+        );
+        assert_eq!(
+            syntax_error("print \"abc").in_file("s.lox", "print \"abc").to_string(),
+            "Syntax error: Unterminated string.\n --> s.lox:1:7\n  |\n1 | print \"abc\n  |       ^^^^"
+        );
+    }
+
+    #[test]
+    fn strings_spanning_lines_are_underlined_where_they_start() {
+        let source = "var \"a\nb\";";
+        assert_eq!(
+            syntax_error(source).in_file("s.lox", source).to_string(),
+            "Syntax error: at '\"a\nb\"': Expect variable name.\n --> s.lox:1:5\n  |\n\
+             1 | var \"a\n  |     ^^"
+        );
+    }
+
+    #[test]
+    fn carets_line_up_under_wide_characters() {
+        let source = "print \"世界\" + ;";
+        assert_eq!(
+            syntax_error(source).in_file("s.lox", source).to_string(),
+            "Syntax error: at ';': Expect expression.\n --> s.lox:1:14\n  |\n\
+             1 | print \"世界\" + ;\n  \
+             |                ^"
+        );
+        // the quote takes one caret and the wide character two
+        let source = "print \"界";
+        assert!(syntax_error(source)
+            .in_file("s.lox", source)
+            .to_string()
+            .ends_with("\n  |       ^^^"));
+    }
+}
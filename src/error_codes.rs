@@ -0,0 +1,638 @@
+use crate::error::RloxError;
+
+/**
+ * A kind of diagnostic, under a code that stays the same from release to release so that it can
+ *   be searched for and explained. The codes are grouped by what they are about: E00 reading
+ *   scripts, E01 scanning and preprocessing, E02 parsing, E03 variables, E04 statements and
+ *   classes used where they cannot be, and E05 what goes wrong running a program. Each error is
+ *   given its code where it is made.
+ */
+#[derive(Debug, PartialEq)]
+pub struct ErrorCode {
+    pub code: &'static str,
+    pub title: &'static str,
+    // a longer description, with an example of code that has the error and how to fix it
+    pub explanation: &'static str,
+}
+
+pub static CANNOT_READ_SCRIPT: ErrorCode = ErrorCode {
+    code: "E0001",
+    title: "cannot read script",
+    explanation: "The script could not be read: the file does not exist, is not readable, is not
+valid UTF-8, or is larger than the limit for scripts.
+
+Check the path, which is relative to the working directory, and the file's permissions.",
+};
+
+pub static UNTERMINATED_STRING: ErrorCode = ErrorCode {
+    code: "E0101",
+    title: "unterminated string",
+    explanation:
+        "A string literal was opened with `\"` but the script ended before the `\"` that closes it.
+
+    print \"hello;
+
+Strings may run over several lines, so the error is only found at the end of the script; it is
+reported where the string starts. Close the string:
+
+    print \"hello\";",
+};
+
+pub static UNEXPECTED_CHARACTER: ErrorCode = ErrorCode {
+    code: "E0102",
+    title: "unexpected character",
+    explanation: "The script contains a character that is not part of any Lox token.
+
+    var a = 1 @ 2;
+
+Lox has no `@`, `#` outside of preprocessor directives, `$`, `|`, `&` and so on. Logical and
+and or are written as the keywords `and` and `or`:
+
+    var a = 1 and 2;",
+};
+
+pub static INVALID_NUMBER_LITERAL: ErrorCode = ErrorCode {
+    code: "E0103",
+    title: "invalid number literal",
+    explanation: "A number literal could not be read as a number.
+
+Number literals are digits with an optional fractional part, like `12` or `3.25`; a leading or
+trailing `.`, as in `.5` or `5.`, is not part of the number. Write `0.5` or `5.0` instead.",
+};
+
+pub static UNKNOWN_DIRECTIVE: ErrorCode = ErrorCode {
+    code: "E0104",
+    title: "unknown preprocessor directive",
+    explanation: "A line starts with `#` followed by something that is not a directive.
+
+    #ifdef DEBUG
+
+The directives are `#if NAME`, `#else` and `#end`:
+
+    #if DEBUG",
+};
+
+pub static UNBALANCED_CONDITIONAL: ErrorCode = ErrorCode {
+    code: "E0105",
+    title: "unbalanced preprocessor conditional",
+    explanation:
+        "An `#else` or `#end` has no `#if` before it, or an `#if` is never closed by an `#end`.
+
+    #if DEBUG
+    print \"debugging\";
+
+Close every `#if` with an `#end`:
+
+    #if DEBUG
+    print \"debugging\";
+    #end",
+};
+
+pub static MALFORMED_IF: ErrorCode = ErrorCode {
+    code: "E0106",
+    title: "malformed `#if`",
+    explanation: "An `#if` must be followed by exactly one name, the definition it tests.
+
+    #if DEBUG VERBOSE
+
+Nest the conditions instead:
+
+    #if DEBUG
+    #if VERBOSE
+    ...
+    #end
+    #end",
+};
+
+pub static EXPECTED_EXPRESSION: ErrorCode = ErrorCode {
+    code: "E0201",
+    title: "expected expression",
+    explanation: "An expression was expected, but something else was found.
+
+    print (1 + );
+
+An operator is missing an operand, or a statement is missing its value. Complete it:
+
+    print (1 + 2);",
+};
+
+pub static MISSING_PUNCTUATION: ErrorCode = ErrorCode {
+    code: "E0202",
+    title: "missing punctuation",
+    explanation:
+        "A piece of punctuation the grammar requires, most often a `;`, `)` or `}`, is missing.
+
+    print \"hello\"
+
+The message says what was expected and after what. Add it:
+
+    print \"hello\";",
+};
+
+pub static INVALID_ASSIGNMENT_TARGET: ErrorCode = ErrorCode {
+    code: "E0203",
+    title: "invalid assignment target",
+    explanation: "The left-hand side of `=` is not something that can be assigned to.
+
+    var a = 1;
+    a + 1 = 3;
+
+Only a variable, like `a`, or an instance's field, like `point.x`, can be assigned. Assign to
+the variable itself:
+
+    a = 3 - 1;",
+};
+
+pub static EXPECTED_NAME: ErrorCode = ErrorCode {
+    code: "E0204",
+    title: "expected name",
+    explanation:
+        "A name was expected, for a declaration or after `.`, but something else was found.
+
+    var 1 = 2;
+    fun (a) { return a; }
+
+Names start with a letter or `_`. Give the declaration a name:
+
+    var one = 2;
+    fun identity(a) { return a; }",
+};
+
+pub static INPUT_AFTER_EXPRESSION: ErrorCode = ErrorCode {
+    code: "E0205",
+    title: "unexpected input after expression",
+    explanation: "A single expression was parsed, but more input followed it.
+
+This is reported where only an expression is expected, like the REPL's bare expressions. End
+the expression, or write the input as statements each ending in `;`.",
+};
+
+pub static TOO_MANY_ARGUMENTS: ErrorCode = ErrorCode {
+    code: "E0206",
+    title: "too many arguments or parameters",
+    explanation: "A function was declared with, or called with, more than 255 of them.
+
+Pass related values together instead, say as the fields of an instance.",
+};
+
+pub static NESTING_TOO_DEEP: ErrorCode = ErrorCode {
+    code: "E0207",
+    title: "nesting too deep",
+    explanation:
+        "Expressions or statements are nested inside each other more deeply than the parser allows.
+
+    print ((((((((((1))))))))));  // ...nested hundreds of times
+
+Split the deepest parts out into variables or functions.",
+};
+
+pub static UNDEFINED_VARIABLE: ErrorCode = ErrorCode {
+    code: "E0301",
+    title: "undefined variable",
+    explanation: "A variable was read or assigned that has not been declared.
+
+    print count;
+
+Declare it with `var` first. Assigning to a variable never declares it:
+
+    var count = 0;
+    print count;",
+};
+
+pub static UNINITIALIZED_VARIABLE: ErrorCode = ErrorCode {
+    code: "E0302",
+    title: "uninitialized variable",
+    explanation: "A variable was read before it was given a value.
+
+    var total;
+    print total;
+
+This is only an error when uninitialized variables are made errors; otherwise `var total;`
+makes `total` nil. Assign it first:
+
+    var total = 0;
+    print total;",
+};
+
+pub static READ_IN_OWN_INITIALIZER: ErrorCode = ErrorCode {
+    code: "E0303",
+    title: "local variable read in its own initializer",
+    explanation: "A local variable's initializer refers to the variable being declared.
+
+    var a = 1;
+    {
+      var a = a + 1;
+    }
+
+Inside the block, `a` already means the new variable, which has no value yet. Use another name:
+
+    var a = 1;
+    {
+      var b = a + 1;
+    }",
+};
+
+pub static ALREADY_DECLARED: ErrorCode = ErrorCode {
+    code: "E0304",
+    title: "variable already declared in this scope",
+    explanation: "A local variable was declared twice in the same scope.
+
+    fun f() {
+      var a = 1;
+      var a = 2;
+    }
+
+Assign the existing variable instead, or declare the second in a block of its own:
+
+    fun f() {
+      var a = 1;
+      a = 2;
+    }",
+};
+
+pub static RETURN_OUTSIDE_FUNCTION: ErrorCode = ErrorCode {
+    code: "E0401",
+    title: "return outside of a function",
+    explanation: "A `return` statement appears in top-level code, outside of any function.
+
+    return 1;
+
+Only functions and methods can return. Top-level code can end the script with `exit(code)`.",
+};
+
+pub static RETURN_FROM_INITIALIZER: ErrorCode = ErrorCode {
+    code: "E0402",
+    title: "value returned from an initializer",
+    explanation: "A class's `init` method returns a value.
+
+    class Point {
+      init(x) { this.x = x; return this.x; }
+    }
+
+Calling a class always returns the new instance, so `init` may only `return;` on its own:
+
+    class Point {
+      init(x) { this.x = x; }
+    }",
+};
+
+pub static THIS_OUTSIDE_CLASS: ErrorCode = ErrorCode {
+    code: "E0403",
+    title: "`this` outside of a class",
+    explanation: "`this` is used outside of any method.
+
+    fun area() { return this.width * this.height; }
+
+`this` is the instance a method was called on, so there is none outside a class. Make the
+function a method:
+
+    class Rectangle {
+      area() { return this.width * this.height; }
+    }",
+};
+
+pub static SUPER_OUTSIDE_CLASS: ErrorCode = ErrorCode {
+    code: "E0404",
+    title: "`super` outside of a class",
+    explanation: "`super` is used outside of any method.
+
+    fun f() { return super.f(); }
+
+`super` looks methods up in the superclass of the class a method belongs to. Call a method of
+an instance, or of the class, directly instead.",
+};
+
+pub static SUPER_WITHOUT_SUPERCLASS: ErrorCode = ErrorCode {
+    code: "E0405",
+    title: "`super` in a class with no superclass",
+    explanation: "`super` is used in a class that does not inherit from another.
+
+    class A {
+      f() { return super.f(); }
+    }
+
+Give the class a superclass with `<`:
+
+    class A < Base {
+      f() { return super.f(); }
+    }",
+};
+
+pub static INHERITS_FROM_ITSELF: ErrorCode = ErrorCode {
+    code: "E0406",
+    title: "class inherits from itself",
+    explanation: "A class names itself as its own superclass.
+
+    class A < A {}
+
+Inherit from a different class, or from none:
+
+    class A {}",
+};
+
+pub static SUPERCLASS_NOT_A_CLASS: ErrorCode = ErrorCode {
+    code: "E0407",
+    title: "superclass is not a class",
+    explanation: "The value after `<` in a class declaration is not a class.
+
+    var Base = \"base\";
+    class A < Base {}
+
+Inherit from a class:
+
+    class Base {}
+    class A < Base {}",
+};
+
+pub static JUMP_OUTSIDE_LOOP: ErrorCode = ErrorCode {
+    code: "E0408",
+    title: "`break` or `continue` outside of a loop",
+    explanation: "A `break` or `continue` statement is not inside a `while` or `for` loop.
+
+    if (done) break;
+
+They only end or continue the innermost loop, inside the same function. Put the statement in a
+loop, or use `return` to leave a function early.",
+};
+
+pub static OPERAND_NOT_A_NUMBER: ErrorCode = ErrorCode {
+    code: "E0501",
+    title: "operand must be a number",
+    explanation: "An arithmetic or comparison operator was given something other than a number.
+
+    print -\"one\";
+    print \"a\" < \"b\";
+
+`-`, `*`, `/`, `<`, `<=`, `>` and `>=` only work on numbers. Convert the values, or compare
+them with `==` instead.",
+};
+
+pub static INVALID_PLUS_OPERANDS: ErrorCode = ErrorCode {
+    code: "E0502",
+    title: "invalid operands to `+`",
+    explanation: "`+` was given a mix of values it cannot add.
+
+    print \"total: \" + 4;
+
+`+` adds two numbers or joins two strings. Joining a string and a number is only allowed when
+string coercion is turned on, with `string_coercion = true` in `rlox.toml`.",
+};
+
+pub static DIVISION_BY_ZERO: ErrorCode = ErrorCode {
+    code: "E0503",
+    title: "division by zero",
+    explanation: "A number was divided by zero while division by zero is made an error.
+
+    print 1 / 0;
+
+Without `--division-by-zero-error` this is infinity, as in IEEE 754 arithmetic. Check the
+divisor first:
+
+    if (count != 0) print total / count;",
+};
+
+pub static NOT_CALLABLE: ErrorCode = ErrorCode {
+    code: "E0504",
+    title: "value is not callable",
+    explanation: "Something that is not a function or class was called.
+
+    var name = \"lox\";
+    name();
+
+Only functions, methods, natives and classes can be called.",
+};
+
+pub static WRONG_ARGUMENT_COUNT: ErrorCode = ErrorCode {
+    code: "E0505",
+    title: "wrong number of arguments",
+    explanation: "A function was called with more or fewer arguments than it has parameters.
+
+    fun add(a, b) { return a + b; }
+    add(1);
+
+Lox has no default arguments. Pass one argument for each parameter:
+
+    add(1, 2);",
+};
+
+pub static NOT_AN_INSTANCE: ErrorCode = ErrorCode {
+    code: "E0506",
+    title: "property access on a non-instance",
+    explanation: "A property was read or a field set on a value that is not an instance.
+
+    var point = 1;
+    print point.x;
+
+Only instances of classes have properties. Make the value an instance:
+
+    class Point {}
+    var point = Point();
+    point.x = 1;
+    print point.x;",
+};
+
+pub static UNDEFINED_PROPERTY: ErrorCode = ErrorCode {
+    code: "E0507",
+    title: "undefined property",
+    explanation: "An instance has no field or method with the name that was read.
+
+    class Point {}
+    print Point().x;
+
+Set the field first, usually in `init`, or check the name for typos:
+
+    class Point {
+      init() { this.x = 0; }
+    }
+    print Point().x;",
+};
+
+pub static STACK_OVERFLOW: ErrorCode = ErrorCode {
+    code: "E0508",
+    title: "stack overflow",
+    explanation: "Calls nested more deeply than the call depth limit, usually because a recursion never stops.
+
+    fun f(n) { return f(n + 1); }
+    f(0);
+
+Give the recursion a case that returns without calling again. Deep but finite recursion can be
+allowed with `max_call_depth` in `rlox.toml`, or run in constant space with `tail_calls = true`.",
+};
+
+pub static UNSUPPORTED_OPERATION: ErrorCode = ErrorCode {
+    code: "E0509",
+    title: "operation not supported",
+    explanation: "The interpreter met an operator it does not implement. Every operator the parser
+accepts should be implemented, so this is a bug in rlox rather than in the script.
+
+Please report it along with the script that caused it.",
+};
+
+// every code, in order
+pub static ERROR_CODES: [&ErrorCode; 35] = [
+    &CANNOT_READ_SCRIPT,
+    &UNTERMINATED_STRING,
+    &UNEXPECTED_CHARACTER,
+    &INVALID_NUMBER_LITERAL,
+    &UNKNOWN_DIRECTIVE,
+    &UNBALANCED_CONDITIONAL,
+    &MALFORMED_IF,
+    &EXPECTED_EXPRESSION,
+    &MISSING_PUNCTUATION,
+    &INVALID_ASSIGNMENT_TARGET,
+    &EXPECTED_NAME,
+    &INPUT_AFTER_EXPRESSION,
+    &TOO_MANY_ARGUMENTS,
+    &NESTING_TOO_DEEP,
+    &UNDEFINED_VARIABLE,
+    &UNINITIALIZED_VARIABLE,
+    &READ_IN_OWN_INITIALIZER,
+    &ALREADY_DECLARED,
+    &RETURN_OUTSIDE_FUNCTION,
+    &RETURN_FROM_INITIALIZER,
+    &THIS_OUTSIDE_CLASS,
+    &SUPER_OUTSIDE_CLASS,
+    &SUPER_WITHOUT_SUPERCLASS,
+    &INHERITS_FROM_ITSELF,
+    &SUPERCLASS_NOT_A_CLASS,
+    &JUMP_OUTSIDE_LOOP,
+    &OPERAND_NOT_A_NUMBER,
+    &INVALID_PLUS_OPERANDS,
+    &DIVISION_BY_ZERO,
+    &NOT_CALLABLE,
+    &WRONG_ARGUMENT_COUNT,
+    &NOT_AN_INSTANCE,
+    &UNDEFINED_PROPERTY,
+    &STACK_OVERFLOW,
+    &UNSUPPORTED_OPERATION,
+];
+
+// the code of what went wrong in `error`; None for `exit`, which is not an error to report
+pub fn code_of(error: &RloxError) -> Option<&'static ErrorCode> {
+    match error {
+        RloxError::IoError(_) => Some(&CANNOT_READ_SCRIPT),
+        RloxError::SyntaxError(e) => Some(e.code()),
+        RloxError::RuntimeError(e) => Some(e.code()),
+        RloxError::Exit(_) => None,
+    }
+}
+
+// the error with `code`, in either case, e.g. for `rlox --explain e0203`
+pub fn lookup(code: &str) -> Option<&'static ErrorCode> {
+    ERROR_CODES
+        .iter()
+        .copied()
+        .find(|error| error.code.eq_ignore_ascii_case(code))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{code_of, lookup, ERROR_CODES};
+    use crate::error::RloxError;
+    use crate::interpreter::Interpreter;
+    use crate::preprocessor::{preprocess, Defines};
+    use crate::resolver::resolve;
+    use crate::{parse_expression, parse_program};
+
+    // the code of the first error `source` gives, from whichever stage first rejects it
+    fn code(source: &str) -> &'static str {
+        let error = match preprocess(source, &Defines::new()) {
+            Err(e) => e,
+            Ok(source) => first_error(&source),
+        };
+        code_of(&error).unwrap().code
+    }
+
+    fn first_error(source: &str) -> RloxError {
+        let parsed = parse_program(source);
+        if let Some(e) = parsed.diagnostics.into_iter().next() {
+            return e;
+        }
+        let locals = match resolve(&parsed.ast) {
+            Ok(locals) => locals,
+            Err(errors) => return errors.into_iter().next().unwrap(),
+        };
+        Interpreter::default()
+            .with_division_by_zero_errors(true)
+            .with_uninitialized_errors(true)
+            .with_max_call_depth(50)
+            .interpret(&parsed.ast, locals)
+            .unwrap_err()
+    }
+
+    #[test]
+    fn every_error_is_given_its_code() {
+        let cases = [
+            ("print \"abc", "E0101"),
+            ("print 1 @ 2;", "E0102"),
+            ("#define A", "E0104"),
+            ("#if A\nprint 1;", "E0105"),
+            ("#end", "E0105"),
+            ("#if A B\n#end", "E0106"),
+            ("print (1 + );", "E0201"),
+            ("print * 2;", "E0201"),
+            ("print 1", "E0202"),
+            ("var a; a + 1 = 3;", "E0203"),
+            ("var 1 = 2;", "E0204"),
+            ("fun f(a, a) {}", "E0304"),
+            ("{ var a = 1; var a = 2; }", "E0304"),
+            ("var a;\nprint a;", "E0302"),
+            ("print b;", "E0301"),
+            ("{ var a = a; }", "E0303"),
+            ("return 1;", "E0401"),
+            ("class A { init() { return 1; } }", "E0402"),
+            ("print this;", "E0403"),
+            ("print super.a;", "E0404"),
+            ("class A { f() { super.f(); } }", "E0405"),
+            ("class A < A {}", "E0406"),
+            ("var B = 1;\nclass A < B {}", "E0407"),
+            ("break;", "E0408"),
+            ("continue;", "E0408"),
+            ("print -\"a\";", "E0501"),
+            ("print 1 + nil;", "E0502"),
+            ("print 1 / 0;", "E0503"),
+            ("\"a\"();", "E0504"),
+            ("fun f(a) {}\nf();", "E0505"),
+            ("print 1.a;", "E0506"),
+            ("class A {}\nprint A().a;", "E0507"),
+            ("fun f() { f(); }\nf();", "E0508"),
+        ];
+        for (source, expected) in cases.iter() {
+            assert_eq!(code(source), *expected, "for {:?}", source);
+        }
+        let arguments = vec!["1"; 256].join(", ");
+        assert_eq!(code(&format!("print f({});", arguments)), "E0206");
+        assert_eq!(code(&format!("print {}1;", "(".repeat(1000))), "E0207");
+        let parsed = parse_expression("1 2");
+        assert_eq!(code_of(&parsed.diagnostics[0]).unwrap().code, "E0205");
+        let io = RloxError::IoError(std::io::Error::from(std::io::ErrorKind::NotFound));
+        assert_eq!(code_of(&io).unwrap().code, "E0001");
+
+        // number literals are only digits, so always parse, and unsupported operators never
+        // come out of the parser; every other code is given by some source above
+        let given = cases
+            .iter()
+            .map(|(_, code)| *code)
+            .chain(vec!["E0206", "E0207", "E0205", "E0001", "E0103", "E0509"])
+            .collect::<Vec<_>>();
+        for error in ERROR_CODES.iter() {
+            assert!(given.contains(&error.code), "{} is never given", error.code);
+        }
+    }
+
+    #[test]
+    fn codes_are_unique_sorted_and_explained() {
+        for pair in ERROR_CODES.windows(2) {
+            assert!(
+                pair[0].code < pair[1].code,
+                "{} is out of order",
+                pair[1].code
+            );
+        }
+        for error in ERROR_CODES.iter() {
+            assert!(!error.explanation.is_empty());
+        }
+        assert_eq!(lookup("e0203").unwrap().title, "invalid assignment target");
+        assert!(lookup("E9999").is_none());
+    }
+}
@@ -0,0 +1,296 @@
+/**
+ * What a test script says it should do, in the comment format of the Crafting Interpreters test
+ *   suite:
+ *   print 1 + 2; // expect: 3
+ *   print -nil;  // expect runtime error: Operand must be a number.
+ *   print (1 + ); // Error at ')': Expect expression.
+ *   // [line 5] Error at end: Expect '}' after block.
+ *   Each `expect:` is a line of standard output, in order. A runtime error is expected on the
+ *   line of its comment, after which the script exits with 70. A compile error is expected on
+ *   the line of its comment, or the line it names, in any order among the others; the script then
+ *   exits with 65 without running. Errors only the suite's C implementation reports, under
+ *   `[c line N]`, are left out.
+ */
+#[derive(Debug, Default, PartialEq)]
+pub struct Expectations {
+    output: Vec<String>,
+    runtime_error: Option<(String, usize)>,
+    // the line each is on, and its message without the suite's leading `Error`
+    compile_errors: Vec<(usize, String)>,
+}
+
+// what running a script actually did
+pub struct Outcome<'a> {
+    pub stdout: &'a str,
+    pub stderr: &'a str,
+    pub exit_code: Option<i32>,
+}
+
+const EXPECT: &str = "// expect: ";
+const EXPECT_RUNTIME_ERROR: &str = "// expect runtime error: ";
+
+impl Expectations {
+    pub fn parse(source: &str) -> Self {
+        let mut expectations = Expectations::default();
+        for (i, line) in source.lines().enumerate() {
+            if let Some((_, expected)) = split_once(line, EXPECT) {
+                expectations.output.push(expected.to_string());
+            } else if let Some((_, message)) = split_once(line, EXPECT_RUNTIME_ERROR) {
+                expectations.runtime_error = Some((message.to_string(), i + 1));
+            } else if let Some(error) = compile_error(line, i + 1) {
+                expectations.compile_errors.push(error);
+            }
+        }
+        expectations
+    }
+
+    /**
+     * Every way `outcome` differs from what was expected, each described on its own; empty when
+     *   the script passed.
+     */
+    pub fn check(&self, outcome: &Outcome) -> Vec<String> {
+        let mut failures = Vec::new();
+        let actual = outcome.stdout.lines().collect::<Vec<_>>();
+        for i in 0..self.output.len().max(actual.len()) {
+            match (self.output.get(i), actual.get(i)) {
+                (Some(expected), Some(actual)) if expected == actual => {}
+                (Some(expected), Some(actual)) => failures.push(format!(
+                    "output line {}: expected {:?}, got {:?}",
+                    i + 1,
+                    expected,
+                    actual
+                )),
+                (Some(expected), None) => failures.push(format!(
+                    "output line {}: expected {:?}, got nothing",
+                    i + 1,
+                    expected
+                )),
+                (None, Some(actual)) => {
+                    failures.push(format!("output line {}: unexpected {:?}", i + 1, actual))
+                }
+                (None, None) => {}
+            }
+        }
+        let expected_code = match &self.runtime_error {
+            _ if !self.compile_errors.is_empty() => {
+                let mut reported = reported_errors(outcome.stderr);
+                for (line, message) in &self.compile_errors {
+                    let found = reported
+                        .iter()
+                        .position(|r| r == &(*line, message.as_str()));
+                    match found {
+                        Some(at) => {
+                            reported.remove(at);
+                        }
+                        None => failures.push(format!(
+                            "expected compile error {:?} on line {}",
+                            message, line
+                        )),
+                    }
+                }
+                for (line, message) in reported {
+                    failures.push(format!(
+                        "unexpected compile error {:?} on line {}",
+                        message, line
+                    ));
+                }
+                65
+            }
+            Some((message, line)) => {
+                let mut stderr = outcome.stderr.lines();
+                let reported = (stderr.next(), stderr.next());
+                let location = format!("[line {}]", line);
+                match reported {
+                    (Some(actual), Some(at))
+                        if without_label(actual) == message && at.starts_with(&location) => {}
+                    _ => failures.push(format!(
+                        "expected runtime error {:?} at {}, got {:?}",
+                        message,
+                        location,
+                        outcome.stderr.trim_end()
+                    )),
+                }
+                70
+            }
+            None => {
+                if !outcome.stderr.is_empty() {
+                    failures.push(format!(
+                        "unexpected error output {:?}",
+                        outcome.stderr.trim_end()
+                    ));
+                }
+                0
+            }
+        };
+        if outcome.exit_code != Some(expected_code) {
+            failures.push(format!(
+                "expected exit code {}, got {}",
+                expected_code,
+                outcome
+                    .exit_code
+                    .map_or("none".to_string(), |code| code.to_string())
+            ));
+        }
+        failures
+    }
+}
+
+// the compile error a comment on line `line` expects: `// Error ...`, or `// [line N] Error ...`
+//   for one on line N; `[java line N]` counts too, being what the suite expects of jlox
+fn compile_error(line: &str, number: usize) -> Option<(usize, String)> {
+    let (_, comment) = split_once(line, "// ")?;
+    let (number, error) = match comment.strip_prefix('[') {
+        Some(rest) => {
+            let (location, error) = split_once(rest, "] ")?;
+            let location = location.strip_prefix("java ").unwrap_or(location);
+            (location.strip_prefix("line ")?.parse().ok()?, error)
+        }
+        None => (number, comment),
+    };
+    let message = error.strip_prefix("Error")?;
+    let message = message
+        .strip_prefix(": ")
+        .or_else(|| message.strip_prefix(' '))?;
+    Some((number, message.to_string()))
+}
+
+// each error rlox reported with the file it is in, as its line and message; in rlox's own format
+//   a message is followed on the next line by its location, ` --> script.lox:3:12`
+fn reported_errors(stderr: &str) -> Vec<(usize, &str)> {
+    let lines = stderr.lines().collect::<Vec<_>>();
+    lines
+        .windows(2)
+        .filter_map(|pair| {
+            let location = pair[1].trim_start().strip_prefix("--> ")?;
+            let mut parts = location.rsplit(':');
+            let (last, before) = (parts.next()?, parts.next()?);
+            let line = before.parse().or_else(|_| last.parse()).ok()?;
+            Some((line, without_label(pair[0])))
+        })
+        .collect()
+}
+
+// a reported error without the `error[E0501]: ` rlox heads it with, leaving the message alone
+fn without_label(line: &str) -> &str {
+    match line.strip_prefix("error") {
+        Some(rest) if rest.starts_with('[') => split_once(rest, "]: ").map_or(line, |(_, m)| m),
+        Some(rest) => rest.strip_prefix(": ").unwrap_or(line),
+        None => line,
+    }
+}
+
+// the text either side of the first `separator` in `s`
+fn split_once<'a>(s: &'a str, separator: &str) -> Option<(&'a str, &'a str)> {
+    s.find(separator)
+        .map(|at| (&s[..at], &s[at + separator.len()..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expectations, Outcome};
+
+    const SCRIPT: &str = "print 1; // expect: 1\nprint \"a\"; // expect: a\nprint -nil; // expect runtime error: Operand must be a number.\n";
+
+    #[test]
+    fn expectation_comments_are_collected_in_order() {
+        let expectations = Expectations::parse(SCRIPT);
+        assert_eq!(expectations.output, vec!["1", "a"]);
+        assert_eq!(
+            expectations.runtime_error,
+            Some(("Operand must be a number.".to_string(), 3))
+        );
+        assert_eq!(Expectations::parse("print 1;"), Expectations::default());
+    }
+
+    const COMPILE_ERRORS: &str = "print (1 + ); // Error at ')': Expect expression.\n\
+                                  print 1 @ 2; // Error: Unexpected character.\n\
+                                  // [java line 2] Error at '2': Expect ';' after value.\n\
+                                  // [c line 2] Error: Unexpected character.\n\
+                                  {\n\
+                                  // [line 6] Error at end: Expect '}' after block.\n";
+
+    #[test]
+    fn compile_error_comments_are_collected_with_their_lines() {
+        assert_eq!(
+            Expectations::parse(COMPILE_ERRORS).compile_errors,
+            vec![
+                (1, "at ')': Expect expression.".to_string()),
+                (2, "Unexpected character.".to_string()),
+                (2, "at '2': Expect ';' after value.".to_string()),
+                (6, "at end: Expect '}' after block.".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn compile_errors_match_in_any_order_and_exit_with_65() {
+        let expectations = Expectations::parse(COMPILE_ERRORS);
+        let stderr = "error[E0102]: Unexpected character.\n --> t.lox:2:9\n  |\n\
+                      2 | print 1 @ 2;\n  |         ^\n\
+                      error[E0201]: at ')': Expect expression.\n --> t.lox:1:12\n\
+                      error[E0202]: at '2': Expect ';' after value.\n --> t.lox:2:11\n\
+                      error[E0202]: at end: Expect '}' after block.\n --> t.lox:6:1\n";
+        let passing = Outcome {
+            stdout: "",
+            stderr,
+            exit_code: Some(65),
+        };
+        assert!(expectations.check(&passing).is_empty());
+        let failing = Outcome {
+            stderr: "error[E0201]: at ')': Expect expression.\n --> t.lox:3:12\n",
+            exit_code: Some(70),
+            ..passing
+        };
+        assert_eq!(
+            Expectations::parse("print (1 + ); // Error at ')': Expect expression.")
+                .check(&failing),
+            vec![
+                "expected compile error \"at ')': Expect expression.\" on line 1",
+                "unexpected compile error \"at ')': Expect expression.\" on line 3",
+                "expected exit code 65, got 70"
+            ]
+        );
+    }
+
+    #[test]
+    fn outcomes_pass_only_when_output_errors_and_exit_code_all_match() {
+        let expectations = Expectations::parse(SCRIPT);
+        let passing = Outcome {
+            stdout: "1\na\n",
+            stderr: "Operand must be a number.\n[line 3]\n",
+            exit_code: Some(70),
+        };
+        assert!(expectations.check(&passing).is_empty());
+        let labelled = Outcome {
+            stderr: "error[E0501]: Operand must be a number.\n[line 3]\n",
+            ..passing
+        };
+        assert!(expectations.check(&labelled).is_empty());
+        let failing = Outcome {
+            stdout: "1\nb\nc\n",
+            stderr: "Operand must be a number.\n[line 2]\n",
+            exit_code: Some(70),
+        };
+        assert_eq!(
+            expectations.check(&failing),
+            vec![
+                "output line 2: expected \"a\", got \"b\"",
+                "output line 3: unexpected \"c\"",
+                "expected runtime error \"Operand must be a number.\" at [line 3], \
+                 got \"Operand must be a number.\\n[line 2]\""
+            ]
+        );
+        let crashed = Outcome {
+            stdout: "",
+            stderr: "",
+            exit_code: None,
+        };
+        assert_eq!(
+            Expectations::parse("print 1; // expect: 1").check(&crashed),
+            vec![
+                "output line 1: expected \"1\", got nothing",
+                "expected exit code 0, got none"
+            ]
+        );
+    }
+}
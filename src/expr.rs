@@ -1,30 +1,82 @@
+#![allow(clippy::new_ret_no_self)] // constructors hand back the wrapping Expr variant
+
 use std::rc::Rc;
 
-use crate::token::{Literal, Token};
+use crate::stmt::Stmt;
+use crate::token::Token;
 
 #[derive(Clone)]
 pub enum Expr {
+    Assign(Rc<AssignExpr>),
     Binary(Rc<BinaryExpr>),
+    Call(Rc<CallExpr>),
+    Function(Rc<FunctionExpr>),
+    Get(Rc<GetExpr>),
     Grouping(Rc<GroupingExpr>),
     Literal(Rc<LiteralExpr>),
+    Logical(Rc<LogicalExpr>),
+    Set(Rc<SetExpr>),
+    Super(Rc<SuperExpr>),
+    This(Rc<ThisExpr>),
     Unary(Rc<UnaryExpr>),
+    Variable(Rc<VariableExpr>),
 }
 
 impl Expr {
-    pub(crate) fn accept<T>(&self, visitor: &dyn Visitor<T>) -> T {
+    pub fn accept<T>(&self, visitor: &dyn ExprVisitor<T>) -> T {
         visitor.visit_expr(self)
     }
+
+    // the line of the expression's leftmost token; None for a literal, which keeps no token
+    pub fn line(&self) -> Option<usize> {
+        let line = |token: &Token| Some(*token.line_number());
+        match self {
+            Expr::Assign(expr) => line(expr.name()),
+            Expr::Binary(expr) => expr.lhs().line().or_else(|| line(expr.operator())),
+            Expr::Call(expr) => expr.callee().line().or_else(|| line(expr.paren())),
+            Expr::Function(expr) => line(expr.keyword()),
+            Expr::Get(expr) => expr.object().line().or_else(|| line(expr.name())),
+            Expr::Grouping(expr) => expr.expression().line(),
+            Expr::Literal(_) => None,
+            Expr::Logical(expr) => expr.lhs().line().or_else(|| line(expr.operator())),
+            Expr::Set(expr) => expr.object().line().or_else(|| line(expr.name())),
+            Expr::Super(expr) => line(expr.keyword()),
+            Expr::This(expr) => line(expr.keyword()),
+            Expr::Unary(expr) => line(expr.operator()),
+            Expr::Variable(expr) => line(expr.name()),
+        }
+    }
 }
 
 /**
- * Any operation that can be performed on Expressions will impl Visitor
+ * Any operation that can be performed on Expressions will impl ExprVisitor
  *   Matching on Expr will force implementer to implement a match arm for every Expr variant that exists.
  *   Adding new Expr variances will conveniently raise syntax errors in existing implementations that do not provide match arms for those Expr variants.
+ *   Public so that tools outside this crate can walk the AST the same way the printers and the resolver do.
  */
-pub(crate) trait Visitor<T> {
+pub trait ExprVisitor<T> {
     fn visit_expr(&self, expr: &Expr) -> T;
 }
 
+pub struct AssignExpr {
+    name: Token,
+    value: Expr,
+}
+
+impl AssignExpr {
+    pub fn new(name: Token, value: Expr) -> Expr {
+        Expr::Assign(Rc::new(AssignExpr { name, value }))
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+
+    pub fn value(&self) -> &Expr {
+        &self.value
+    }
+}
+
 pub struct BinaryExpr {
     operator: Token,
     lhs: Expr,
@@ -36,19 +88,95 @@ impl BinaryExpr {
         Expr::Binary(Rc::new(BinaryExpr { operator, lhs, rhs }))
     }
 
-    pub(crate) fn operator(&self) -> &Token {
+    pub fn operator(&self) -> &Token {
         &self.operator
     }
 
-    pub(crate) fn lhs(&self) -> &Expr {
+    pub fn lhs(&self) -> &Expr {
         &self.lhs
     }
 
-    pub(crate) fn rhs(&self) -> &Expr {
+    pub fn rhs(&self) -> &Expr {
         &self.rhs
     }
 }
 
+pub struct CallExpr {
+    callee: Expr,
+    paren: Token, // closing paren, kept for reporting errors raised by the call
+    arguments: Vec<Expr>,
+}
+
+impl CallExpr {
+    pub fn new(callee: Expr, paren: Token, arguments: Vec<Expr>) -> Expr {
+        Expr::Call(Rc::new(CallExpr {
+            callee,
+            paren,
+            arguments,
+        }))
+    }
+
+    pub fn callee(&self) -> &Expr {
+        &self.callee
+    }
+
+    pub fn paren(&self) -> &Token {
+        &self.paren
+    }
+
+    pub fn arguments(&self) -> &[Expr] {
+        &self.arguments
+    }
+}
+
+// an anonymous function, e.g. `fun (a, b) { return a + b; }`
+pub struct FunctionExpr {
+    keyword: Token,
+    params: Vec<Token>,
+    body: Vec<Stmt>,
+}
+
+impl FunctionExpr {
+    pub fn new(keyword: Token, params: Vec<Token>, body: Vec<Stmt>) -> Expr {
+        Expr::Function(Rc::new(FunctionExpr {
+            keyword,
+            params,
+            body,
+        }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+
+    pub fn params(&self) -> &[Token] {
+        &self.params
+    }
+
+    pub fn body(&self) -> &[Stmt] {
+        &self.body
+    }
+}
+
+pub struct GetExpr {
+    object: Expr,
+    name: Token,
+}
+
+impl GetExpr {
+    pub fn new(object: Expr, name: Token) -> Expr {
+        Expr::Get(Rc::new(GetExpr { object, name }))
+    }
+
+    pub fn object(&self) -> &Expr {
+        &self.object
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+}
+
 pub struct GroupingExpr {
     expression: Expr,
 }
@@ -58,7 +186,7 @@ impl GroupingExpr {
         Expr::Grouping(Rc::new(GroupingExpr { expression }))
     }
 
-    pub(crate) fn expression(&self) -> &Expr {
+    pub fn expression(&self) -> &Expr {
         &self.expression
     }
 }
@@ -66,8 +194,8 @@ impl GroupingExpr {
 pub enum LiteralExpr {
     Nil,
     String(String),
-    Float(f32),
-    Bool(bool)
+    Float(f64),
+    Bool(bool),
 }
 
 impl LiteralExpr {
@@ -76,6 +204,91 @@ impl LiteralExpr {
     }
 }
 
+pub struct LogicalExpr {
+    operator: Token,
+    lhs: Expr,
+    rhs: Expr,
+}
+
+impl LogicalExpr {
+    pub fn new(operator: Token, lhs: Expr, rhs: Expr) -> Expr {
+        Expr::Logical(Rc::new(LogicalExpr { operator, lhs, rhs }))
+    }
+
+    pub fn operator(&self) -> &Token {
+        &self.operator
+    }
+
+    pub fn lhs(&self) -> &Expr {
+        &self.lhs
+    }
+
+    pub fn rhs(&self) -> &Expr {
+        &self.rhs
+    }
+}
+
+pub struct SetExpr {
+    object: Expr,
+    name: Token,
+    value: Expr,
+}
+
+impl SetExpr {
+    pub fn new(object: Expr, name: Token, value: Expr) -> Expr {
+        Expr::Set(Rc::new(SetExpr {
+            object,
+            name,
+            value,
+        }))
+    }
+
+    pub fn object(&self) -> &Expr {
+        &self.object
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+
+    pub fn value(&self) -> &Expr {
+        &self.value
+    }
+}
+
+pub struct SuperExpr {
+    keyword: Token,
+    method: Token,
+}
+
+impl SuperExpr {
+    pub fn new(keyword: Token, method: Token) -> Expr {
+        Expr::Super(Rc::new(SuperExpr { keyword, method }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+
+    pub fn method(&self) -> &Token {
+        &self.method
+    }
+}
+
+pub struct ThisExpr {
+    keyword: Token,
+}
+
+impl ThisExpr {
+    pub fn new(keyword: Token) -> Expr {
+        Expr::This(Rc::new(ThisExpr { keyword }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+}
+
 pub struct UnaryExpr {
     operator: Token,
     rhs: Expr,
@@ -86,10 +299,24 @@ impl UnaryExpr {
         Expr::Unary(Rc::new(UnaryExpr { operator, rhs }))
     }
 
-    pub(crate) fn operator(&self) -> &Token {
+    pub fn operator(&self) -> &Token {
         &self.operator
     }
-    pub(crate) fn rhs(&self) -> &Expr {
+    pub fn rhs(&self) -> &Expr {
         &self.rhs
     }
 }
+
+pub struct VariableExpr {
+    name: Token,
+}
+
+impl VariableExpr {
+    pub fn new(name: Token) -> Expr {
+        Expr::Variable(Rc::new(VariableExpr { name }))
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+}
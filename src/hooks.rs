@@ -0,0 +1,153 @@
+use crate::callable::LoxCallable;
+use crate::interpreter::Value;
+use crate::stmt::Stmt;
+use crate::token::Token;
+
+/**
+ * Callbacks the interpreter makes as it runs, for tracers, debuggers and profilers built on top
+ *   of it. Every callback does nothing by default, so hooks implement just the events they need;
+ *   they take `&self`, so hooks that record what they see keep it in a `RefCell` or `Cell`.
+ */
+pub trait InterpreterHooks {
+    // before each statement runs, including the ones inside blocks and function bodies
+    fn statement_enter(&self, _stmt: &Stmt) {}
+
+    // after each statement, however it finished, including by a runtime error
+    fn statement_exit(&self, _stmt: &Stmt) {}
+
+    // before a function, native function or class is called, with its checked arguments
+    fn function_call(&self, _callee: &dyn LoxCallable, _arguments: &[Value]) {}
+
+    // after the call, with the value it returned, or None if a runtime error unwound out of it
+    //   A call that ends in a tail call returns after the tail call does, with the same value.
+    fn function_return(&self, _callee: &dyn LoxCallable, _value: Option<&Value>) {}
+
+    // after `name = value` assigns an existing variable
+    fn variable_assign(&self, _name: &Token, _value: &Value) {}
+
+    // when a block or a function body starts running in a new environment of its own
+    fn environment_create(&self) {}
+
+    // asked after `statement_enter`; Some(code) ends the program there, as `exit(code)` would
+    fn interrupt(&self) -> Option<i32> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use super::InterpreterHooks;
+    use crate::callable::LoxCallable;
+    use crate::interpreter::{Interpreter, Value};
+    use crate::parse_program;
+    use crate::resolver::resolve;
+    use crate::stmt::Stmt;
+    use crate::token::Token;
+
+    // the statements the recorder follows in and out
+    fn kind(stmt: &Stmt) -> Option<&'static str> {
+        match stmt {
+            Stmt::If(_) => Some("if"),
+            Stmt::Block(_) => Some("block"),
+            _ => None,
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        events: RefCell<Vec<String>>,
+    }
+
+    impl InterpreterHooks for Recorder {
+        fn statement_enter(&self, stmt: &Stmt) {
+            if let Some(kind) = kind(stmt) {
+                self.events.borrow_mut().push(format!("enter {}", kind));
+            }
+        }
+
+        fn statement_exit(&self, stmt: &Stmt) {
+            if let Some(kind) = kind(stmt) {
+                self.events.borrow_mut().push(format!("exit {}", kind));
+            }
+        }
+
+        fn function_call(&self, callee: &dyn LoxCallable, arguments: &[Value]) {
+            let event = format!("call {} {}", callee, arguments[0]);
+            self.events.borrow_mut().push(event);
+        }
+
+        fn function_return(&self, callee: &dyn LoxCallable, value: Option<&Value>) {
+            let event = format!("return {} {}", callee, value.unwrap());
+            self.events.borrow_mut().push(event);
+        }
+
+        fn variable_assign(&self, name: &Token, value: &Value) {
+            let event = format!("assign {} {}", name.lexeme(), value);
+            self.events.borrow_mut().push(event);
+        }
+
+        fn environment_create(&self) {
+            self.events.borrow_mut().push("environment".to_string());
+        }
+    }
+
+    #[test]
+    fn hooks_see_statements_calls_and_assignments_in_order() {
+        let recorder = Rc::new(Recorder::default());
+        let interpreter = Interpreter::default().with_hooks(recorder.clone());
+        let program = parse_program(
+            "var total = 0;\n\
+             fun add(n) { total = total + n; return total; }\n\
+             add(2);\n\
+             if (true) { total = 3; }",
+        )
+        .ast;
+        let locals = resolve(&program).unwrap();
+        interpreter.interpret(&program, locals).unwrap();
+        assert_eq!(
+            *recorder.events.borrow(),
+            vec![
+                "call <fn add> 2",
+                "environment",
+                "assign total 2",
+                "return <fn add> 2",
+                "enter if",
+                "enter block",
+                "environment",
+                "assign total 3",
+                "exit block",
+                "exit if"
+            ]
+        );
+    }
+
+    #[test]
+    fn calls_ending_in_tail_calls_return_the_tail_call_value() {
+        let recorder = Rc::new(Recorder::default());
+        let interpreter = Interpreter::default()
+            .with_tail_calls(true)
+            .with_hooks(recorder.clone());
+        let program = parse_program(
+            "fun g(n) { return n; }\n\
+             fun f(n) { return g(n); }\n\
+             f(2);",
+        )
+        .ast;
+        let locals = resolve(&program).unwrap();
+        interpreter.interpret(&program, locals).unwrap();
+        assert_eq!(
+            *recorder.events.borrow(),
+            vec![
+                "call <fn f> 2",
+                "environment",
+                "call <fn g> 2",
+                "environment",
+                "return <fn g> 2",
+                "return <fn f> 2"
+            ]
+        );
+    }
+}
@@ -0,0 +1,1691 @@
+use crate::error::{RloxError, RloxRuntimeError};
+use std::cell::{Cell, RefCell};
+use std::fmt::{self, Display};
+use std::rc::Rc;
+
+use crate::callable::{LoxCallable, LoxFunction};
+use crate::class::{LoxClass, LoxInstance};
+use crate::environment::Environment;
+use crate::error_codes::{
+    ErrorCode, DIVISION_BY_ZERO, INVALID_PLUS_OPERANDS, NOT_AN_INSTANCE, NOT_CALLABLE,
+    OPERAND_NOT_A_NUMBER, STACK_OVERFLOW, SUPERCLASS_NOT_A_CLASS, SUPER_OUTSIDE_CLASS,
+    UNDEFINED_PROPERTY, UNSUPPORTED_OPERATION, WRONG_ARGUMENT_COUNT,
+};
+use crate::expr::{
+    BinaryExpr, CallExpr, Expr, ExprVisitor, GetExpr, LiteralExpr, LogicalExpr, SetExpr, SuperExpr,
+    UnaryExpr,
+};
+use crate::hooks::InterpreterHooks;
+use crate::natives::{define_natives, seed_from_time};
+use crate::resolver::Locals;
+use crate::stmt::{ClassStmt, ExpressionStmt, Stmt, StmtVisitor};
+use crate::token::{Token, TokenCategory, TokenType};
+
+// deep enough for any real recursion, shallow enough to fit the stack the CLI runs scripts on
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 1024;
+
+// the deepest limit a config may set: about a quarter of the calls to a trivial function that
+//   the CLI's stack holds in an unoptimized build, leaving room for calls with deeper bodies
+pub const MAX_CALL_DEPTH: usize = 10_000;
+
+/**
+ * A Lox runtime value.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    String(String),
+    Bool(bool),
+    Nil,
+    Callable(Rc<dyn LoxCallable>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<LoxInstance>),
+}
+
+impl Value {
+    // nil and false are falsey, everything else is truthy, including 0 and ""
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    // values of different types are never equal: there is no coercion between numbers and strings
+    pub fn is_equal(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Callable(a), Value::Callable(b)) => a == b,
+            (Value::Class(a), Value::Class(b)) => a == b,
+            (Value::Instance(a), Value::Instance(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+// how deep `Value::pretty` shows instances held in fields, and how many fields it shows of each
+const PRETTY_MAX_DEPTH: usize = 3;
+const PRETTY_MAX_FIELDS: usize = 8;
+
+impl Value {
+    /**
+     * How the REPL shows a value, telling apart what `print` would show the same: strings quoted,
+     *   functions as `<fn name/arity>`, classes as `<class Name>`, and instances with their fields,
+     *   e.g. `Point { x: 1, y: 2 }`. Instances nested too deeply, say in a cycle of fields, are cut
+     *   short as `Point { ... }`, as are the fields past the first few.
+     */
+    pub fn pretty(&self) -> String {
+        self.pretty_at(0)
+    }
+
+    fn pretty_at(&self, depth: usize) -> String {
+        match self {
+            Value::String(s) => format!("{:?}", s),
+            Value::Callable(c) => match c.name() {
+                Some(name) => format!("<fn {}/{}>", name, c.arity()),
+                None => format!("<fn/{}>", c.arity()),
+            },
+            Value::Class(c) => format!("<class {}>", c.name()),
+            Value::Instance(i) if depth >= PRETTY_MAX_DEPTH => {
+                format!("{} {{ ... }}", i.class().name())
+            }
+            Value::Instance(i) => {
+                let fields = i.fields();
+                let mut shown = fields
+                    .iter()
+                    .take(PRETTY_MAX_FIELDS)
+                    .map(|(name, value)| format!("{}: {}", name, value.pretty_at(depth + 1)))
+                    .collect::<Vec<_>>();
+                if fields.len() > PRETTY_MAX_FIELDS {
+                    shown.push("...".to_string());
+                }
+                match shown.is_empty() {
+                    true => format!("{} {{}}", i.class().name()),
+                    false => format!("{} {{ {} }}", i.class().name(), shown.join(", ")),
+                }
+            }
+            value => value.to_string(),
+        }
+    }
+}
+
+/**
+ * How `print` shows a value: numbers without a trailing `.0` when they are whole, strings without
+ *   quotes, and `nil` as `nil`.
+ */
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Number(n) if n.is_infinite() => match n.is_sign_positive() {
+                true => write!(f, "Infinity"),
+                false => write!(f, "-Infinity"),
+            },
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Nil => write!(f, "nil"),
+            Value::Callable(c) => write!(f, "{}", c),
+            Value::Class(c) => write!(f, "{}", c),
+            Value::Instance(i) => write!(f, "{}", i),
+        }
+    }
+}
+
+/**
+ * Tree-walking evaluator: visiting an expression computes its value, visiting a statement runs it.
+ */
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    // the innermost scope of whatever is running
+    environment: RefCell<Rc<RefCell<Environment>>>,
+    // where each local reference of every program interpreted so far resolved to
+    locals: RefCell<Locals>,
+    // every program interpreted so far, kept alive so no node they resolved is freed and its
+    //   address reused by a later program's node
+    programs: RefCell<Vec<Vec<Stmt>>>,
+    // calls in progress, each of which holds native stack until it returns
+    call_depth: Cell<usize>,
+    max_call_depth: usize,
+    tail_calls: bool,
+    hooks: Option<Rc<dyn InterpreterHooks>>,
+    // a call in tail position, for the call whose body just returned it to make in its place
+    tail_call: RefCell<Option<Call>>,
+    division_by_zero_errors: bool,
+    string_coercion: bool,
+    uninitialized_errors: bool,
+    // what `clock()` returns when it is pinned rather than reading the system clock
+    fixed_clock: Option<f64>,
+    // where `random()` has got to in its sequence
+    random_state: Cell<u64>,
+}
+
+// a callee and the arguments to call it with, checked against its arity
+type Call = (Rc<dyn LoxCallable>, Vec<Value>);
+
+// how a statement finished: normally, or by unwinding towards the loop it breaks out of or
+//   continues, or the call it returns from
+#[derive(Debug, PartialEq)]
+enum Flow {
+    Normal,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+impl StmtVisitor<Result<Flow, RloxError>> for Interpreter {
+    fn visit_stmt(&self, stmt: &Stmt) -> Result<Flow, RloxError> {
+        match stmt {
+            Stmt::Block(stmt) => {
+                let enclosing = self.environment.borrow().clone();
+                self.execute_block(stmt.statements(), Environment::new(enclosing))
+            }
+            Stmt::Break(_) => Ok(Flow::Break),
+            Stmt::Continue(_) => Ok(Flow::Continue),
+            Stmt::Expression(stmt) => self.evaluate(stmt.expression()).map(|_| Flow::Normal),
+            Stmt::Function(stmt) => {
+                let function =
+                    LoxFunction::declared(stmt.clone(), self.environment.borrow().clone());
+                self.environment
+                    .borrow()
+                    .borrow_mut()
+                    .define(stmt.name().lexeme(), Value::Callable(Rc::new(function)));
+                Ok(Flow::Normal)
+            }
+            Stmt::Print(stmt) => {
+                println!("{}", self.evaluate(stmt.expression())?);
+                Ok(Flow::Normal)
+            }
+            Stmt::If(stmt) => {
+                if self.evaluate(stmt.condition())?.is_truthy() {
+                    self.execute(stmt.then_branch())
+                } else if let Some(else_branch) = stmt.else_branch() {
+                    self.execute(else_branch)
+                } else {
+                    Ok(Flow::Normal)
+                }
+            }
+            Stmt::Var(stmt) => {
+                let name = stmt.name().lexeme();
+                let value = match stmt.initializer() {
+                    Some(initializer) => Some(self.evaluate(initializer)?),
+                    None if self.uninitialized_errors => None,
+                    None => Some(Value::Nil),
+                };
+                let environment = self.environment.borrow();
+                match value {
+                    Some(value) => environment.borrow_mut().define(name, value),
+                    None => environment.borrow_mut().declare(name),
+                }
+                Ok(Flow::Normal)
+            }
+            Stmt::While(stmt) => {
+                while self.evaluate(stmt.condition())?.is_truthy() {
+                    match self.execute(stmt.body())? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                    // reached after a `continue` too, so a for loop still advances
+                    if let Some(increment) = stmt.increment() {
+                        self.evaluate(increment)?;
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            // a tail call is left for the call being returned from to make once its body has
+            //   unwound, so the callee runs in place of the caller instead of on top of it
+            Stmt::Return(stmt) => {
+                let value = match stmt.value() {
+                    Some(Expr::Call(call)) if self.tail_calls => {
+                        let pending = self.callee(call)?;
+                        self.tail_call.replace(Some(pending));
+                        Value::Nil
+                    }
+                    Some(value) => self.evaluate(value)?,
+                    None => Value::Nil,
+                };
+                Ok(Flow::Return(value))
+            }
+            Stmt::Class(stmt) => self.execute_class(stmt),
+        }
+    }
+}
+
+impl ExprVisitor<Result<Value, RloxError>> for Interpreter {
+    fn visit_expr(&self, expr: &Expr) -> Result<Value, RloxError> {
+        match expr {
+            Expr::Binary(expr) => self.visit_binary_expr(expr),
+            Expr::Call(expr) => self.visit_call_expr(expr),
+            Expr::Function(expr) => {
+                let function =
+                    LoxFunction::anonymous(expr.clone(), self.environment.borrow().clone());
+                Ok(Value::Callable(Rc::new(function)))
+            }
+            Expr::Get(expr) => self.visit_get_expr(expr),
+            Expr::Grouping(expr) => self.evaluate(expr.expression()),
+            Expr::Literal(expr) => Ok(self.visit_literal_expr(expr)),
+            Expr::Logical(expr) => self.visit_logical_expr(expr),
+            Expr::Set(expr) => self.visit_set_expr(expr),
+            Expr::Super(super_expr) => {
+                let depth = self.locals.borrow().depth(expr);
+                self.visit_super_expr(super_expr, depth)
+            }
+            // the receiver, defined when the method was bound
+            Expr::This(this) => self.look_up_variable(expr, this.keyword()),
+            Expr::Unary(expr) => self.visit_unary_expr(expr),
+            Expr::Variable(variable) => self.look_up_variable(expr, variable.name()),
+            Expr::Assign(assign) => {
+                let value = self.evaluate(assign.value())?;
+                let resolution = self.locals.borrow().resolution(expr);
+                match resolution {
+                    Some((depth, slot)) => self.environment.borrow().borrow_mut().assign_at(
+                        depth,
+                        slot,
+                        assign.name(),
+                        value.clone(),
+                    )?,
+                    None => self
+                        .globals
+                        .borrow_mut()
+                        .assign(assign.name(), value.clone())?,
+                }
+                if let Some(hooks) = &self.hooks {
+                    hooks.variable_assign(assign.name(), &value);
+                }
+                Ok(value)
+            }
+        }
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        let mut globals = Environment::default();
+        define_natives(&mut globals);
+        let globals = Rc::new(RefCell::new(globals));
+        Interpreter {
+            environment: RefCell::new(globals.clone()),
+            globals,
+            locals: RefCell::new(Locals::default()),
+            programs: RefCell::new(Vec::new()),
+            call_depth: Cell::new(0),
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            tail_calls: false,
+            hooks: None,
+            tail_call: RefCell::new(None),
+            division_by_zero_errors: false,
+            string_coercion: false,
+            uninitialized_errors: false,
+            fixed_clock: None,
+            random_state: Cell::new(seed_from_time()),
+        }
+    }
+}
+
+impl Interpreter {
+    /**
+     * Makes a call that would nest deeper than `limit` calls the runtime error "Stack overflow."
+     *   Every Lox call recurses on the native stack, so without a limit deep recursion would
+     *   abort the process instead.
+     */
+    pub fn with_max_call_depth(mut self, limit: usize) -> Self {
+        self.max_call_depth = limit;
+        self
+    }
+
+    /**
+     * Installs callbacks on the running program's statements, calls and assignments. The caller
+     *   keeps its own `Rc` to read back whatever the hooks recorded. Local scopes then also keep
+     *   their variables' names, for `scopes` to report.
+     */
+    pub fn with_hooks(mut self, hooks: Rc<dyn InterpreterHooks>) -> Self {
+        self.hooks = Some(hooks);
+        self.globals.borrow_mut().name_locals();
+        self
+    }
+
+    /**
+     * Makes `return f(...);` reuse the returning call rather than nest a new one, so recursion
+     *   written as a loop runs in constant stack space and never reaches the call depth limit.
+     */
+    pub fn with_tail_calls(mut self, enabled: bool) -> Self {
+        self.tail_calls = enabled;
+        self
+    }
+
+    /**
+     * Makes dividing by zero the runtime error "Division by zero." instead of yielding infinity,
+     *   or NaN for `0 / 0`, as IEEE 754 arithmetic and so Lox itself would.
+     */
+    pub fn with_division_by_zero_errors(mut self, enabled: bool) -> Self {
+        self.division_by_zero_errors = enabled;
+        self
+    }
+
+    /**
+     * Lets `+` concatenate a string with a number on either side, printing the number the way
+     *   `print` would, e.g. `"scone" + 4` is `"scone4"`, instead of a runtime error.
+     */
+    pub fn with_string_coercion(mut self, enabled: bool) -> Self {
+        self.string_coercion = enabled;
+        self
+    }
+
+    /**
+     * Makes `var a;` leave `a` unassigned rather than nil, so reading it before it is assigned is
+     *   the runtime error "Uninitialized variable 'a'.".
+     */
+    pub fn with_uninitialized_errors(mut self, enabled: bool) -> Self {
+        self.uninitialized_errors = enabled;
+        self
+    }
+
+    // makes `clock()` always return `seconds`, so programs that print times print the same each run
+    pub fn with_fixed_clock(mut self, seconds: f64) -> Self {
+        self.fixed_clock = Some(seconds);
+        self
+    }
+
+    // starts `random()` from `seed` rather than from the time, so it returns the same sequence
+    pub fn with_random_seed(self, seed: u64) -> Self {
+        self.random_state.set(seed);
+        self
+    }
+
+    pub(crate) fn fixed_clock(&self) -> Option<f64> {
+        self.fixed_clock
+    }
+
+    pub(crate) fn random_state(&self) -> &Cell<u64> {
+        &self.random_state
+    }
+
+    // runs statements in order, stopping at the first runtime error; `locals` must be what
+    //   resolving exactly these statements produced
+    //   (the resolver rejects `break`, `continue` and `return` where there is no loop or function
+    //   to unwind to, so none reaches this far)
+    pub fn interpret(&self, statements: &[Stmt], locals: Locals) -> Result<(), RloxError> {
+        self.locals.borrow_mut().extend(locals);
+        self.programs.borrow_mut().push(statements.to_vec());
+        self.execute_all(statements).map(|_| ())
+    }
+
+    /**
+     * What an identifier starting with `prefix` could be completed to, e.g. for tab completion in
+     *   a REPL: the keywords, and every global defined so far, natives included, in sorted order.
+     */
+    pub fn completions(&self, prefix: &str) -> Vec<String> {
+        let keywords = TokenType::ALL
+            .iter()
+            .map(|token_type| token_type.info())
+            .filter(|info| info.category == TokenCategory::Keyword)
+            .filter_map(|info| info.lexeme);
+        let globals = self.globals.borrow();
+        let mut completions = keywords
+            .chain(globals.names())
+            .filter(|name| name.starts_with(prefix))
+            .map(|name| name.to_string())
+            .collect::<Vec<_>>();
+        completions.sort();
+        completions.dedup();
+        completions
+    }
+
+    // defines, or redefines, a global as if by `var`, e.g. for an embedder to hand a value in
+    pub fn define_global(&self, name: &str, value: Value) {
+        self.globals.borrow_mut().define(name, value);
+    }
+
+    /**
+     * Forgets everything the programs interpreted so far defined, leaving the globals as a new
+     *   interpreter's, with just the natives. The options it was built with are kept.
+     */
+    pub fn reset(&self) {
+        let mut globals = Environment::default();
+        define_natives(&mut globals);
+        if self.hooks.is_some() {
+            globals.name_locals();
+        }
+        *self.globals.borrow_mut() = globals;
+        self.environment.replace(self.globals.clone());
+        self.locals.replace(Locals::default());
+        self.programs.borrow_mut().clear();
+    }
+
+    /**
+     * The variables in scope where the program is running, innermost scope first and the globals
+     *   last, each with its value or None if it is not yet assigned. Locals are only known by name
+     *   when hooks are installed, so without them the local scopes are empty.
+     */
+    pub fn scopes(&self) -> Vec<Vec<(String, Option<Value>)>> {
+        let mut scopes = Vec::new();
+        let mut environment = Some(self.environment.borrow().clone());
+        while let Some(scope) = environment {
+            scopes.push(scope.borrow().variables());
+            environment = scope.borrow().enclosing();
+        }
+        scopes
+    }
+
+    // like `interpret`, for a single expression whose value is wanted, e.g. one typed into a REPL;
+    //   `locals` must be what resolving it as an expression statement produced
+    pub fn interpret_expression(&self, expr: &Expr, locals: Locals) -> Result<Value, RloxError> {
+        self.locals.borrow_mut().extend(locals);
+        self.programs
+            .borrow_mut()
+            .push(vec![ExpressionStmt::new(expr.clone())]);
+        self.evaluate(expr)
+    }
+
+    // a local by the scope distance and slot the resolver found for it, or else a global by name
+    fn look_up_variable(&self, expr: &Expr, name: &Token) -> Result<Value, RloxError> {
+        let resolution = self.locals.borrow().resolution(expr);
+        match resolution {
+            Some((depth, slot)) => self.environment.borrow().borrow().get_at(depth, slot, name),
+            None => self.globals.borrow().get(name),
+        }
+    }
+
+    fn execute(&self, stmt: &Stmt) -> Result<Flow, RloxError> {
+        match &self.hooks {
+            None => stmt.accept::<Result<Flow, RloxError>>(self),
+            Some(hooks) => {
+                hooks.statement_enter(stmt);
+                let flow = match hooks.interrupt() {
+                    Some(code) => Err(RloxError::Exit(code)),
+                    None => stmt.accept::<Result<Flow, RloxError>>(self),
+                };
+                hooks.statement_exit(stmt);
+                flow
+            }
+        }
+    }
+
+    // stops early at a `break`, `continue` or `return`, handing it on to the enclosing loop or call
+    fn execute_all(&self, statements: &[Stmt]) -> Result<Flow, RloxError> {
+        for stmt in statements {
+            match self.execute(stmt)? {
+                Flow::Normal => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Normal)
+    }
+
+    // the enclosing scope is restored however the block exits, so a runtime error inside it
+    //   cannot leave the REPL stuck in a scope that has ended
+    fn execute_block(
+        &self,
+        statements: &[Stmt],
+        environment: Environment,
+    ) -> Result<Flow, RloxError> {
+        if let Some(hooks) = &self.hooks {
+            hooks.environment_create();
+        }
+        let previous = self.environment.replace(Rc::new(RefCell::new(environment)));
+        let result = self.execute_all(statements);
+        self.environment.replace(previous);
+        result
+    }
+
+    // with a superclass, the methods close over an extra scope defining `super` as that class
+    fn execute_class(&self, stmt: &ClassStmt) -> Result<Flow, RloxError> {
+        let mut closure = self.environment.borrow().clone();
+        let superclass = match stmt.superclass() {
+            Some(superclass) => match (self.evaluate(superclass)?, superclass) {
+                (Value::Class(class), _) => Some(class),
+                (_, Expr::Variable(superclass)) => {
+                    return Err(error(
+                        superclass.name(),
+                        &SUPERCLASS_NOT_A_CLASS,
+                        "Superclass must be a class.",
+                    ))
+                }
+                _ => {
+                    return Err(error(
+                        stmt.name(),
+                        &SUPERCLASS_NOT_A_CLASS,
+                        "Superclass must be a class.",
+                    ))
+                }
+            },
+            None => None,
+        };
+        if let Some(superclass) = &superclass {
+            let mut environment = Environment::new(closure);
+            environment.define("super", Value::Class(superclass.clone()));
+            closure = Rc::new(RefCell::new(environment));
+        }
+        let methods = stmt
+            .methods()
+            .iter()
+            .map(|method| {
+                let function = LoxFunction::method(method.clone(), closure.clone());
+                (method.name().lexeme().to_string(), Rc::new(function))
+            })
+            .collect();
+        let class = LoxClass::new(stmt.name().lexeme(), superclass, methods);
+        self.environment
+            .borrow()
+            .borrow_mut()
+            .define(stmt.name().lexeme(), Value::Class(class));
+        Ok(Flow::Normal)
+    }
+
+    // runs a function body in the environment holding its parameters; the call's result is the
+    //   value of the `return` that unwound out of it, or nil if the body ran to its end
+    pub(crate) fn execute_body(
+        &self,
+        body: &[Stmt],
+        environment: Environment,
+    ) -> Result<Value, RloxError> {
+        match self.execute_block(body, environment)? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Value::Nil),
+        }
+    }
+
+    pub fn evaluate(&self, expr: &Expr) -> Result<Value, RloxError> {
+        expr.accept::<Result<Value, RloxError>>(self)
+    }
+
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<Value, RloxError> {
+        // operands are evaluated left to right, before any type checks
+        let lhs = self.evaluate(expr.lhs())?;
+        let rhs = self.evaluate(expr.rhs())?;
+        let operator = expr.operator();
+        match (operator.token_type(), lhs, rhs) {
+            (TokenType::Comma, _, rhs) => Ok(rhs),
+            (TokenType::EqualEqual, lhs, rhs) => Ok(Value::Bool(lhs.is_equal(&rhs))),
+            (TokenType::BangEqual, lhs, rhs) => Ok(Value::Bool(!lhs.is_equal(&rhs))),
+            (TokenType::Plus, Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
+            (TokenType::Plus, Value::String(a), Value::String(b)) => Ok(Value::String(a + &b)),
+            (TokenType::Plus, Value::String(a), Value::Number(b)) if self.string_coercion => {
+                Ok(Value::String(format!("{}{}", a, Value::Number(b))))
+            }
+            (TokenType::Plus, Value::Number(a), Value::String(b)) if self.string_coercion => {
+                Ok(Value::String(format!("{}{}", Value::Number(a), b)))
+            }
+            (TokenType::Plus, _, _) => Err(error(
+                operator,
+                &INVALID_PLUS_OPERANDS,
+                "Operands must be two numbers or two strings.",
+            )),
+            (_, Value::Number(a), Value::Number(b)) => match operator.token_type() {
+                TokenType::Minus => Ok(Value::Number(a - b)),
+                TokenType::Slash if b == 0.0 && self.division_by_zero_errors => {
+                    Err(error(operator, &DIVISION_BY_ZERO, "Division by zero."))
+                }
+                TokenType::Slash => Ok(Value::Number(a / b)),
+                TokenType::Star => Ok(Value::Number(a * b)),
+                TokenType::Greater => Ok(Value::Bool(a > b)),
+                TokenType::GreaterEqual => Ok(Value::Bool(a >= b)),
+                TokenType::Less => Ok(Value::Bool(a < b)),
+                TokenType::LessEqual => Ok(Value::Bool(a <= b)),
+                _ => unsupported(operator),
+            },
+            _ => Err(error(
+                operator,
+                &OPERAND_NOT_A_NUMBER,
+                "Operands must be numbers.",
+            )),
+        }
+    }
+
+    /**
+     * Each tail call the body returns is made in turn at the same depth, until one returns a
+     *   value. Hooks still see the calls nest: a call that ended in a tail call is reported to
+     *   return only once the tail call has, with the value it returned.
+     */
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<Value, RloxError> {
+        let (mut callable, arguments) = self.callee(expr)?;
+        if self.call_depth.get() >= self.max_call_depth {
+            return Err(error(expr.paren(), &STACK_OVERFLOW, "Stack overflow."));
+        }
+        self.call_depth.set(self.call_depth.get() + 1);
+        let mut result = self.call(&callable, arguments);
+        // the calls that ended in a tail call, outermost first; only kept for the hooks
+        let mut callers = Vec::new();
+        while result.is_ok() {
+            match self.tail_call.take() {
+                Some((next, arguments)) => {
+                    result = self.call(&next, arguments);
+                    let caller = std::mem::replace(&mut callable, next);
+                    if self.hooks.is_some() {
+                        callers.push(caller);
+                    }
+                }
+                None => break,
+            }
+        }
+        if let Some(hooks) = &self.hooks {
+            for callee in std::iter::once(&callable).chain(callers.iter().rev()) {
+                hooks.function_return(callee.as_ref(), result.as_ref().ok());
+            }
+        }
+        self.call_depth.set(self.call_depth.get() - 1);
+        // a runtime error records each call it unwinds out of, for its stack trace
+        result.map_err(|e| match e {
+            RloxError::RuntimeError(mut e) => {
+                e.calls
+                    .push((callable.frame(), *expr.paren().line_number()));
+                RloxError::RuntimeError(e)
+            }
+            e => e,
+        })
+    }
+
+    // calls `callable`, telling any hooks; `visit_call_expr` tells them when the call returns
+    fn call(
+        &self,
+        callable: &Rc<dyn LoxCallable>,
+        arguments: Vec<Value>,
+    ) -> Result<Value, RloxError> {
+        if let Some(hooks) = &self.hooks {
+            hooks.function_call(callable.as_ref(), &arguments);
+        }
+        callable.call(self, arguments)
+    }
+
+    // the callee, then the arguments left to right, are all evaluated before any check
+    fn callee(&self, expr: &CallExpr) -> Result<Call, RloxError> {
+        let callee = self.evaluate(expr.callee())?;
+        let arguments = expr
+            .arguments()
+            .iter()
+            .map(|argument| self.evaluate(argument))
+            .collect::<Result<Vec<_>, _>>()?;
+        let callable: Rc<dyn LoxCallable> = match callee {
+            Value::Callable(callable) => callable,
+            Value::Class(class) => class,
+            _ => {
+                return Err(error(
+                    expr.paren(),
+                    &NOT_CALLABLE,
+                    "Can only call functions and classes.",
+                ))
+            }
+        };
+        if arguments.len() != callable.arity() {
+            let message = format!(
+                "Expected {} arguments but got {}.",
+                callable.arity(),
+                arguments.len()
+            );
+            return Err(error(expr.paren(), &WRONG_ARGUMENT_COUNT, &message));
+        }
+        Ok((callable, arguments))
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> Result<Value, RloxError> {
+        match self.evaluate(expr.object())? {
+            Value::Instance(instance) => instance.get(expr.name()),
+            _ => Err(error(
+                expr.name(),
+                &NOT_AN_INSTANCE,
+                "Only instances have properties.",
+            )),
+        }
+    }
+
+    // the object is checked before the value is evaluated
+    fn visit_set_expr(&self, expr: &SetExpr) -> Result<Value, RloxError> {
+        let instance = match self.evaluate(expr.object())? {
+            Value::Instance(instance) => instance,
+            _ => {
+                return Err(error(
+                    expr.name(),
+                    &NOT_AN_INSTANCE,
+                    "Only instances have fields.",
+                ))
+            }
+        };
+        let value = self.evaluate(expr.value())?;
+        instance.set(expr.name(), value.clone());
+        Ok(value)
+    }
+
+    // the method is looked up from the superclass of the class declaring the calling method,
+    //   not of the receiver's class, and bound to the receiver
+    //   `super` is alone in the scope `depth` out, and `this` alone in the scope just inside it
+    fn visit_super_expr(&self, expr: &SuperExpr, depth: Option<usize>) -> Result<Value, RloxError> {
+        let environment = self.environment.borrow().clone();
+        let environment = environment.borrow();
+        let bound = depth.map(|depth| {
+            (
+                environment.get_at(depth, 0, expr.keyword()),
+                environment.get_at(depth - 1, 0, expr.keyword()),
+            )
+        });
+        let (superclass, this) = match bound {
+            Some((Ok(Value::Class(superclass)), Ok(Value::Instance(this)))) => (superclass, this),
+            // the resolver only allows `super` in a subclass's methods, which bind both
+            _ => {
+                return Err(error(
+                    expr.keyword(),
+                    &SUPER_OUTSIDE_CLASS,
+                    "Can't use 'super' outside of a subclass.",
+                ))
+            }
+        };
+        match superclass.find_method(expr.method().lexeme()) {
+            Some(method) => Ok(Value::Callable(Rc::new(method.bind(this)))),
+            None => Err(error(
+                expr.method(),
+                &UNDEFINED_PROPERTY,
+                &format!("Undefined property '{}'.", expr.method().lexeme()),
+            )),
+        }
+    }
+
+    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Value {
+        match expr {
+            LiteralExpr::Nil => Value::Nil,
+            LiteralExpr::String(s) => Value::String(s.clone()),
+            LiteralExpr::Float(f) => Value::Number(*f),
+            LiteralExpr::Bool(b) => Value::Bool(*b),
+        }
+    }
+
+    // yields whichever operand decided the result, not a boolean; `rhs` runs only if it must
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<Value, RloxError> {
+        let lhs = self.evaluate(expr.lhs())?;
+        let decided = match expr.operator().token_type() {
+            TokenType::Or => lhs.is_truthy(),
+            _ => !lhs.is_truthy(),
+        };
+        match decided {
+            true => Ok(lhs),
+            false => self.evaluate(expr.rhs()),
+        }
+    }
+
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<Value, RloxError> {
+        let rhs = self.evaluate(expr.rhs())?;
+        match (expr.operator().token_type(), rhs) {
+            (TokenType::Bang, rhs) => Ok(Value::Bool(!rhs.is_truthy())),
+            (TokenType::Minus, Value::Number(n)) => Ok(Value::Number(-n)),
+            (TokenType::Minus, _) => Err(error(
+                expr.operator(),
+                &OPERAND_NOT_A_NUMBER,
+                "Operand must be a number.",
+            )),
+            _ => unsupported(expr.operator()),
+        }
+    }
+}
+
+fn error(token: &Token, code: &'static ErrorCode, message: &str) -> RloxError {
+    RloxError::RuntimeError(Box::new(RloxRuntimeError {
+        code,
+        token: token.clone(),
+        message: message.to_string(),
+        calls: Vec::new(),
+        file: None,
+    }))
+}
+
+fn unsupported<T>(token: &Token) -> Result<T, RloxError> {
+    Err(error(
+        token,
+        &UNSUPPORTED_OPERATION,
+        "Not supported by the interpreter yet.",
+    ))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{Interpreter, Value};
+    use crate::error::RloxError;
+    use crate::resolver::resolve;
+    use crate::stmt::Stmt;
+    use crate::{parse_expression, parse_program};
+
+    // resolves the program first, as running it for real would
+    pub(crate) fn interpret(interpreter: &Interpreter, program: &[Stmt]) -> Result<(), RloxError> {
+        let locals = resolve(program).expect("program resolves");
+        interpreter.interpret(program, locals)
+    }
+
+    // parses `source` as an expression and evaluates it in `interpreter`
+    pub(crate) fn evaluate(interpreter: &Interpreter, source: &str) -> Result<Value, RloxError> {
+        let expr = parse_expression(source).ast.expect("valid expression");
+        interpreter.evaluate(&expr)
+    }
+
+    // evaluates `source` in a fresh interpreter, failing with the runtime error's message
+    fn evaluate_alone(source: &str) -> Result<Value, String> {
+        evaluate(&Interpreter::default(), source).map_err(|e| match e {
+            RloxError::RuntimeError(e) => e.message().to_string(),
+            _ => panic!("expected a runtime error"),
+        })
+    }
+
+    #[test]
+    fn arithmetic_follows_precedence() {
+        assert_eq!(evaluate_alone("1 + 2 * 3 - 4 / 2"), Ok(Value::Number(5.0)));
+        assert_eq!(evaluate_alone("-(1 + 2) * 2"), Ok(Value::Number(-6.0)));
+        assert_eq!(evaluate_alone("0.1 + 0.2"), Ok(Value::Number(0.1 + 0.2)));
+        assert_eq!(
+            evaluate_alone("\"foo\" + \"bar\""),
+            Ok(Value::String("foobar".to_string()))
+        );
+    }
+
+    #[test]
+    fn comparison_equality_and_not() {
+        assert_eq!(evaluate_alone("1 < 2 == !false"), Ok(Value::Bool(true)));
+        assert_eq!(evaluate_alone("2 >= 3"), Ok(Value::Bool(false)));
+        assert_eq!(evaluate_alone("nil == nil"), Ok(Value::Bool(true)));
+        assert_eq!(evaluate_alone("1 != \"1\""), Ok(Value::Bool(true)));
+        assert_eq!(evaluate_alone("!nil"), Ok(Value::Bool(true)));
+        assert_eq!(evaluate_alone("!0"), Ok(Value::Bool(false)));
+        assert_eq!(evaluate_alone("(1, 2)"), Ok(Value::Number(2.0)));
+    }
+
+    #[test]
+    fn only_nil_and_false_are_falsey() {
+        let cases = [
+            (Value::Nil, false),
+            (Value::Bool(false), false),
+            (Value::Bool(true), true),
+            (Value::Number(0.0), true),
+            (Value::Number(-1.5), true),
+            (Value::String(String::new()), true),
+            (Value::String("false".to_string()), true),
+        ];
+        for (value, truthy) in cases.iter() {
+            assert_eq!(value.is_truthy(), *truthy, "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn equality_never_coerces_across_types() {
+        // every value equals itself and nothing else in the table
+        let values = [
+            Value::Nil,
+            Value::Bool(false),
+            Value::Bool(true),
+            Value::Number(0.0),
+            Value::Number(1.0),
+            Value::String(String::new()),
+            Value::String("0".to_string()),
+            Value::String("1".to_string()),
+            Value::String("nil".to_string()),
+            Value::String("false".to_string()),
+        ];
+        for (i, a) in values.iter().enumerate() {
+            for (j, b) in values.iter().enumerate() {
+                assert_eq!(a.is_equal(b), i == j, "{:?} == {:?}", a, b);
+            }
+        }
+        assert!(Value::Number(0.0).is_equal(&Value::Number(-0.0)));
+        assert!(!Value::Number(f64::NAN).is_equal(&Value::Number(f64::NAN)));
+    }
+
+    #[test]
+    fn variables_are_defined_read_and_assigned() {
+        let interpreter = Interpreter::default();
+        let program = parse_program("var a = 1;\nvar b;\nvar c = a = a + 2;").ast;
+        interpret(&interpreter, &program).unwrap();
+        assert_eq!(evaluate(&interpreter, "a").unwrap(), Value::Number(3.0));
+        assert_eq!(evaluate(&interpreter, "b").unwrap(), Value::Nil);
+        assert_eq!(evaluate(&interpreter, "c").unwrap(), Value::Number(3.0));
+    }
+
+    #[test]
+    fn blocks_scope_their_declarations_even_when_they_fail() {
+        let interpreter = Interpreter::default();
+        let program = parse_program("var a = 1;\n{ var a = 2; var b = a; }").ast;
+        interpret(&interpreter, &program).unwrap();
+        let failing = parse_program("{ var a = 3; a = -\"3\"; }").ast;
+        assert!(interpret(&interpreter, &failing).is_err());
+        assert_eq!(evaluate(&interpreter, "a").unwrap(), Value::Number(1.0));
+        assert!(evaluate(&interpreter, "b").is_err());
+    }
+
+    #[test]
+    fn if_runs_only_the_selected_branch() {
+        let interpreter = Interpreter::default();
+        let program = parse_program(
+            "var log = \"\";\n\
+             if (0) log = log + \"then\"; else log = log + \"else\";\n\
+             if (nil) log = log + \"then\"; else log = log + \"else\";\n\
+             if (false) log = log + \"skipped\";\n\
+             if (true) {} else print -\"never evaluated\";",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        assert_eq!(
+            evaluate(&interpreter, "log").unwrap(),
+            Value::String("thenelse".to_string())
+        );
+    }
+
+    #[test]
+    fn logical_operators_short_circuit_and_return_an_operand() {
+        assert_eq!(
+            evaluate_alone("\"hi\" or 2"),
+            Ok(Value::String("hi".to_string()))
+        );
+        assert_eq!(
+            evaluate_alone("nil or \"yes\""),
+            Ok(Value::String("yes".to_string()))
+        );
+        assert_eq!(evaluate_alone("nil and 1"), Ok(Value::Nil));
+        assert_eq!(evaluate_alone("0 and false"), Ok(Value::Bool(false)));
+        // the right operand would be a runtime error if it were ever evaluated
+        assert_eq!(evaluate_alone("true or -\"x\""), Ok(Value::Bool(true)));
+        assert_eq!(evaluate_alone("false and -\"x\""), Ok(Value::Bool(false)));
+        assert_eq!(
+            evaluate_alone("false or -\"x\""),
+            Err("Operand must be a number.".to_string())
+        );
+    }
+
+    #[test]
+    fn while_loops_until_the_condition_is_falsey() {
+        let interpreter = Interpreter::default();
+        let program = parse_program(
+            "var i = 0;\n\
+             var sum = 0;\n\
+             {\n\
+               while (i < 5) { var step = i; sum = sum + step; i = i + 1; }\n\
+             }\n\
+             while (false) i = -\"never\";",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        assert_eq!(evaluate(&interpreter, "i").unwrap(), Value::Number(5.0));
+        assert_eq!(evaluate(&interpreter, "sum").unwrap(), Value::Number(10.0));
+    }
+
+    #[test]
+    fn for_loops_run_with_any_clause_left_empty() {
+        let interpreter = Interpreter::default();
+        let program = parse_program(
+            "var n = 0;\n\
+             for (var i = 0; i < 3; i = i + 1) n = n + 1;\n\
+             var j = 0;\n\
+             for (; j < 4;) j = j + 1;\n\
+             for (j = 0; j < 2;) j = j + 1;",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        assert_eq!(evaluate(&interpreter, "n").unwrap(), Value::Number(3.0));
+        assert_eq!(evaluate(&interpreter, "j").unwrap(), Value::Number(2.0));
+        // the loop variable was scoped to its loop
+        assert!(evaluate(&interpreter, "i").is_err());
+    }
+
+    #[test]
+    fn break_leaves_only_the_innermost_loop() {
+        let interpreter = Interpreter::default();
+        let program = parse_program(
+            "var log = \"\";\n\
+             for (var i = 0; i < 3; i = i + 1) {\n\
+               var j = 0;\n\
+               while (true) {\n\
+                 if (j == i) { break; }\n\
+                 log = log + \"j\";\n\
+                 j = j + 1;\n\
+               }\n\
+               log = log + \"i\";\n\
+             }\n\
+             var n = 0;\n\
+             for (;;) { n = n + 1; if (n == 4) break; }",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        assert_eq!(
+            evaluate(&interpreter, "log").unwrap(),
+            Value::String("ijijji".to_string())
+        );
+        assert_eq!(evaluate(&interpreter, "n").unwrap(), Value::Number(4.0));
+    }
+
+    #[test]
+    fn continue_skips_the_rest_of_the_body_but_not_the_increment() {
+        let interpreter = Interpreter::default();
+        let program = parse_program(
+            "var odd = 0;\n\
+             for (var i = 0; i < 6; i = i + 1) {\n\
+               if (i == 0 or i == 2 or i == 4) continue;\n\
+               odd = odd + i;\n\
+             }\n\
+             var j = 0;\n\
+             var skipped = 0;\n\
+             while (j < 3) {\n\
+               j = j + 1;\n\
+               { if (j == 2) { skipped = j; continue; } }\n\
+               skipped = skipped - 100;\n\
+             }",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        assert_eq!(evaluate(&interpreter, "odd").unwrap(), Value::Number(9.0));
+        assert_eq!(
+            evaluate(&interpreter, "skipped").unwrap(),
+            Value::Number(-98.0)
+        );
+    }
+
+    #[test]
+    fn functions_are_called_with_their_arguments_bound() {
+        let interpreter = Interpreter::default();
+        let program = parse_program(
+            "var log = \"\";\n\
+             fun add(a, b) { log = log + a + b; }\n\
+             add(\"1\", \"2\");\n\
+             var twice = fun (f) { f(\"a\", \"b\"); f(\"c\", \"d\"); };\n\
+             twice(add);",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        assert_eq!(
+            evaluate(&interpreter, "log").unwrap(),
+            Value::String("12abcd".to_string())
+        );
+        assert_eq!(
+            evaluate(&interpreter, "add(\"\", \"\")").unwrap(),
+            Value::Nil
+        );
+        assert_eq!(
+            evaluate(&interpreter, "add").unwrap().to_string(),
+            "<fn add>"
+        );
+        assert_eq!(evaluate(&interpreter, "twice").unwrap().to_string(), "<fn>");
+        assert_eq!(
+            evaluate(&interpreter, "add == add").unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            evaluate(&interpreter, "add == twice").unwrap(),
+            Value::Bool(false)
+        );
+        // parameters are local to the call
+        assert!(evaluate(&interpreter, "a").is_err());
+    }
+
+    #[test]
+    fn return_unwinds_out_of_blocks_and_loops_to_the_call() {
+        let interpreter = Interpreter::default();
+        let program = parse_program(
+            "fun find(limit) {\n\
+               for (var i = 0; i < limit; i = i + 1) {\n\
+                 { while (true) { if (i * i > 10) return i; break; } }\n\
+               }\n\
+               return;\n\
+             }\n\
+             fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }\n\
+             fun nothing() {}\n\
+             var after = 0;\n\
+             while (after < 2) { find(10); after = after + 1; }",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        assert_eq!(
+            evaluate(&interpreter, "find(10)").unwrap(),
+            Value::Number(4.0)
+        );
+        assert_eq!(evaluate(&interpreter, "find(2)").unwrap(), Value::Nil);
+        assert_eq!(evaluate(&interpreter, "nothing()").unwrap(), Value::Nil);
+        assert_eq!(
+            evaluate(&interpreter, "fib(10)").unwrap(),
+            Value::Number(55.0)
+        );
+        // a return inside the called function did not end the caller's loop
+        assert_eq!(evaluate(&interpreter, "after").unwrap(), Value::Number(2.0));
+    }
+
+    #[test]
+    fn closures_keep_the_environment_they_were_declared_in() {
+        let interpreter = Interpreter::default();
+        let program = parse_program(
+            "fun makeCounter() {\n\
+               var i = 0;\n\
+               fun count() { i = i + 1; return i; }\n\
+               return count;\n\
+             }\n\
+             var a = makeCounter();\n\
+             var b = makeCounter();\n\
+             a(); a(); b();\n\
+             var get; var set;\n\
+             {\n\
+               var shared = \"before\";\n\
+               get = fun () { return shared; };\n\
+               set = fun (value) { shared = value; };\n\
+             }\n\
+             set(\"after\");\n\
+             fun adder(x) { return fun (y) { return x + y; }; }",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        // each counter has its own `i`, which outlives the call that declared it
+        assert_eq!(evaluate(&interpreter, "a()").unwrap(), Value::Number(3.0));
+        assert_eq!(evaluate(&interpreter, "b()").unwrap(), Value::Number(2.0));
+        // closures over the same variable see each other's assignments
+        assert_eq!(
+            evaluate(&interpreter, "get()").unwrap(),
+            Value::String("after".to_string())
+        );
+        assert_eq!(
+            evaluate(&interpreter, "adder(1)(2)").unwrap(),
+            Value::Number(3.0)
+        );
+        assert!(evaluate(&interpreter, "shared").is_err());
+    }
+
+    #[test]
+    fn state_persists_across_programs_interpreted_one_after_another() {
+        // like REPL lines: each program is parsed and resolved alone, then dropped
+        let interpreter = Interpreter::default();
+        let lines = [
+            "var total = 0;",
+            "fun add(n) { var sum = total + n; total = sum; }",
+            "{ var i = 1; add(i); add(i + 1); }",
+            "add(total);",
+        ];
+        for line in lines.iter() {
+            interpret(&interpreter, &parse_program(line).ast).unwrap();
+        }
+        assert_eq!(evaluate(&interpreter, "total").unwrap(), Value::Number(6.0));
+    }
+
+    #[test]
+    fn completions_cover_keywords_and_the_globals_defined_so_far() {
+        let interpreter = Interpreter::default();
+        assert_eq!(
+            interpreter.completions("c"),
+            vec!["class", "clock", "continue"]
+        );
+        let program = parse_program("var count = 0;\nclass Cat {}\n{ var cold = 1; }").ast;
+        interpret(&interpreter, &program).unwrap();
+        assert_eq!(
+            interpreter.completions("c"),
+            vec!["class", "clock", "continue", "count"]
+        );
+        assert_eq!(interpreter.completions("Ca"), vec!["Cat"]);
+        assert!(interpreter.completions("zz").is_empty());
+    }
+
+    #[test]
+    fn define_global_binds_a_value_programs_can_use() {
+        let interpreter = Interpreter::default();
+        interpreter.define_global("_", Value::Number(20.0));
+        let program = parse_program("var a = _ * 2;\nfun f() { return _; }").ast;
+        interpret(&interpreter, &program).unwrap();
+        interpreter.define_global("_", Value::Nil);
+        assert_eq!(
+            evaluate(&interpreter, "f() == nil and a == 40").unwrap(),
+            Value::Bool(true)
+        );
+    }
+
+    #[test]
+    fn reset_leaves_only_the_natives_but_keeps_the_options() {
+        let interpreter = Interpreter::default().with_division_by_zero_errors(true);
+        let program = parse_program("var a = 1;\nfun f() { return a; }").ast;
+        interpret(&interpreter, &program).unwrap();
+        interpreter.reset();
+        assert_eq!(interpreter.completions("a"), vec!["and"]);
+        assert_eq!(interpreter.completions("f"), vec!["false", "for", "fun"]);
+        assert!(interpret(&interpreter, &parse_program("print a;").ast).is_err());
+        assert!(interpret(&interpreter, &parse_program("var b = 1 / 0;").ast).is_err());
+        let program = parse_program("var a = clock();\n{ var b = a; a = b; }").ast;
+        assert!(interpret(&interpreter, &program).is_ok());
+    }
+
+    #[test]
+    fn classes_create_instances_with_their_own_fields() {
+        let interpreter = Interpreter::default();
+        let program = parse_program(
+            "class Bagel {}\n\
+             var a = Bagel();\n\
+             var b = Bagel();\n\
+             a.flavor = \"plain\";\n\
+             b.flavor = a.flavor + \" and more\";\n\
+             a.next = b;\n\
+             a.next.flavor = \"sesame\";",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        assert_eq!(
+            evaluate(&interpreter, "Bagel").unwrap().to_string(),
+            "Bagel"
+        );
+        assert_eq!(
+            evaluate(&interpreter, "a").unwrap().to_string(),
+            "Bagel instance"
+        );
+        assert_eq!(
+            evaluate(&interpreter, "a.flavor").unwrap(),
+            Value::String("plain".to_string())
+        );
+        assert_eq!(
+            evaluate(&interpreter, "b.flavor").unwrap(),
+            Value::String("sesame".to_string())
+        );
+        assert_eq!(
+            evaluate(&interpreter, "a.next == b").unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(
+            evaluate(&interpreter, "a == b").unwrap(),
+            Value::Bool(false)
+        );
+        assert_eq!(
+            evaluate(&interpreter, "Bagel == Bagel").unwrap(),
+            Value::Bool(true)
+        );
+        let message = |source: &str| match evaluate(&interpreter, source) {
+            Err(RloxError::RuntimeError(e)) => e.message().to_string(),
+            _ => panic!("expected a runtime error"),
+        };
+        assert_eq!(message("a.size"), "Undefined property 'size'.");
+        assert_eq!(message("Bagel.size"), "Only instances have properties.");
+        assert_eq!(message("nil.size = 1"), "Only instances have fields.");
+        assert_eq!(message("Bagel(1)"), "Expected 0 arguments but got 1.");
+    }
+
+    #[test]
+    fn methods_are_bound_to_the_instance_they_were_read_from() {
+        let interpreter = Interpreter::default();
+        let program = parse_program(
+            "class Person {\n\
+               greet(greeting) { return greeting + \", \" + this.name; }\n\
+               later() { return fun () { return this.name; }; }\n\
+             }\n\
+             var jane = Person();\n\
+             jane.name = \"Jane\";\n\
+             var bill = Person();\n\
+             bill.name = \"Bill\";\n\
+             var greet = jane.greet;\n\
+             bill.greet = jane.greet;",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        let string = |s: &str| Value::String(s.to_string());
+        assert_eq!(
+            evaluate(&interpreter, "jane.greet(\"Hi\")").unwrap(),
+            string("Hi, Jane")
+        );
+        assert_eq!(
+            evaluate(&interpreter, "greet(\"Hello\")").unwrap(),
+            string("Hello, Jane")
+        );
+        // a method stored in a field remembers its original receiver
+        assert_eq!(
+            evaluate(&interpreter, "bill.greet(\"Hey\")").unwrap(),
+            string("Hey, Jane")
+        );
+        assert_eq!(
+            evaluate(&interpreter, "bill.later()()").unwrap(),
+            string("Bill")
+        );
+        assert_eq!(
+            evaluate(&interpreter, "jane.greet").unwrap().to_string(),
+            "<fn greet>"
+        );
+    }
+
+    #[test]
+    fn init_runs_on_instantiation_and_always_returns_the_instance() {
+        let interpreter = Interpreter::default();
+        let program = parse_program(
+            "class Point {\n\
+               init(x, y) {\n\
+                 this.x = x;\n\
+                 if (y == nil) return;\n\
+                 this.y = y;\n\
+               }\n\
+             }\n\
+             var p = Point(1, 2);\n\
+             var q = Point(3, nil);",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        assert_eq!(
+            evaluate(&interpreter, "p.x + p.y").unwrap(),
+            Value::Number(3.0)
+        );
+        assert_eq!(evaluate(&interpreter, "q.x").unwrap(), Value::Number(3.0));
+        assert!(evaluate(&interpreter, "q.y").is_err());
+        // calling init directly re-runs it and still yields the instance
+        assert_eq!(
+            evaluate(&interpreter, "p.init(5, nil) == p").unwrap(),
+            Value::Bool(true)
+        );
+        assert_eq!(evaluate(&interpreter, "p.x").unwrap(), Value::Number(5.0));
+        match evaluate(&interpreter, "Point(1)") {
+            Err(RloxError::RuntimeError(e)) => {
+                assert_eq!(e.message(), "Expected 2 arguments but got 1.")
+            }
+            _ => panic!("expected a runtime error"),
+        }
+    }
+
+    #[test]
+    fn subclasses_inherit_methods_and_call_super() {
+        let interpreter = Interpreter::default();
+        let program = parse_program(
+            "class A {\n\
+               init(name) { this.name = name; }\n\
+               method() { return \"A method\"; }\n\
+               who() { return \"A \" + this.name; }\n\
+             }\n\
+             class B < A {\n\
+               method() { return \"B method\"; }\n\
+               test() { return super.method(); }\n\
+             }\n\
+             class C < B {}\n\
+             var c = C(\"c\");",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        let string = |s: &str| Value::String(s.to_string());
+        assert_eq!(
+            evaluate(&interpreter, "c.method()").unwrap(),
+            string("B method")
+        );
+        assert_eq!(evaluate(&interpreter, "c.who()").unwrap(), string("A c"));
+        // `super` in B's method means A, even though the receiver is a C
+        assert_eq!(
+            evaluate(&interpreter, "c.test()").unwrap(),
+            string("A method")
+        );
+    }
+
+    #[test]
+    fn inheriting_from_a_non_class_is_a_runtime_error() {
+        let program = parse_program(
+            "var NotAClass = \"so not a class\";\n\
+             class Subclass < NotAClass {}",
+        )
+        .ast;
+        match interpret(&Interpreter::default(), &program) {
+            Err(RloxError::RuntimeError(e)) => {
+                assert_eq!(e.message(), "Superclass must be a class.");
+                assert_eq!(e.line_number(), 2);
+            }
+            _ => panic!("expected a runtime error"),
+        }
+    }
+
+    #[test]
+    fn bad_calls_are_runtime_errors() {
+        let interpreter = Interpreter::default();
+        let program = parse_program("fun f(a, b) {}").ast;
+        interpret(&interpreter, &program).unwrap();
+        let error = |source: &str| match evaluate(&interpreter, source) {
+            Err(RloxError::RuntimeError(e)) => e.message().to_string(),
+            _ => panic!("expected a runtime error"),
+        };
+        assert_eq!(error("f(1)"), "Expected 2 arguments but got 1.");
+        assert_eq!(error("f(1, 2, 3)"), "Expected 2 arguments but got 3.");
+        assert_eq!(error("\"f\"()"), "Can only call functions and classes.");
+        assert_eq!(error("nil(1)"), "Can only call functions and classes.");
+    }
+
+    #[test]
+    fn recursion_past_the_call_depth_limit_is_a_stack_overflow() {
+        let interpreter = Interpreter::default().with_max_call_depth(50);
+        let program = parse_program(
+            "fun count(n) {\n  if (n == 0) return 0;\n  return count(n - 1) + 1;\n}\nvar a = count(49);",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        let program = parse_program("a = count(50);").ast;
+        match interpret(&interpreter, &program) {
+            Err(RloxError::RuntimeError(e)) => {
+                assert_eq!(e.message(), "Stack overflow.");
+                assert_eq!(e.line_number(), 3);
+            }
+            _ => panic!("expected a runtime error"),
+        }
+        // the calls the error unwound out of no longer count
+        assert_eq!(
+            evaluate(&interpreter, "count(49)").unwrap(),
+            Value::Number(49.0)
+        );
+    }
+
+    #[test]
+    fn runtime_errors_in_calls_trace_each_frame_out_to_the_script() {
+        let source =
+            "fun inner() {\n  return -nil;\n}\nfun outer() {\n  var a = 1;\n  return inner();\n}\n\
+                      class Box {\n  init() {\n    outer();\n  }\n}\nBox();";
+        let program = parse_program(source).ast;
+        let trace = |interpreter: Interpreter| match interpret(&interpreter, &program) {
+            Err(RloxError::RuntimeError(e)) => e
+                .trace()
+                .iter()
+                .map(|(function, line)| format!("{}:{}", function, line))
+                .collect::<Vec<_>>(),
+            _ => panic!("expected a runtime error"),
+        };
+        assert_eq!(
+            trace(Interpreter::default()),
+            vec!["inner():2", "outer():6", "Box.init():10", "script:13"]
+        );
+        // a tail call replaces the frame it was made from
+        assert_eq!(
+            trace(Interpreter::default().with_tail_calls(true)),
+            vec!["inner():2", "Box.init():10", "script:13"]
+        );
+        match interpret(&Interpreter::default(), &parse_program("-nil;").ast) {
+            Err(RloxError::RuntimeError(e)) => assert!(e.trace().is_empty()),
+            _ => panic!("expected a runtime error"),
+        }
+    }
+
+    #[test]
+    fn tail_calls_run_in_constant_stack_space_when_enabled() {
+        let source = "fun countdown(n) {\n  if (n == 0) return \"liftoff\";\n  return countdown(n - 1);\n}\n\
+                      var a = countdown(1000000);";
+        let program = parse_program(source).ast;
+        let interpreter = Interpreter::default().with_tail_calls(true);
+        interpret(&interpreter, &program).unwrap();
+        assert_eq!(
+            evaluate(&interpreter, "a").unwrap(),
+            Value::String("liftoff".to_string())
+        );
+        let interpreter = Interpreter::default().with_max_call_depth(50);
+        match interpret(&interpreter, &program) {
+            Err(RloxError::RuntimeError(e)) => assert_eq!(e.message(), "Stack overflow."),
+            _ => panic!("expected a runtime error"),
+        }
+    }
+
+    #[test]
+    fn tail_calls_keep_their_results_and_errors() {
+        let interpreter = Interpreter::default().with_tail_calls(true);
+        let program = parse_program(
+            "fun add(a, b) {\n  return a + b;\n}\nfun twice(x) {\n  return add(x, x);\n}\n\
+             class Point {\n  init(x) {\n    this.x = x;\n  }\n}\nfun point() {\n  return Point(3);\n}",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        assert_eq!(
+            evaluate(&interpreter, "twice(2) + 1").unwrap(),
+            Value::Number(5.0)
+        );
+        assert_eq!(
+            evaluate(&interpreter, "point().x").unwrap(),
+            Value::Number(3.0)
+        );
+        assert_eq!(
+            evaluate(&interpreter, "twice(\"a\")").unwrap(),
+            Value::String("aa".to_string())
+        );
+        match evaluate(&interpreter, "twice(nil)") {
+            Err(RloxError::RuntimeError(e)) => {
+                assert_eq!(e.line_number(), 2);
+                assert_eq!(e.message(), "Operands must be two numbers or two strings.");
+            }
+            _ => panic!("expected a runtime error"),
+        }
+    }
+
+    #[test]
+    fn undefined_variables_are_runtime_errors() {
+        assert_eq!(
+            evaluate_alone("x"),
+            Err("Undefined variable 'x'.".to_string())
+        );
+        assert_eq!(
+            evaluate_alone("x = 1"),
+            Err("Undefined variable 'x'.".to_string())
+        );
+    }
+
+    #[test]
+    fn values_print_with_lox_formatting() {
+        let printed = [
+            Value::Number(3.0),
+            Value::Number(-0.5),
+            Value::Number(1.0 / 0.0),
+            Value::String("hi".to_string()),
+            Value::Bool(false),
+            Value::Nil,
+        ]
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>();
+        assert_eq!(printed, vec!["3", "-0.5", "Infinity", "hi", "false", "nil"]);
+    }
+
+    #[test]
+    fn pretty_values_show_their_type_and_cut_deep_instances_short() {
+        let interpreter = Interpreter::default();
+        let program = parse_program(
+            "class Node {\n  init(value) {\n    this.value = value;\n    this.next = this;\n  }\n}\n\
+             class Empty {}\n\
+             fun add(a, b) { return a + b; }",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        let pretty = |source: &str| evaluate(&interpreter, source).unwrap().pretty();
+        assert_eq!(pretty("\"1\""), "\"1\"");
+        assert_eq!(pretty("1"), "1");
+        assert_eq!(pretty("nil"), "nil");
+        assert_eq!(pretty("add"), "<fn add/2>");
+        assert_eq!(pretty("clock"), "<fn clock/0>");
+        assert_eq!(pretty("fun (x) { return x; }"), "<fn/1>");
+        assert_eq!(pretty("Node"), "<class Node>");
+        assert_eq!(pretty("Empty()"), "Empty {}");
+        assert_eq!(
+            pretty("Node(\"a\")"),
+            "Node { next: Node { next: Node { next: Node { ... }, value: \"a\" }, value: \"a\" }, \
+             value: \"a\" }"
+        );
+        let program = parse_program(
+            "var wide = Empty();\n\
+             for (var i = 0; i < 10; i = i + 1) wide.f = i;\n\
+             wide.a = 1; wide.b = 2; wide.c = 3; wide.d = 4; wide.e = 5; wide.g = 6; wide.h = 7;\n\
+             wide.i = 8; wide.j = 9;",
+        )
+        .ast;
+        interpret(&interpreter, &program).unwrap();
+        assert_eq!(
+            pretty("wide"),
+            "Empty { a: 1, b: 2, c: 3, d: 4, e: 5, f: 9, g: 6, h: 7, ... }"
+        );
+        // `print` is unchanged
+        assert_eq!(
+            evaluate(&interpreter, "Node(\"a\")").unwrap().to_string(),
+            "Node instance"
+        );
+    }
+
+    #[test]
+    fn division_by_zero_is_infinity_unless_configured_as_an_error() {
+        assert_eq!(evaluate_alone("1 / 0"), Ok(Value::Number(f64::INFINITY)));
+        assert_eq!(
+            evaluate_alone("-1 / 0"),
+            Ok(Value::Number(f64::NEG_INFINITY))
+        );
+        let interpreter = Interpreter::default().with_division_by_zero_errors(true);
+        let program = parse_program("var a = 0;\nvar b = 6 / 3;\nb = 1 /\n  a;").ast;
+        match interpret(&interpreter, &program) {
+            Err(RloxError::RuntimeError(e)) => {
+                assert_eq!(e.message(), "Division by zero.");
+                assert_eq!(e.line_number(), 3);
+            }
+            _ => panic!("expected a runtime error"),
+        }
+        assert!(evaluate(&interpreter, "0 / -0").is_err());
+    }
+
+    #[test]
+    fn string_coercion_mode_concatenates_strings_with_numbers() {
+        assert_eq!(
+            evaluate_alone("\"scone\" + 4"),
+            Err("Operands must be two numbers or two strings.".to_string())
+        );
+        let interpreter = Interpreter::default().with_string_coercion(true);
+        let string = |s: &str| Value::String(s.to_string());
+        assert_eq!(
+            evaluate(&interpreter, "\"scone\" + 4").unwrap(),
+            string("scone4")
+        );
+        assert_eq!(
+            evaluate(&interpreter, "2.5 + \"x\"").unwrap(),
+            string("2.5x")
+        );
+        assert_eq!(
+            evaluate(&interpreter, "1 + 2 + \"3\"").unwrap(),
+            string("33")
+        );
+        assert_eq!(evaluate(&interpreter, "1 + 2").unwrap(), Value::Number(3.0));
+        // only numbers are coerced
+        assert!(evaluate(&interpreter, "\"a\" + nil").is_err());
+        assert!(evaluate(&interpreter, "true + \"a\"").is_err());
+    }
+
+    #[test]
+    fn uninitialized_mode_rejects_reads_before_assignment() {
+        let source = "var a;\nvar b = a;";
+        let program = parse_program(source).ast;
+        assert!(interpret(&Interpreter::default(), &program).is_ok());
+        let interpreter = Interpreter::default().with_uninitialized_errors(true);
+        match interpret(&interpreter, &program) {
+            Err(RloxError::RuntimeError(e)) => {
+                assert_eq!(e.message(), "Uninitialized variable 'a'.");
+                assert_eq!(e.line_number(), 2);
+            }
+            _ => panic!("expected a runtime error"),
+        }
+        // assigning, even nil, initializes the variable
+        let program = parse_program("var c;\nc = nil;\nvar d = c;").ast;
+        assert!(interpret(&interpreter, &program).is_ok());
+    }
+
+    #[test]
+    fn runtime_errors_carry_the_operator_line() {
+        match evaluate(&Interpreter::default(), "1 +\n\n  (2 <\n \"x\")") {
+            Err(RloxError::RuntimeError(e)) => {
+                assert_eq!(e.token().lexeme(), "<");
+                assert_eq!(e.line_number(), 3);
+                assert_eq!(e.to_string(), "Operands must be numbers.\n[line 3]");
+            }
+            _ => panic!("expected a runtime error"),
+        }
+    }
+
+    #[test]
+    fn mismatched_operands_are_runtime_errors() {
+        assert_eq!(
+            evaluate_alone("1 + \"a\""),
+            Err("Operands must be two numbers or two strings.".to_string())
+        );
+        assert_eq!(
+            evaluate_alone("\"a\" < 1"),
+            Err("Operands must be numbers.".to_string())
+        );
+        assert_eq!(
+            evaluate_alone("-true"),
+            Err("Operand must be a number.".to_string())
+        );
+    }
+}
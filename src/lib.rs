@@ -1,6 +1,25 @@
 pub mod error;
+pub mod diagnostic;
+pub mod error_codes;
 pub mod token;
 pub mod scanner;
+pub mod preprocessor;
 pub mod expr;
+pub mod stmt;
+pub mod parser;
+pub mod resolver;
+pub mod interpreter;
+pub mod environment;
+pub mod callable;
+pub mod natives;
+pub mod class;
+pub mod hooks;
+pub mod debugger;
 pub mod ast_printer;
 pub mod ast_printer_rpn;
+pub mod ast_json;
+pub mod ast_dot;
+pub mod config;
+pub mod expectations;
+
+pub use parser::{parse_expression, parse_program, Parsed};
@@ -1,55 +1,1471 @@
 use std::env;
+use std::ffi::OsString;
 use std::fs;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
 
-mod error;
-mod scanner;
-mod token;
-mod expr;
-mod parser;
-mod ast_printer;     // example visitor impl
-mod ast_printer_rpn; // another example visitor impl
+use rlox::ast_dot::AstDotPrinter;
+use rlox::ast_json::AstJsonPrinter;
+use rlox::callable::LoxCallable;
+use rlox::config::{Config, CONFIG_FILE_NAMES};
+use rlox::debugger::Debugger;
+use rlox::diagnostic::{render, to_json};
+use rlox::error::RloxError;
+use rlox::error_codes::lookup;
+use rlox::expectations::{Expectations, Outcome};
+use rlox::hooks::InterpreterHooks;
+use rlox::interpreter::{Interpreter, Value, DEFAULT_MAX_CALL_DEPTH};
+use rlox::natives::define_script_arguments;
+use rlox::parser::DEFAULT_MAX_NESTING_DEPTH;
+use rlox::preprocessor::{parse_define, preprocess, Defines};
+use rlox::resolver::resolve;
+use rlox::scanner::{normalize_source, Scanner};
+use rlox::stmt::{ExpressionStmt, Stmt};
+use rlox::token::Literal;
+use rlox::{parse_expression, parse_program};
 
-use crate::scanner::Scanner;
-use crate::error::RloxError;
+/**
+ * Scripts larger than this are refused before they are read. A script is held in memory whole,
+ *   along with its tokens, so an arbitrarily large file would otherwise end in an out-of-memory abort.
+ */
+const MAX_SCRIPT_BYTES: u64 = 64 * 1024 * 1024;
 
-fn main() -> Result<(), RloxError> {
+/**
+ * The stack scripts run on. Every Lox call recurses on the native stack, and an unoptimized build
+ *   needs tens of kilobytes per call, so the main thread's stack would overflow well before
+ *   DEFAULT_MAX_CALL_DEPTH calls. The memory is only reserved, not committed, until it is used.
+ */
+const STACK_BYTES: usize = 512 * 1024 * 1024;
+
+// how often `run --watch` looks at the script's modification time
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
+// how many lines of REPL history are kept when RLOX_HISTORY_SIZE does not say
+const DEFAULT_HISTORY_SIZE: usize = 1000;
+
+fn main() {
     let args = env::args().skip(1).collect::<Vec<_>>();
-    execute(args)
+    let script = thread::Builder::new()
+        .stack_size(STACK_BYTES)
+        .spawn(move || execute(args))
+        .expect("failed to start the interpreter thread");
+    match script.join() {
+        Ok(Err(e)) => exit_with(&[e]),
+        Ok(Ok(())) => {}
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+// reports every error, then exits with the sysexits.h code for the first, or the code a script
+//   gave `exit`
+fn exit_with(errors: &[RloxError]) -> ! {
+    report(errors);
+    std::process::exit(match errors.first() {
+        Some(RloxError::IoError(_)) => 74,
+        Some(RloxError::SyntaxError(_)) => 65,
+        Some(RloxError::RuntimeError(_)) => 70,
+        Some(RloxError::Exit(code)) => *code,
+        None => 0,
+    });
+}
+
+// prints each error on stderr, for people or as JSON; a script calling `exit` is not an error
+fn report(errors: &[RloxError]) {
+    for e in errors {
+        match e {
+            RloxError::Exit(_) => {}
+            e if JSON_ERRORS.load(Ordering::Relaxed) => eprintln!("{}", to_json(e)),
+            e => eprintln!("{}", render(e, COLOR.load(Ordering::Relaxed))),
+        }
+    }
+}
+
+// whether diagnostics are written as JSON, one object a line; set from `--error-format`
+static JSON_ERRORS: AtomicBool = AtomicBool::new(false);
+
+fn set_error_format(format: &str) {
+    let json = match format {
+        "human" => false,
+        "json" => true,
+        _ => usage(),
+    };
+    JSON_ERRORS.store(json, Ordering::Relaxed);
+}
+
+// whether diagnostics on stderr are colored; set once, from `--color`, before anything runs
+static COLOR: AtomicBool = AtomicBool::new(false);
+
+/**
+ * Whether `--color=when` colors diagnostics: `always`, `never`, or for `auto` only when they go
+ *   to a terminal and NO_COLOR is unset or empty. None for any other `when`.
+ */
+fn color_wanted(when: &str, terminal: bool, no_color: Option<OsString>) -> Option<bool> {
+    match when {
+        "always" => Some(true),
+        "never" => Some(false),
+        "auto" => Some(terminal && no_color.is_none_or(|setting| setting.is_empty())),
+        _ => None,
+    }
+}
+
+fn set_color(when: &str) {
+    let terminal = io::stderr().is_terminal();
+    let color = color_wanted(when, terminal, env::var_os("NO_COLOR")).unwrap_or_else(|| usage());
+    COLOR.store(color, Ordering::Relaxed);
+}
+
+/**
+ * Takes `arg` into `options` if it is one every command takes, each written as one argument:
+ *   `--color=WHEN` or `--error-format=FORMAT`. False if it is neither.
+ */
+fn parse_global(arg: &str, options: &mut Options) -> bool {
+    if let Some(when) = arg.strip_prefix("--color=") {
+        options.color = Some(when.to_string());
+    } else if let Some(format) = arg.strip_prefix("--error-format=") {
+        options.error_format = Some(format.to_string());
+    } else {
+        return false;
+    }
+    true
+}
+
+// the options every command takes that come before the command, and the arguments after them
+fn split_globals(mut args: Vec<String>) -> (Options, Vec<String>) {
+    let mut options = Options::default();
+    while args
+        .first()
+        .is_some_and(|arg| parse_global(arg, &mut options))
+    {
+        args.remove(0);
+    }
+    (options, args)
+}
+
+fn set_globals(options: &Options) {
+    if let Some(when) = &options.color {
+        set_color(when);
+    }
+    if let Some(format) = &options.error_format {
+        set_error_format(format);
+    }
 }
 
 pub(crate) fn execute(args: Vec<String>) -> Result<(), RloxError> {
-    match args.len() {
-        l if l > 1 => {
-            println!("Usage: rlox [script]");
-            std::process::exit(64);
+    let (globals, args) = split_globals(args);
+    set_color("auto");
+    set_globals(&globals);
+    let (command, rest) = match args.split_first() {
+        Some((command, rest)) => (command.as_str(), rest),
+        None => return execute_shorthand(args),
+    };
+    let accepted: &[&str] = match command {
+        "run" => &[
+            "--define",
+            "--division-by-zero-error",
+            "--prelude",
+            "--deterministic",
+            "--seed",
+            "-v",
+            "-vv",
+            "-e",
+            "--eval",
+            "--watch",
+        ],
+        "repl" => &[
+            "--define",
+            "--division-by-zero-error",
+            "--prelude",
+            "--deterministic",
+            "--seed",
+            "-v",
+            "-vv",
+            "--prompt",
+        ],
+        "debug" => &[
+            "--define",
+            "--division-by-zero-error",
+            "--prelude",
+            "--deterministic",
+            "--seed",
+        ],
+        "check" | "tokens" => &["--define"],
+        "ast" => &["--define", "--json", "--dot"],
+        "test" | "config" | "help" => &[],
+        "--help" | "-h" => help(),
+        "--explain" => return explain(rest),
+        _ => return execute_shorthand(args),
+    };
+    let options = parse_options(rest, accepted).unwrap_or_else(|| usage());
+    set_globals(&options);
+    VERBOSITY.store(options.verbosity, Ordering::Relaxed);
+    let start = |config: &Config| {
+        start_interpreter(config, &options, None).unwrap_or_else(|errors| exit_with(&errors))
+    };
+    match (command, options.operands.as_slice()) {
+        ("run", []) if !options.watch => match &options.eval {
+            Some(snippet) => run_snippet(snippet, &options.defines, &start(&load_config())),
+            None => usage(),
+        },
+        ("run", [file_path, arguments @ ..]) if options.watch && file_path != "-" => {
+            watch(file_path, arguments, &load_config(), &options)
+        }
+        ("run", [file_path, arguments @ ..]) if options.eval.is_none() && !options.watch => {
+            let interpreter = start(&load_config());
+            run_file(file_path, arguments, &options.defines, &interpreter)
+        }
+        ("repl", []) => {
+            let prompt = options.prompt.as_deref().unwrap_or("> ");
+            run_repl(&options.defines, &start(&load_config()), prompt)
+        }
+        ("debug", [file_path, arguments @ ..]) if file_path != "-" => {
+            debug(file_path, arguments, &options)
+        }
+        ("check", [file_path]) => check(file_path, &options.defines),
+        ("tokens", [file_path]) => dump_tokens(file_path, &options.defines),
+        ("ast", [file_path]) => match options.dot {
+            true => dump_ast(file_path, &options.defines, AstFormat::Dot),
+            false => dump_ast(file_path, &options.defines, AstFormat::Json),
+        },
+        ("test", [dir]) => run_tests(Path::new(dir)),
+        ("config", []) => {
+            println!("{}", config_json());
+            Ok(())
+        }
+        ("help", []) => help(),
+        _ => usage(),
+    }
+}
+
+/**
+ * The forms rlox took before it had subcommands, still accepted so that scripts and CI calling
+ *   it keep working: `rlox script`, a bare `rlox` for the REPL, `-e`, `--check`, `--dump-tokens`,
+ *   `--dump-ast`, `ast --dot` and `--print-config`, with options anywhere among the arguments.
+ */
+fn execute_shorthand(mut args: Vec<String>) -> Result<(), RloxError> {
+    // the options end at the first operand; the script path and everything after it are left alone
+    let operands = args.split_off(first_operand(&args));
+    let (defines, args) = match split_defines(args) {
+        Some(split) => split,
+        None => usage(),
+    };
+    let (division_by_zero_errors, args) = split_flag(args, "--division-by-zero-error");
+    let (deterministic, args) = split_flag(args, "--deterministic");
+    let (seed, args) = match split_option(args, "--seed") {
+        Some((None, args)) => (None, args),
+        Some((Some(seed), args)) => (Some(seed.parse().unwrap_or_else(|_| usage())), args),
+        None => usage(),
+    };
+    let (verbose, args) = split_flag(args, "-v");
+    let (very_verbose, args) = split_flag(args, "-vv");
+    VERBOSITY.store(
+        if very_verbose { 2 } else { verbose as usize },
+        Ordering::Relaxed,
+    );
+    let (prompt, args) = match split_option(args, "--prompt") {
+        Some((prompt, args)) => (prompt.unwrap_or_else(|| "> ".to_string()), args),
+        None => usage(),
+    };
+    let args = [args, operands].concat();
+    let options = Options {
+        defines,
+        division_by_zero_errors,
+        deterministic,
+        seed,
+        ..Options::default()
+    };
+    let start = || {
+        start_interpreter(&load_config(), &options, None)
+            .unwrap_or_else(|errors| exit_with(&errors))
+    };
+    match args.as_slice() {
+        [flag] if flag == "--print-config" => {
+            println!("{}", config_json());
+            Ok(())
+        }
+        [flag, file_path] if flag == "--check" => check(file_path, &options.defines),
+        [flag, file_path] if flag == "--dump-tokens" => dump_tokens(file_path, &options.defines),
+        [flag, file_path] if flag == "--dump-ast" => {
+            dump_ast(file_path, &options.defines, AstFormat::Json)
+        }
+        [command, flag, file_path] if command == "ast" && flag == "--dot" => {
+            dump_ast(file_path, &options.defines, AstFormat::Dot)
+        }
+        [flag, snippet] if flag == "-e" || flag == "--eval" => {
+            run_snippet(snippet, &options.defines, &start())
+        }
+        [flag] if flag == "-e" || flag == "--eval" => usage(),
+        [file_path, arguments @ ..] => run_file(file_path, arguments, &options.defines, &start()),
+        // piped input is a script, as for `rlox -`; only a terminal gets the REPL
+        [] if !io::stdin().is_terminal() => run_file("-", &[], &options.defines, &start()),
+        [] => run_repl(&options.defines, &start(), &prompt),
+    }
+}
+
+const HELP: &str = "\
+Usage: rlox [--color=WHEN] [--error-format=FORMAT] <command> [options] [operands]
+
+Commands:
+  run [options] (script | -) [ARG]...  run a script, or standard input, passing it the ARGs
+  run [options] (-e | --eval) SOURCE   run SOURCE given on the command line
+  run [options] --watch script [ARG]...
+                                       run a script again each time it is saved
+  repl [options] [--prompt PROMPT]     start an interactive session
+  debug [options] script [ARG]...      run a script under a debugger taking commands on
+                                       standard input; `help` lists them
+  check [--define NAME[=value]]... script
+                                       scan, parse and resolve a script without running it
+  ast [--define NAME[=value]]... [--json | --dot] script
+                                       print a script's syntax tree, as JSON by default
+  tokens [--define NAME[=value]]... script
+                                       print the tokens a script scans into
+  test DIRECTORY                       check the .lox scripts under DIRECTORY against their
+                                       `// expect` comments
+  config                               print this build's configuration as JSON
+  --explain CODE                       describe the error with CODE, e.g. E0203, at length,
+                                       with an example and how to fix it
+  help                                 print this message
+
+Options for every command:
+  --color=WHEN                         color diagnostics: auto, the default, only when stderr
+                                       is a terminal and NO_COLOR is not set; always; or never
+  --error-format=FORMAT                write diagnostics for people, human, the default, or as
+                                       json, an object a line with each one's code, severity,
+                                       message, file, span and notes
+
+Options for run, repl and debug (which takes neither -v nor -vv):
+  --define NAME[=value]                define NAME, as 1 if no value is given, for `#if`
+  --division-by-zero-error             make division by zero a runtime error, not infinity
+  --prelude FILE                       run FILE first, into the same globals; may be repeated,
+                                       and follows any in RLOX_PRELUDE, a `:`-separated list
+  --deterministic                      pin clock() at 0 and start random() from the seed, 0 unless
+                                       --seed says otherwise, so every run prints the same
+  --seed N                             start random() from N rather than from the time
+  -v, -vv                              describe each phase on stderr, and with -vv every call
+                                       and new environment too
+
+Defaults for run and repl are read from rlox.toml, or else .rloxrc, in the working directory:
+  backend = \"tree-walk\"                the only backend so far
+  division_by_zero_error = false       as --division-by-zero-error
+  max_call_depth = 1024                calls deeper than this are a stack overflow; at most
+                                       10000, which the interpreter's stack can hold
+  tail_calls = false                   run `return f(...);` without nesting a new call
+  string_coercion = false              let `+` join a string and a number
+  uninitialized_errors = false         make reading a variable before assigning it an error
+  prelude = [\"lib.lox\"]                run these first, before any other preludes
+
+Options come before a command's operands. `rlox script` is short for `rlox run script`, and
+`rlox` on its own for `rlox repl`, or, when standard input is not a terminal, `rlox run -`.";
+
+fn help() -> ! {
+    println!("{}", HELP);
+    std::process::exit(0);
+}
+
+fn usage() -> ! {
+    eprintln!("{}", HELP);
+    std::process::exit(64);
+}
+
+/**
+ * The options given to a subcommand. They end at its first operand, so that the arguments after
+ *   a script's path are passed to the script as they are.
+ */
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    defines: Defines,
+    division_by_zero_errors: bool,
+    prompt: Option<String>,
+    eval: Option<String>,
+    dot: bool,
+    watch: bool,
+    preludes: Vec<String>,
+    verbosity: usize,
+    deterministic: bool,
+    seed: Option<u64>,
+    color: Option<String>,
+    error_format: Option<String>,
+    operands: Vec<String>,
+}
+
+// None if an option is not one of those `accepted`, is malformed or is missing its value
+fn parse_options(args: &[String], accepted: &[&str]) -> Option<Options> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') || arg == "-" {
+            options.operands.push(arg.clone());
+            options.operands.extend(args.cloned());
+            break;
+        }
+        if parse_global(arg, &mut options) {
+            continue;
+        }
+        if !accepted.contains(&arg.as_str()) {
+            return None;
+        }
+        match arg.as_str() {
+            "--define" => {
+                let (name, value) = parse_define(args.next()?)?;
+                options.defines.insert(name, value);
+            }
+            "--division-by-zero-error" => options.division_by_zero_errors = true,
+            "--prompt" => options.prompt = Some(args.next()?.clone()),
+            "-e" | "--eval" => options.eval = Some(args.next()?.clone()),
+            "--json" => options.dot = false,
+            "--dot" => options.dot = true,
+            "--watch" => options.watch = true,
+            "--prelude" => options.preludes.push(args.next()?.clone()),
+            "-v" => options.verbosity = options.verbosity.max(1),
+            "-vv" => options.verbosity = 2,
+            "--deterministic" => options.deterministic = true,
+            "--seed" => options.seed = Some(args.next()?.parse().ok()?),
+            _ => return None,
+        }
+    }
+    Some(options)
+}
+
+// pulls every `--define NAME[=value]` pair out of the arguments, or None if one is malformed
+fn split_defines(args: Vec<String>) -> Option<(Defines, Vec<String>)> {
+    let mut defines = Defines::new();
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--define" => {
+                let (name, value) = parse_define(&args.next()?)?;
+                defines.insert(name, value);
+            }
+            _ => rest.push(arg),
+        }
+    }
+    Some((defines, rest))
+}
+
+/**
+ * Describes this build for scripts and CI to assert on. None of the optional features exist yet,
+ *   so each is reported as disabled rather than left out.
+ */
+fn config_json() -> String {
+    let features = ["vm", "nan-boxing", "serde", "lsp"]
+        .iter()
+        .map(|f| format!("\"{}\":false", f))
+        .collect::<Vec<_>>();
+    let extensions = [
+        "lambdas",
+        "adjacent-string-concatenation",
+        "conditional-compilation",
+        "repl-heredoc",
+    ]
+    .iter()
+    .map(|e| format!("\"{}\"", e))
+    .collect::<Vec<_>>();
+    format!(
+        "{{\"version\":\"{}\",\"features\":{{{}}},\"extensions\":[{}],\
+         \"limits\":{{\"max_script_bytes\":{},\"max_nesting_depth\":{},\
+         \"max_call_depth\":{}}}}}",
+        env!("CARGO_PKG_VERSION"),
+        features.join(","),
+        extensions.join(","),
+        MAX_SCRIPT_BYTES,
+        DEFAULT_MAX_NESTING_DEPTH,
+        DEFAULT_MAX_CALL_DEPTH
+    )
+}
+
+// where the shorthand's options end: the first argument that is neither an option nor an option's
+//   value
+fn first_operand(args: &[String]) -> usize {
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if !arg.starts_with('-') || arg == "-" {
+            return i;
+        }
+        i += match arg.as_str() {
+            "--define" | "--seed" | "--prompt" | "-e" | "--eval" | "--check" | "--dump-tokens"
+            | "--dump-ast" => 2,
+            _ => 1,
+        };
+    }
+    args.len()
+}
+
+// removes every occurrence of a boolean flag from the arguments, reporting whether there was one
+fn split_flag(args: Vec<String>, flag: &str) -> (bool, Vec<String>) {
+    let (found, rest): (Vec<_>, Vec<_>) = args.into_iter().partition(|arg| arg == flag);
+    (!found.is_empty(), rest)
+}
+
+// removes every `flag VALUE` pair from the arguments, keeping the last value, or None if one has
+//   no value after it
+fn split_option(args: Vec<String>, flag: &str) -> Option<(Option<String>, Vec<String>)> {
+    let mut value = None;
+    let mut rest = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg == flag {
+            true => value = Some(args.next()?),
+            false => rest.push(arg),
+        }
+    }
+    Some((value, rest))
+}
+
+/**
+ * Reads a script and resolves its `#if` sections against the `--define`s. A `file_path` of `-`
+ *   reads the whole of standard input instead, so scripts can come from pipes and heredocs.
+ */
+fn read_script(file_path: &str, defines: &Defines) -> Result<String, RloxError> {
+    trace(&format!("reading {}", file_path));
+    let data = match file_path {
+        "-" => read_stdin_script()?,
+        _ => {
+            let size = fs::metadata(file_path)?.len();
+            if size > MAX_SCRIPT_BYTES {
+                let msg = format!(
+                    "{} is {} bytes, more than the {} byte limit for scripts",
+                    file_path, size, MAX_SCRIPT_BYTES
+                );
+                return Err(io::Error::new(io::ErrorKind::InvalidData, msg).into());
+            }
+            fs::read_to_string(file_path)?
+        }
+    };
+    preprocess(&normalize_source(&data), defines)
+}
+
+// standard input has no size to check up front, so it is read up to one byte past the limit
+fn read_stdin_script() -> io::Result<String> {
+    let mut data = String::new();
+    let size = io::stdin()
+        .lock()
+        .take(MAX_SCRIPT_BYTES + 1)
+        .read_to_string(&mut data)? as u64;
+    if size > MAX_SCRIPT_BYTES {
+        let msg = format!(
+            "standard input is more than the {} byte limit for scripts",
+            MAX_SCRIPT_BYTES
+        );
+        return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+    }
+    Ok(data)
+}
+
+fn run_file(
+    file_path: &str,
+    arguments: &[String],
+    defines: &Defines,
+    interpreter: &Interpreter,
+) -> Result<(), RloxError> {
+    let source = read_script(file_path, defines)?;
+    define_script_arguments(interpreter, arguments.to_vec());
+    if let Err(errors) = run(file_path, &source, interpreter) {
+        exit_with(&errors);
+    }
+    Ok(())
+}
+
+// runs a script stopped at its first line, for the debugger's commands to take it from there
+fn debug(file_path: &str, arguments: &[String], options: &Options) -> Result<(), RloxError> {
+    let source = read_script(file_path, &options.defines)?;
+    let debugger = Rc::new(Debugger::new(
+        &source,
+        Box::new(io::BufReader::new(io::stdin())),
+        Box::new(io::stdout()),
+    ));
+    let interpreter = start_interpreter(&load_config(), options, Some(debugger.clone()))
+        .unwrap_or_else(|errors| exit_with(&errors));
+    let interpreter = Rc::new(interpreter);
+    debugger.attach(&interpreter);
+    define_script_arguments(&interpreter, arguments.to_vec());
+    if let Err(errors) = run(file_path, &source, &interpreter) {
+        exit_with(&errors);
+    }
+    Ok(())
+}
+
+/**
+ * Runs every `.lox` script under `dir` in its own rlox process and checks it against its
+ *   `// expect` comments, then reports how many passed. Exits with 1 if any failed.
+ */
+fn run_tests(dir: &Path) -> Result<(), RloxError> {
+    let mut scripts = Vec::new();
+    collect_scripts(dir, &mut scripts)?;
+    let rlox = env::current_exe()?;
+    let mut failed = 0;
+    for script in &scripts {
+        let expectations = Expectations::parse(&fs::read_to_string(script)?);
+        let output = Command::new(&rlox).arg(script).output()?;
+        let failures = expectations.check(&Outcome {
+            stdout: &String::from_utf8_lossy(&output.stdout),
+            stderr: &String::from_utf8_lossy(&output.stderr),
+            exit_code: output.status.code(),
+        });
+        if !failures.is_empty() {
+            failed += 1;
+            println!("FAIL {}", script.display());
+            for failure in failures {
+                println!("    {}", failure);
+            }
+        }
+    }
+    println!("{} passed, {} failed", scripts.len() - failed, failed);
+    if failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// every `.lox` file under `dir`, in sorted order so runs are reproducible
+fn collect_scripts(dir: &Path, scripts: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_scripts(&path, scripts)?;
+        } else if path.extension().is_some_and(|e| e == "lox") {
+            scripts.push(path);
         }
-        1 => run_file(&args[0]),
-        _ => run_repl(),
     }
+    Ok(())
+}
+
+/**
+ * Runs the script in a new interpreter, with the screen cleared first, whenever its modification
+ *   time changes. Errors are reported but do not end the watch; only interrupting rlox does.
+ */
+fn watch(
+    file_path: &str,
+    arguments: &[String],
+    config: &Config,
+    options: &Options,
+) -> Result<(), RloxError> {
+    let mut last_modified = None;
+    loop {
+        let modified = fs::metadata(file_path).and_then(|m| m.modified()).ok();
+        if modified != last_modified {
+            last_modified = modified;
+            print!("\x1b[2J\x1b[H");
+            let result = start_interpreter(config, options, None).and_then(|interpreter| {
+                define_script_arguments(&interpreter, arguments.to_vec());
+                let source = read_script(file_path, &options.defines).map_err(|e| vec![e])?;
+                run(file_path, &source, &interpreter)
+            });
+            if let Err(errors) = result {
+                report(&errors);
+            }
+            io::stdout().flush()?;
+        }
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+/**
+ * The project's settings from the first of CONFIG_FILE_NAMES in the working directory, or the
+ *   defaults if there is none. A file that cannot be read or parsed ends rlox with EX_CONFIG.
+ */
+fn load_config() -> Config {
+    let path = match CONFIG_FILE_NAMES
+        .iter()
+        .find(|name| Path::new(name).is_file())
+    {
+        Some(path) => path,
+        None => return Config::default(),
+    };
+    match fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|s| Config::parse(&s))
+    {
+        Ok(config) => config,
+        Err(message) => {
+            eprintln!("error in {}: {}", path, message);
+            std::process::exit(78);
+        }
+    }
+}
+
+/**
+ * An interpreter configured by `config` and then the command line, with the preludes run into
+ *   it: the config's first, then RLOX_PRELUDE's, then those given with `--prelude`.
+ */
+// `hooks` are installed if given, or else the tracer if -vv asked for one
+fn start_interpreter(
+    config: &Config,
+    options: &Options,
+    hooks: Option<Rc<dyn InterpreterHooks>>,
+) -> Result<Interpreter, Vec<RloxError>> {
+    let mut interpreter = config.configure(Interpreter::default());
+    if options.division_by_zero_errors {
+        interpreter = interpreter.with_division_by_zero_errors(true);
+    }
+    match (options.seed, options.deterministic) {
+        (Some(seed), _) => interpreter = interpreter.with_random_seed(seed),
+        (None, true) => interpreter = interpreter.with_random_seed(0),
+        (None, false) => {}
+    }
+    if options.deterministic {
+        interpreter = interpreter.with_fixed_clock(0.0);
+    }
+    match hooks {
+        Some(hooks) => interpreter = interpreter.with_hooks(hooks),
+        None if verbosity() >= 2 => interpreter = interpreter.with_hooks(Rc::new(Tracer)),
+        None => {}
+    }
+    let from_environment =
+        prelude_paths(env::var("RLOX_PRELUDE").ok().as_deref(), &options.preludes);
+    let preludes = config.prelude.iter().cloned().chain(from_environment);
+    run_preludes(
+        &preludes.collect::<Vec<_>>(),
+        &options.defines,
+        &interpreter,
+    )?;
+    Ok(interpreter)
 }
 
-fn run_file(file_path: &str) -> Result<(), RloxError> {
-    let data = fs::read_to_string(&file_path)?;
-    run(data)
+// RLOX_PRELUDE's `:`-separated files, then those given with `--prelude`, in the order to run them
+fn prelude_paths(setting: Option<&str>, flags: &[String]) -> Vec<String> {
+    let from_setting = setting.into_iter().flat_map(|paths| paths.split(':'));
+    from_setting
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .chain(flags.iter().cloned())
+        .collect()
+}
+
+// runs each prelude into the interpreter's globals, stopping at the first to fail
+fn run_preludes(
+    paths: &[String],
+    defines: &Defines,
+    interpreter: &Interpreter,
+) -> Result<(), Vec<RloxError>> {
+    for path in paths {
+        let source = read_script(path, defines).map_err(|e| vec![e])?;
+        run(path, &source, interpreter)?;
+    }
+    Ok(())
+}
+
+// runs source given on the command line as if it were the whole of a script
+fn run_snippet(
+    snippet: &str,
+    defines: &Defines,
+    interpreter: &Interpreter,
+) -> Result<(), RloxError> {
+    let source = preprocess(&normalize_source(snippet), defines)?;
+    if let Err(errors) = run("<eval>", &source, interpreter) {
+        exit_with(&errors);
+    }
+    Ok(())
+}
+
+// scans, parses and resolves the script, reporting every diagnostic without running any of it
+fn check(file_path: &str, defines: &Defines) -> Result<(), RloxError> {
+    let source = read_script(file_path, defines)?;
+    let parsed = parse_program(&source);
+    if !parsed.diagnostics.is_empty() {
+        exit_with(&in_file(parsed.diagnostics, file_path, &source));
+    }
+    if let Err(errors) = resolve(&parsed.ast) {
+        exit_with(&in_file(errors, file_path, &source));
+    }
+    Ok(())
+}
+
+// prints the longer description of an error code that rlox reports errors under
+fn explain(args: &[String]) -> Result<(), RloxError> {
+    let code = match args {
+        [code] => code,
+        _ => usage(),
+    };
+    match lookup(code) {
+        Some(error) => println!("{}: {}\n\n{}", error.code, error.title, error.explanation),
+        None => {
+            eprintln!("{} is not an rlox error code", code);
+            std::process::exit(64);
+        }
+    }
+    Ok(())
 }
 
-fn run(source: String) -> Result<(), RloxError> {
-    let scanner = Scanner::new(source);
+// prints what the scanner made of the script, one `type lexeme literal line:column` per line
+fn dump_tokens(file_path: &str, defines: &Defines) -> Result<(), RloxError> {
+    let source = read_script(file_path, defines)?;
+    let (scanner, errors) = Scanner::scan(source.clone());
+    if !errors.is_empty() {
+        exit_with(&in_file(errors, file_path, &source));
+    }
     for token in scanner.tokens() {
-        println!("{}", token);
+        let literal = match token.literal() {
+            Some(Literal::String(s)) => format!("{:?}", s),
+            Some(Literal::Float(n)) => n.to_string(),
+            None => "-".to_string(),
+        };
+        println!(
+            "{:?} {:?} {} {}:{}",
+            token.token_type(),
+            token.lexeme(),
+            literal,
+            token.line_number(),
+            token.column()
+        );
+    }
+    Ok(())
+}
+
+enum AstFormat {
+    Json,
+    Dot,
+}
+
+// prints the parsed AST of the script instead of running it
+fn dump_ast(file_path: &str, defines: &Defines, format: AstFormat) -> Result<(), RloxError> {
+    let data = read_script(file_path, defines)?;
+    let parsed = parse_program(&data);
+    if !parsed.diagnostics.is_empty() {
+        exit_with(&in_file(parsed.diagnostics, file_path, &data));
+    }
+    match format {
+        AstFormat::Json => println!("{}", AstJsonPrinter::default().print_program(&parsed.ast)),
+        AstFormat::Dot => print!("{}", AstDotPrinter::default().print_program(&parsed.ast)),
+    }
+    Ok(())
+}
+
+/**
+ * Scans, parses, resolves and then interprets the source, read from `file_path`. Compile errors
+ *   are all returned together and nothing runs; execution stops at the first runtime error.
+ */
+fn run(file_path: &str, source: &str, interpreter: &Interpreter) -> Result<(), Vec<RloxError>> {
+    if verbosity() >= 1 {
+        if let Ok(scanner) = Scanner::new(source.to_string()) {
+            trace(&format!("scanned {} tokens", scanner.tokens().len()));
+        }
+    }
+    let parsed = parse_program(source);
+    if !parsed.diagnostics.is_empty() {
+        return Err(in_file(parsed.diagnostics, file_path, source));
+    }
+    if verbosity() >= 1 {
+        let printer = AstDotPrinter::default();
+        printer.print_program(&parsed.ast);
+        trace(&format!(
+            "parsed {} statements, {} AST nodes",
+            parsed.ast.len(),
+            printer.node_count() - 1
+        ));
+    }
+    run_statements(&parsed.ast, interpreter).map_err(|errors| in_file(errors, file_path, source))
+}
+
+// errors found in the source read from `file_path`, for syntax errors to be shown in context
+fn in_file(errors: Vec<RloxError>, file_path: &str, source: &str) -> Vec<RloxError> {
+    let file = match file_path {
+        "-" => "<stdin>",
+        _ => file_path,
+    };
+    errors
+        .into_iter()
+        .map(|e| e.in_file(file, source))
+        .collect()
+}
+
+fn run_statements(statements: &[Stmt], interpreter: &Interpreter) -> Result<(), Vec<RloxError>> {
+    let locals = resolve(statements)?;
+    trace(&format!(
+        "resolved {} local variable references",
+        locals.len()
+    ));
+    let (result, elapsed) = timed(|| interpreter.interpret(statements, locals));
+    trace(&format!("interpreted in {:?}", elapsed));
+    result.map_err(|e| vec![e])
+}
+
+/**
+ * How much rlox says about what it is doing, on stderr: 0 says nothing, 1 (`-v`) each phase of
+ *   running a script and what it produced, and 2 (`-vv`) every call and new environment as well.
+ *   It is set once, before anything runs.
+ */
+static VERBOSITY: AtomicUsize = AtomicUsize::new(0);
+
+fn verbosity() -> usize {
+    VERBOSITY.load(Ordering::Relaxed)
+}
+
+// reports a phase of running a script when rlox is verbose at all
+fn trace(message: &str) {
+    if verbosity() >= 1 {
+        eprintln!("[rlox] {}", message);
+    }
+}
+
+// reports every call and new environment, for `-vv`
+struct Tracer;
+
+impl InterpreterHooks for Tracer {
+    fn function_call(&self, callee: &dyn LoxCallable, arguments: &[Value]) {
+        let arguments = arguments.iter().map(Value::pretty).collect::<Vec<_>>();
+        trace(&format!(
+            "call {} with ({})",
+            callee.frame(),
+            arguments.join(", ")
+        ));
+    }
+
+    fn function_return(&self, callee: &dyn LoxCallable, value: Option<&Value>) {
+        match value {
+            Some(value) => trace(&format!(
+                "return {} from {}",
+                value.pretty(),
+                callee.frame()
+            )),
+            None => trace(&format!("unwind from {}", callee.frame())),
+        }
     }
+
+    fn environment_create(&self) {
+        trace("create environment");
+    }
+}
+
+// REPL input that is a bare expression, e.g. `1 + 2` with no `print` or `;`, has its value printed
+//   by `Value::pretty`, which tells apart e.g. the string "1" and the number 1, and kept in `_`
+fn run_repl_input(source: &str, interpreter: &Interpreter) -> Result<(), Vec<RloxError>> {
+    let parsed = parse_expression(source);
+    let expr = match parsed.ast {
+        Some(expr) if parsed.diagnostics.is_empty() => expr,
+        // the input is an expression, but one the parser had to recover from
+        Some(_) => return Err(in_file(parsed.diagnostics, "<repl>", source)),
+        None => return run("<repl>", source, interpreter),
+    };
+    let locals = resolve(&[ExpressionStmt::new(expr.clone())])
+        .map_err(|errors| in_file(errors, "<repl>", source))?;
+    let value = interpreter
+        .interpret_expression(&expr, locals)
+        .map_err(|e| vec![e])?;
+    println!("{}", value.pretty());
+    interpreter.define_global("_", value);
     Ok(())
 }
 
-fn run_repl() -> Result<(), RloxError> {
-    let stdin = std::io::stdin();
+/**
+ * Reads, runs and prints until `exit` or the end of input. `prompt` is shown before each input,
+ *   with `{line}` in it replaced by the input's number, counting from 1.
+ */
+fn run_repl(defines: &Defines, interpreter: &Interpreter, prompt: &str) -> Result<(), RloxError> {
+    let stdin = io::stdin();
+    // only a person at a terminal needs telling what this is
+    if stdin.is_terminal() {
+        println!("{}", banner());
+    }
+    let mut input = stdin.lock();
+    let mut history = History::load(
+        history_path(env::var_os("XDG_DATA_HOME"), env::var_os("HOME")),
+        history_size(env::var("RLOX_HISTORY_SIZE").ok().as_deref()),
+    );
+    let mut timing = false;
+    let mut line = 0;
     loop {
-        print!("> ");
+        line += 1;
+        print!("{}", prompt.replace("{line}", &line.to_string()));
+        // stdout is line-buffered, and the prompt has no newline to flush it
+        io::stdout().flush()?;
         let mut buffer = String::new();
-        stdin.read_line(&mut buffer)?;
-        if buffer == "exit" {
+        match input.read_line(&mut buffer) {
+            // end of input, e.g. Ctrl-D, ends the session like `exit`, on a line of its own
+            Ok(0) => {
+                println!();
+                break Ok(());
+            }
+            Ok(_) => {}
+            // a line that is not UTF-8 is reported and skipped, like any other bad input
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                eprintln!("{}", e);
+                continue;
+            }
+            Err(e) => break Err(e.into()),
+        }
+        if is_exit_command(&buffer) {
             break Ok(());
         }
-        let _result = run(buffer);
+        // drops every definition the session has made, as if it had just started
+        if buffer.trim() == ":reset" {
+            history.add(&buffer);
+            interpreter.reset();
+            continue;
+        }
+        if let Some(enabled) = time_command(&buffer) {
+            history.add(&buffer);
+            timing = enabled;
+            continue;
+        }
+        let (result, elapsed) = match load_command(&buffer) {
+            Some(file_path) => {
+                history.add(&buffer);
+                timed(|| {
+                    read_script(file_path, defines)
+                        .map_err(|e| vec![e])
+                        .and_then(|source| run(file_path, &source, interpreter))
+                })
+            }
+            None => {
+                if let Some(terminator) = heredoc_terminator(&buffer) {
+                    buffer = read_heredoc(&mut input, &terminator)?;
+                } else {
+                    buffer = read_continuation(&mut input, buffer)?;
+                }
+                history.add(&buffer);
+                timed(|| run_repl_input(&buffer, interpreter))
+            }
+        };
+        match result {
+            Err(errors) if matches!(errors.first(), Some(RloxError::Exit(_))) => exit_with(&errors),
+            Err(errors) => report(&errors),
+            Ok(()) => {}
+        }
+        if timing {
+            println!("[{:?}]", elapsed);
+        }
+    }
+}
+
+/**
+ * A line of the form `<<TAG` starts heredoc input: every following line is taken verbatim,
+ *   bypassing line-at-a-time evaluation, until a line consisting of just `TAG` is read.
+ */
+fn heredoc_terminator(line: &str) -> Option<String> {
+    let tag = line.trim().strip_prefix("<<")?.trim();
+    match tag.is_empty() {
+        true => None,
+        false => Some(tag.to_string()),
+    }
+}
+
+fn read_heredoc<R: BufRead>(input: &mut R, terminator: &str) -> io::Result<String> {
+    let mut source = String::new();
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 || line.trim_end() == terminator {
+            return Ok(source);
+        }
+        source.push_str(&line);
+    }
+}
+
+/**
+ * Where REPL history is kept between sessions: `$XDG_DATA_HOME/rlox/history` when that is set,
+ *   otherwise `~/.rlox_history`. None when neither variable is set.
+ */
+fn history_path(xdg_data_home: Option<OsString>, home: Option<OsString>) -> Option<PathBuf> {
+    match (xdg_data_home.filter(|dir| !dir.is_empty()), home) {
+        (Some(dir), _) => Some(PathBuf::from(dir).join("rlox").join("history")),
+        (None, Some(home)) => Some(PathBuf::from(home).join(".rlox_history")),
+        (None, None) => None,
+    }
+}
+
+// RLOX_HISTORY_SIZE, the number of most recent lines to keep; 0 keeps no history at all
+fn history_size(setting: Option<&str>) -> usize {
+    setting
+        .and_then(|size| size.trim().parse().ok())
+        .unwrap_or(DEFAULT_HISTORY_SIZE)
+}
+
+/**
+ * The lines typed into the REPL, oldest first, read from the history file when the session starts
+ *   and written back after every input, so a session that is killed still keeps its history.
+ *   History is a convenience: a file that cannot be read or written is simply not used.
+ */
+struct History {
+    path: Option<PathBuf>,
+    lines: Vec<String>,
+    limit: usize,
+}
+
+impl History {
+    fn load(path: Option<PathBuf>, limit: usize) -> Self {
+        let lines = match (&path, limit) {
+            (_, 0) | (None, _) => Vec::new(),
+            (Some(path), _) => fs::read_to_string(path)
+                .map(|saved| saved.lines().map(|line| line.to_string()).collect())
+                .unwrap_or_default(),
+        };
+        let mut history = History { path, lines, limit };
+        history.truncate();
+        history
+    }
+
+    // each line of the input is a line of history; blank lines are not kept
+    fn add(&mut self, input: &str) {
+        if self.limit == 0 {
+            return;
+        }
+        let lines = input.lines().filter(|line| !line.trim().is_empty());
+        self.lines.extend(lines.map(|line| line.to_string()));
+        self.truncate();
+        let _ = self.save();
+    }
+
+    fn truncate(&mut self) {
+        let excess = self.lines.len().saturating_sub(self.limit);
+        self.lines.drain(..excess);
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut saved = self.lines.join("\n");
+        saved.push('\n');
+        fs::write(path, saved)
+    }
+}
+
+fn banner() -> String {
+    let build = match cfg!(debug_assertions) {
+        true => "debug",
+        false => "release",
+    };
+    format!(
+        "rlox {} ({} build)\nType exit or press Ctrl-D to quit.",
+        env!("CARGO_PKG_VERSION"),
+        build
+    )
+}
+
+// `:time on` and `:time off` turn on and off showing how long each input took to run
+fn time_command(line: &str) -> Option<bool> {
+    match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [":time", "on"] => Some(true),
+        [":time", "off"] => Some(false),
+        _ => None,
+    }
+}
+
+fn timed<T>(f: impl FnOnce() -> T) -> (T, Duration) {
+    let started = Instant::now();
+    let result = f();
+    (result, started.elapsed())
+}
+
+// `read_line` keeps the line's newline, so the command is matched on the trimmed line
+fn is_exit_command(line: &str) -> bool {
+    matches!(line.trim(), "exit" | "exit()" | ":quit")
+}
+
+// `:load path` runs a script inside the session, so whatever it defines stays available
+fn load_command(line: &str) -> Option<&str> {
+    let file_path = line.trim().strip_prefix(":load ")?.trim();
+    match file_path.is_empty() {
+        true => None,
+        false => Some(file_path),
+    }
+}
+
+/**
+ * Whether REPL input stopped partway through, e.g. inside an unclosed `{` or `(`, a string, or a
+ *   statement still missing its `;`. A bare expression is complete without a `;`.
+ */
+fn is_incomplete(source: &str) -> bool {
+    if parse_expression(source).ast.is_some() {
+        return false;
+    }
+    parse_program(source).diagnostics.iter().any(|e| match e {
+        RloxError::SyntaxError(e) => {
+            e.description().starts_with("at end:") || e.description() == "Unterminated string."
+        }
+        _ => false,
+    })
+}
+
+// reads lines onto incomplete input until it is complete; a blank line, or the end of input,
+//   gives up and leaves the input to be reported as it is
+fn read_continuation<R: BufRead>(input: &mut R, mut source: String) -> io::Result<String> {
+    while is_incomplete(&source) {
+        print!(".. ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        source.push_str(&line);
+    }
+    Ok(source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        color_wanted, config_json, first_operand, heredoc_terminator, history_path, history_size,
+        is_exit_command, is_incomplete, load_command, parse_options, prelude_paths,
+        read_continuation, read_heredoc, split_defines, split_flag, split_globals, split_option,
+        time_command, History,
+    };
+    use std::ffi::OsString;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn color_is_wanted_as_asked_or_on_terminals_without_no_color() {
+        assert_eq!(color_wanted("always", false, None), Some(true));
+        assert_eq!(color_wanted("never", true, None), Some(false));
+        assert_eq!(color_wanted("auto", true, None), Some(true));
+        assert_eq!(color_wanted("auto", false, None), Some(false));
+        assert_eq!(
+            color_wanted("auto", true, Some(OsString::from("1"))),
+            Some(false)
+        );
+        assert_eq!(
+            color_wanted("auto", true, Some(OsString::new())),
+            Some(true)
+        );
+        assert_eq!(color_wanted("sometimes", true, None), None);
+        let (globals, rest) = split_globals(args(&[
+            "--color=never",
+            "--error-format=json",
+            "run",
+            "--color=always",
+        ]));
+        assert_eq!(globals.color.as_deref(), Some("never"));
+        assert_eq!(globals.error_format.as_deref(), Some("json"));
+        assert_eq!(rest, args(&["run", "--color=always"]));
+    }
+
+    #[test]
+    fn split_defines_removes_every_definition() {
+        let (defines, rest) = split_defines(args(&[
+            "--define",
+            "DEBUG",
+            "--define",
+            "LEVEL=2",
+            "script.lox",
+        ]))
+        .unwrap();
+        assert_eq!(rest, args(&["script.lox"]));
+        assert_eq!(defines.get("DEBUG").map(String::as_str), Some("1"));
+        assert_eq!(defines.get("LEVEL").map(String::as_str), Some("2"));
+        assert!(split_defines(args(&["script.lox", "--define"])).is_none());
+    }
+
+    #[test]
+    fn options_end_at_the_first_operand() {
+        let accepted = ["--define", "--division-by-zero-error", "--prompt"];
+        let options = parse_options(
+            &args(&[
+                "--define",
+                "A=2",
+                "--prompt",
+                "$ ",
+                "script.lox",
+                "--prompt",
+                "-",
+            ]),
+            &accepted,
+        )
+        .unwrap();
+        assert_eq!(options.defines.get("A").map(String::as_str), Some("2"));
+        assert_eq!(options.prompt.as_deref(), Some("$ "));
+        assert!(!options.division_by_zero_errors);
+        assert_eq!(options.operands, args(&["script.lox", "--prompt", "-"]));
+        assert_eq!(
+            parse_options(&args(&["-"]), &[]).unwrap().operands,
+            args(&["-"])
+        );
+        assert!(parse_options(&args(&["--dot", "script.lox"]), &accepted).is_none());
+        assert!(parse_options(&args(&["--prompt"]), &accepted).is_none());
+    }
+
+    #[test]
+    fn preludes_from_the_environment_run_before_those_from_flags() {
+        assert_eq!(
+            prelude_paths(Some("a.lox::b.lox"), &args(&["c.lox", "a.lox"])),
+            args(&["a.lox", "b.lox", "c.lox", "a.lox"])
+        );
+        assert_eq!(prelude_paths(Some(""), &[]), Vec::<String>::new());
+        assert_eq!(prelude_paths(None, &args(&["c.lox"])), args(&["c.lox"]));
+    }
+
+    #[test]
+    fn shorthand_options_end_at_the_first_operand() {
+        assert_eq!(
+            first_operand(&args(&["-v", "s.lox", "-v", "--seed", "3"])),
+            1
+        );
+        assert_eq!(
+            first_operand(&args(&["--seed", "3", "--check", "s.lox", "-v"])),
+            5
+        );
+        assert_eq!(first_operand(&args(&["-e", "print 1;", "-"])), 2);
+        assert_eq!(first_operand(&args(&["ast", "--dot", "s.lox"])), 0);
+        assert_eq!(first_operand(&args(&["--define"])), 1);
+    }
+
+    #[test]
+    fn split_flag_removes_every_occurrence() {
+        let flag = "--division-by-zero-error";
+        assert_eq!(
+            split_flag(args(&[flag, "script.lox", flag]), flag),
+            (true, args(&["script.lox"]))
+        );
+        assert_eq!(
+            split_flag(args(&["script.lox"]), flag),
+            (false, args(&["script.lox"]))
+        );
+    }
+
+    #[test]
+    fn split_option_removes_every_occurrence_and_keeps_the_last_value() {
+        let flag = "--prompt";
+        assert_eq!(
+            split_option(args(&[flag, "$ ", "-x", flag, "{line}> "]), flag),
+            Some((Some("{line}> ".to_string()), args(&["-x"])))
+        );
+        assert_eq!(
+            split_option(args(&["script.lox"]), flag),
+            Some((None, args(&["script.lox"])))
+        );
+        assert_eq!(split_option(args(&[flag]), flag), None);
+    }
+
+    #[test]
+    fn config_json_reports_version_features_and_limits() {
+        assert_eq!(
+            config_json(),
+            format!(
+                "{{\"version\":\"{}\",\
+                 \"features\":{{\"vm\":false,\"nan-boxing\":false,\"serde\":false,\"lsp\":false}},\
+                 \"extensions\":[\"lambdas\",\"adjacent-string-concatenation\",\
+                 \"conditional-compilation\",\"repl-heredoc\"],\
+                 \"limits\":{{\"max_script_bytes\":67108864,\"max_nesting_depth\":256,\
+                 \"max_call_depth\":1024}}}}",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+    }
+
+    #[test]
+    fn heredoc_terminator_requires_a_tag() {
+        assert_eq!(heredoc_terminator("<<EOF\n"), Some("EOF".to_string()));
+        assert_eq!(heredoc_terminator("  << END  \n"), Some("END".to_string()));
+        assert_eq!(heredoc_terminator("<<\n"), None);
+        assert_eq!(heredoc_terminator("print 1 < 2;\n"), None);
+    }
+
+    #[test]
+    fn read_heredoc_collects_lines_verbatim_until_terminator() {
+        let mut input = "var s = \"a\nb\";\n{ print s; }\nEOF\nprint 1;\n".as_bytes();
+        let source = read_heredoc(&mut input, "EOF").unwrap();
+        assert_eq!(source, "var s = \"a\nb\";\n{ print s; }\n");
+    }
+
+    #[test]
+    fn exit_commands_match_with_or_without_the_newline() {
+        for line in ["exit", "exit\n", "exit()\r\n", "  :quit\n"].iter() {
+            assert!(is_exit_command(line), "{:?}", line);
+        }
+        assert!(!is_exit_command("exit;\n"));
+        assert!(!is_exit_command("print exit;\n"));
+    }
+
+    #[test]
+    fn history_lives_under_xdg_data_home_or_else_home() {
+        let dir = |dir: &str| Some(OsString::from(dir));
+        assert_eq!(
+            history_path(dir("/data"), dir("/home/lox")),
+            Some(PathBuf::from("/data/rlox/history"))
+        );
+        assert_eq!(
+            history_path(dir(""), dir("/home/lox")),
+            Some(PathBuf::from("/home/lox/.rlox_history"))
+        );
+        assert_eq!(history_path(None, None), None);
+        assert_eq!(history_size(Some("20")), 20);
+        assert_eq!(history_size(Some("lots")), 1000);
+        assert_eq!(history_size(None), 1000);
+    }
+
+    #[test]
+    fn history_keeps_the_most_recent_lines_across_sessions() {
+        let dir = std::env::temp_dir().join(format!("rlox-history-{}", std::process::id()));
+        let path = dir.join("rlox").join("history");
+        let mut history = History::load(Some(path.clone()), 3);
+        history.add("var a = 1;\n");
+        history.add("fun f() {\n  return a;\n}\n\n");
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "fun f() {\n  return a;\n}\n"
+        );
+        let mut history = History::load(Some(path.clone()), 2);
+        assert_eq!(history.lines, vec!["  return a;", "}"]);
+        history.add("f()\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "}\nf()\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn time_command_turns_timing_on_or_off() {
+        assert_eq!(time_command(":time on\n"), Some(true));
+        assert_eq!(time_command("  :time   off \r\n"), Some(false));
+        assert_eq!(time_command(":time\n"), None);
+        assert_eq!(time_command(":time maybe\n"), None);
+    }
+
+    #[test]
+    fn load_command_takes_the_rest_of_the_line_as_the_path() {
+        assert_eq!(load_command(":load lib.lox\n"), Some("lib.lox"));
+        assert_eq!(
+            load_command("  :load   dir/lib.lox  \r\n"),
+            Some("dir/lib.lox")
+        );
+        assert_eq!(load_command(":load\n"), None);
+        assert_eq!(load_command(":loader x\n"), None);
+        assert_eq!(load_command("print 1;\n"), None);
+    }
+
+    #[test]
+    fn input_is_incomplete_until_it_could_stop_there() {
+        assert!(is_incomplete("fun f() {\n"));
+        assert!(is_incomplete("print (1 +\n"));
+        assert!(is_incomplete("var a = 1\n"));
+        assert!(is_incomplete("print \"a\n"));
+        assert!(!is_incomplete("1 + 2\n"));
+        assert!(!is_incomplete("print 1;\n"));
+        assert!(!is_incomplete("\n"));
+        // a mistake before the end is reported, not waited on
+        assert!(!is_incomplete("print );\n"));
+    }
+
+    #[test]
+    fn read_continuation_reads_until_the_input_is_complete() {
+        let mut input = "  print n;\n}\nprint 2;\n".as_bytes();
+        let source = read_continuation(&mut input, "fun f(n) {\n".to_string()).unwrap();
+        assert_eq!(source, "fun f(n) {\n  print n;\n}\n");
+        let mut input = "\nprint 2;\n".as_bytes();
+        let source = read_continuation(&mut input, "{\n".to_string()).unwrap();
+        assert_eq!(source, "{\n");
+        let mut input = "print 2;\n".as_bytes();
+        let source = read_continuation(&mut input, "print 1;\n".to_string()).unwrap();
+        assert_eq!(source, "print 1;\n");
+    }
+
+    #[test]
+    fn read_heredoc_stops_at_end_of_input() {
+        let mut input = "print 1;\n".as_bytes();
+        assert_eq!(read_heredoc(&mut input, "EOF").unwrap(), "print 1;\n");
     }
 }
@@ -0,0 +1,256 @@
+use std::fmt::{self, Display};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::callable::LoxCallable;
+use crate::environment::Environment;
+use crate::error::RloxError;
+use crate::interpreter::{Interpreter, Value};
+
+/**
+ * A function built into the interpreter rather than written in Lox.
+ */
+#[derive(Clone, Copy)]
+pub struct NativeFunction {
+    name: &'static str,
+    arity: usize,
+    function: fn(&Interpreter, &[Value]) -> Result<Value, RloxError>,
+}
+
+// every native function; each is defined as a global when an interpreter is created
+const NATIVES: [NativeFunction; 3] = [
+    NativeFunction {
+        name: "clock",
+        arity: 0,
+        function: clock,
+    },
+    NativeFunction {
+        name: "random",
+        arity: 0,
+        function: random,
+    },
+    NativeFunction {
+        name: "exit",
+        arity: 1,
+        function: exit,
+    },
+];
+
+pub(crate) fn define_natives(globals: &mut Environment) {
+    for native in NATIVES.iter() {
+        globals.define(native.name, Value::Callable(Rc::new(*native)));
+    }
+}
+
+impl LoxCallable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(self.name)
+    }
+
+    fn frame(&self) -> String {
+        format!("{}()", self.name)
+    }
+
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, RloxError> {
+        (self.function)(interpreter, &arguments)
+    }
+}
+
+impl Display for NativeFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
+
+/**
+ * `argc()` and `argv(n)`, the arguments a script was run with, counted from 0. They stand in for
+ *   a list of the arguments until Lox has lists. `argv` is nil for anything but the index of an
+ *   argument.
+ */
+#[derive(Clone)]
+pub struct ScriptArguments {
+    name: &'static str,
+    arguments: Rc<Vec<String>>,
+}
+
+// defines `argc` and `argv` as globals of `interpreter`, for a script run with `arguments`
+pub fn define_script_arguments(interpreter: &Interpreter, arguments: Vec<String>) {
+    let arguments = Rc::new(arguments);
+    for name in ["argc", "argv"].iter() {
+        let native = ScriptArguments {
+            name,
+            arguments: arguments.clone(),
+        };
+        interpreter.define_global(name, Value::Callable(Rc::new(native)));
+    }
+}
+
+impl LoxCallable for ScriptArguments {
+    fn arity(&self) -> usize {
+        match self.name {
+            "argc" => 0,
+            _ => 1,
+        }
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(self.name)
+    }
+
+    fn frame(&self) -> String {
+        format!("{}()", self.name)
+    }
+
+    fn call(&self, _: &Interpreter, arguments: Vec<Value>) -> Result<Value, RloxError> {
+        Ok(match (self.name, arguments.first()) {
+            ("argc", _) => Value::Number(self.arguments.len() as f64),
+            (_, Some(Value::Number(n))) if n.fract() == 0.0 && *n >= 0.0 => self
+                .arguments
+                .get(*n as usize)
+                .map_or(Value::Nil, |argument| Value::String(argument.clone())),
+            _ => Value::Nil,
+        })
+    }
+}
+
+impl Display for ScriptArguments {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<native fn>")
+    }
+}
+
+// seconds since the Unix epoch, for Lox programs timing themselves, unless the clock is pinned
+fn clock(interpreter: &Interpreter, _: &[Value]) -> Result<Value, RloxError> {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH);
+    let seconds = since_epoch.map_or(0.0, |d| d.as_secs_f64());
+    Ok(Value::Number(interpreter.fixed_clock().unwrap_or(seconds)))
+}
+
+// a number in [0, 1), the next from the interpreter's SplitMix64 sequence
+fn random(interpreter: &Interpreter, _: &[Value]) -> Result<Value, RloxError> {
+    let state = interpreter.random_state();
+    state.set(state.get().wrapping_add(0x9e37_79b9_7f4a_7c15));
+    let mut z = state.get();
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+    Ok(Value::Number((z >> 11) as f64 / (1u64 << 53) as f64))
+}
+
+// ends the program without running anything more, with `code` as its exit status; a fraction is
+//   truncated, and anything but a number exits with 1
+fn exit(_: &Interpreter, arguments: &[Value]) -> Result<Value, RloxError> {
+    match arguments.first() {
+        Some(Value::Number(code)) => Err(RloxError::Exit(*code as i32)),
+        _ => Err(RloxError::Exit(1)),
+    }
+}
+
+// a seed for `random()` that differs from run to run
+pub(crate) fn seed_from_time() -> u64 {
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH);
+    since_epoch.map_or(0, |d| d.as_nanos() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::RloxError;
+    use crate::interpreter::tests::{evaluate, interpret};
+    use crate::interpreter::{Interpreter, Value};
+    use crate::parse_program;
+
+    use super::define_script_arguments;
+
+    #[test]
+    fn clock_is_a_global_counting_seconds_since_the_epoch() {
+        let interpreter = Interpreter::default();
+        let (first, second) = match (
+            evaluate(&interpreter, "clock()").unwrap(),
+            evaluate(&interpreter, "clock()").unwrap(),
+        ) {
+            (Value::Number(first), Value::Number(second)) => (first, second),
+            _ => panic!("expected numbers"),
+        };
+        // 2020-01-01, in seconds rather than milliseconds
+        assert!(first > 1_577_836_800.0 && first < 1_577_836_800_000.0);
+        assert!(second >= first);
+        let clock = evaluate(&interpreter, "clock").unwrap();
+        assert_eq!(clock.to_string(), "<native fn>");
+        assert!(evaluate(&interpreter, "clock(1)").is_err());
+    }
+
+    #[test]
+    fn random_is_in_the_unit_interval_and_repeats_from_a_seed() {
+        let sequence = |interpreter: &Interpreter| {
+            (0..100)
+                .map(|_| match evaluate(interpreter, "random()").unwrap() {
+                    Value::Number(n) => n,
+                    _ => panic!("expected a number"),
+                })
+                .collect::<Vec<_>>()
+        };
+        let seeded = sequence(&Interpreter::default().with_random_seed(7));
+        assert!(seeded.iter().all(|n| (0.0..1.0).contains(n)));
+        assert!(seeded.windows(2).any(|pair| pair[0] != pair[1]));
+        assert_eq!(
+            seeded,
+            sequence(&Interpreter::default().with_random_seed(7))
+        );
+        assert_ne!(
+            seeded,
+            sequence(&Interpreter::default().with_random_seed(8))
+        );
+    }
+
+    #[test]
+    fn exit_unwinds_out_of_the_program_with_its_code() {
+        let interpreter = Interpreter::default();
+        let program = parse_program(
+            "var ran = false;\nfun f() { exit(3.7); ran = true; }\nf();\nran = true;",
+        )
+        .ast;
+        match interpret(&interpreter, &program) {
+            Err(RloxError::Exit(code)) => assert_eq!(code, 3),
+            _ => panic!("expected exit"),
+        }
+        assert_eq!(evaluate(&interpreter, "ran").unwrap(), Value::Bool(false));
+        match evaluate(&interpreter, "exit(\"no\")") {
+            Err(RloxError::Exit(code)) => assert_eq!(code, 1),
+            _ => panic!("expected exit"),
+        }
+    }
+
+    #[test]
+    fn a_fixed_clock_always_reads_the_same() {
+        let interpreter = Interpreter::default().with_fixed_clock(0.0);
+        assert_eq!(
+            evaluate(&interpreter, "clock()").unwrap(),
+            Value::Number(0.0)
+        );
+        assert_eq!(
+            evaluate(&interpreter, "clock()").unwrap(),
+            Value::Number(0.0)
+        );
+    }
+
+    #[test]
+    fn script_arguments_are_counted_and_indexed_from_zero() {
+        let interpreter = Interpreter::default();
+        define_script_arguments(&interpreter, vec!["one".to_string(), "two".to_string()]);
+        assert_eq!(
+            evaluate(&interpreter, "argc()").unwrap(),
+            Value::Number(2.0)
+        );
+        assert_eq!(
+            evaluate(&interpreter, "argv(1)").unwrap(),
+            Value::String("two".to_string())
+        );
+        for missing in ["argv(2)", "argv(-1)", "argv(0.5)", "argv(\"0\")"].iter() {
+            assert_eq!(evaluate(&interpreter, missing).unwrap(), Value::Nil);
+        }
+    }
+}
@@ -1,57 +1,647 @@
-use crate::expr::{BinaryExpr, Expr, GroupingExpr, LiteralExpr, UnaryExpr};
-use crate::token::{Literal, Token, TokenType};
+use crate::error::{RloxError, RloxSyntaxError};
+use crate::error_codes::{
+    ErrorCode, EXPECTED_EXPRESSION, EXPECTED_NAME, INPUT_AFTER_EXPRESSION,
+    INVALID_ASSIGNMENT_TARGET, MISSING_PUNCTUATION, NESTING_TOO_DEEP, TOO_MANY_ARGUMENTS,
+};
+use crate::expr::{
+    AssignExpr, BinaryExpr, CallExpr, Expr, FunctionExpr, GetExpr, GroupingExpr, LiteralExpr,
+    LogicalExpr, SetExpr, SuperExpr, ThisExpr, UnaryExpr, VariableExpr,
+};
+use crate::scanner::{normalize_source, Scanner};
+use crate::stmt::{
+    BlockStmt, BreakStmt, ClassStmt, ContinueStmt, ExpressionStmt, FunctionStmt, IfStmt, PrintStmt,
+    ReturnStmt, Stmt, VarStmt, WhileStmt,
+};
+use crate::token::{Literal, Precedence, Token, TokenType};
+
+const MAX_ARGUMENTS: usize = 255;
+// deep enough for any real script, shallow enough that recursive descent stays well inside the stack
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 256;
+
+/**
+ * The result of parsing source text: whatever AST could be recovered, plus every diagnostic
+ *   reported along the way. The AST is only trustworthy when `diagnostics` is empty.
+ */
+pub struct Parsed<T> {
+    pub ast: T,
+    pub diagnostics: Vec<RloxError>,
+}
+
+// program --> declaration* EOF ;
+pub fn parse_program(source: &str) -> Parsed<Vec<Stmt>> {
+    let (scanner, mut diagnostics) = Scanner::scan(normalize_source(source));
+    let mut parser = Parser::new(scanner.tokens().clone());
+    let ast = parser.program();
+    // the lexical errors come first, then what the parser made of the tokens around them
+    diagnostics.append(&mut parser.errors);
+    Parsed { ast, diagnostics }
+}
+
+// a single expression followed by EOF, e.g. for calculators or REPL tooling
+pub fn parse_expression(source: &str) -> Parsed<Option<Expr>> {
+    let (scanner, mut diagnostics) = Scanner::scan(normalize_source(source));
+    let mut parser = Parser::new(scanner.tokens().clone());
+    let ast = match parser.expression() {
+        Ok(expr) if parser.is_at_end() => Some(expr),
+        Ok(_) => {
+            let e = parser.error(
+                parser.peek(),
+                &INPUT_AFTER_EXPRESSION,
+                "Expect end of expression.",
+            );
+            parser.errors.push(e);
+            None
+        }
+        Err(e) => {
+            parser.errors.push(e);
+            None
+        }
+    };
+    // the lexical errors come first, then what the parser made of the tokens around them
+    diagnostics.append(&mut parser.errors);
+    Parsed { ast, diagnostics }
+}
+
+type PrefixRule = fn(&mut Parser) -> Result<Expr, RloxError>;
+type InfixRule = fn(&mut Parser, Expr) -> Result<Expr, RloxError>;
+
+// how a token parses: starting an expression, following one, and how tightly it binds as infix
+//   (the precedence itself comes from the token table)
+struct ParseRule {
+    prefix: Option<PrefixRule>,
+    infix: Option<InfixRule>,
+    precedence: Precedence,
+}
+
+fn rule(token_type: &TokenType) -> ParseRule {
+    use TokenType::*;
+    let (prefix, infix): (Option<PrefixRule>, Option<InfixRule>) = match token_type {
+        LeftParen => (Some(Parser::grouping), Some(Parser::call)),
+        Dot => (None, Some(Parser::get)),
+        Minus => (Some(Parser::unary), Some(Parser::binary)),
+        Bang => (Some(Parser::unary), None),
+        Comma => (without_lhs(), Some(Parser::binary)),
+        Equal => (None, Some(Parser::assignment)),
+        Or => (without_lhs(), Some(Parser::logical)),
+        And => (without_lhs(), Some(Parser::logical)),
+        BangEqual | EqualEqual => (without_lhs(), Some(Parser::binary)),
+        Greater | GreaterEqual | Less | LessEqual => (without_lhs(), Some(Parser::binary)),
+        Plus => (without_lhs(), Some(Parser::binary)),
+        Slash | Star => (without_lhs(), Some(Parser::binary)),
+        Number | String | True | False | Nil => (Some(Parser::literal), None),
+        Identifier => (Some(Parser::variable), None),
+        This => (Some(Parser::this), None),
+        Super => (Some(Parser::super_), None),
+        Fun => (Some(Parser::lambda), None),
+        _ => (None, None),
+    };
+    ParseRule {
+        prefix,
+        infix,
+        precedence: token_type.info().precedence,
+    }
+}
+
+fn without_lhs() -> Option<PrefixRule> {
+    Some(Parser::binary_operator_without_lhs)
+}
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
-}
-
-enum ParseError {
-    // TODO
+    errors: Vec<RloxError>,
+    depth: usize,
+    max_depth: usize,
 }
 
 impl Parser {
-    pub fn parse(&self) -> Result<Expr, String> {
-        self.expression() // TODO
+    pub fn new(mut tokens: Vec<Token>) -> Self {
+        // every lookahead relies on the stream ending in Eof
+        if tokens.last().map(|t| t.token_type()) != Some(&TokenType::Eof) {
+            let line = tokens.last().map_or(1, |t| *t.line_number());
+            if let Ok(eof) = Token::new(TokenType::Eof, "".to_string(), None, line) {
+                tokens.push(eof);
+            }
+        }
+        Parser {
+            tokens,
+            current: 0,
+            errors: Vec::new(),
+            depth: 0,
+            max_depth: DEFAULT_MAX_NESTING_DEPTH,
+        }
     }
 
-    // expression --> equality ;
-    fn expression(&mut self) -> Expr {
-        self.equality()
+    /**
+     * Limits how deeply statements and expressions may nest, e.g. `((((1))))` or `{ { { } } }`.
+     *   Every level costs native stack in this recursive descent parser, so past the limit parsing
+     *   stops with a syntax error instead of overflowing the stack.
+     */
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
     }
 
-    // equality --> comparison ( ( "!=" | "==" ) comparison )* ;
-    fn equality(&mut self) -> Expr {
-        let mut expr = self.comparison();
-        while self.advance_if_match(&[&TokenType::BangEqual, &TokenType::Equal]) {
-            let operator = self.previous().clone();
-            let lhs = expr.clone();
-            let rhs = self.comparison().clone();
-            expr = BinaryExpr::new(operator, lhs, rhs)
+    /**
+     * Parses the whole token stream. A syntax error does not stop parsing: the parser reports it,
+     *   skips ahead to the next statement boundary, and keeps going, so every error in the source is
+     *   returned together.
+     */
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<RloxError>> {
+        let statements = self.program();
+        match self.errors.is_empty() {
+            true => Ok(statements),
+            false => Err(std::mem::take(&mut self.errors)),
         }
-        expr
     }
 
-    // comparison --> term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
-    fn comparison(&mut self) -> Expr {
-        let mut expr = self.term();
-        while self.advance_if_match(&[
-            &TokenType::Greater,
-            &TokenType::GreaterEqual,
-            &TokenType::Less,
-            &TokenType::LessEqual,
-        ]) {
-            let operator = self.previous().clone();
-            let lhs = expr.clone();
-            let rhs = self.term().clone();
-            expr = BinaryExpr::new(operator, lhs, rhs)
+    // program --> declaration* EOF ;
+    fn program(&mut self) -> Vec<Stmt> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            if let Some(stmt) = self.declaration() {
+                statements.push(stmt);
+            }
+        }
+        statements
+    }
+
+    fn declaration(&mut self) -> Option<Stmt> {
+        match self.try_declaration() {
+            Ok(stmt) => Some(stmt),
+            Err(e) => {
+                self.errors.push(e);
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    // declaration --> classDecl | funDecl | varDecl | statement ;
+    fn try_declaration(&mut self) -> Result<Stmt, RloxError> {
+        if self.advance_if_match(&[&TokenType::Class]) {
+            self.class_declaration()
+        } else if self.is_current_token_type(&TokenType::Fun)
+            && self.is_next_token_type(&TokenType::Identifier)
+        {
+            // without a name, `fun` starts an anonymous function expression instead
+            self.advance();
+            self.function("function")
+        } else if self.advance_if_match(&[&TokenType::Var]) {
+            self.var_declaration()
+        } else {
+            self.statement()
+        }
+    }
+
+    // classDecl --> "class" IDENTIFIER ( "<" IDENTIFIER )? "{" function* "}" ;
+    fn class_declaration(&mut self) -> Result<Stmt, RloxError> {
+        let name = self
+            .consume(&TokenType::Identifier, "Expect class name.")?
+            .clone();
+        let superclass = match self.advance_if_match(&[&TokenType::Less]) {
+            true => {
+                let superclass_name = self
+                    .consume(&TokenType::Identifier, "Expect superclass name.")?
+                    .clone();
+                Some(VariableExpr::new(superclass_name))
+            }
+            false => None,
+        };
+        self.consume(&TokenType::LeftBrace, "Expect '{' before class body.")?;
+        let mut methods = Vec::new();
+        while !self.is_current_token_type(&TokenType::RightBrace) && !self.is_at_end() {
+            if let Stmt::Function(method) = self.function("method")? {
+                methods.push(method);
+            }
+        }
+        self.consume(&TokenType::RightBrace, "Expect '}' after class body.")?;
+        Ok(ClassStmt::new(name, superclass, methods))
+    }
+
+    // funDecl --> "fun" function ;
+    // function --> IDENTIFIER "(" functionBody ;
+    fn function(&mut self, kind: &str) -> Result<Stmt, RloxError> {
+        let name = self
+            .consume(&TokenType::Identifier, &format!("Expect {} name.", kind))?
+            .clone();
+        self.consume(
+            &TokenType::LeftParen,
+            &format!("Expect '(' after {} name.", kind),
+        )?;
+        let (params, body) = self.function_body(kind)?;
+        Ok(FunctionStmt::new(name, params, body))
+    }
+
+    // functionBody --> parameters? ")" block ;
+    // parameters --> IDENTIFIER ( "," IDENTIFIER )* ;
+    //   A level of nesting, like a statement, since function declarations nest through it alone.
+    fn function_body(&mut self, kind: &str) -> Result<(Vec<Token>, Vec<Stmt>), RloxError> {
+        self.nested(|parser| parser.parameters_and_block(kind))
+    }
+
+    fn parameters_and_block(&mut self, kind: &str) -> Result<(Vec<Token>, Vec<Stmt>), RloxError> {
+        let mut params = Vec::new();
+        if !self.is_current_token_type(&TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    // reported, but the parser is not confused, so there is no need to synchronize
+                    let e = self.error(
+                        self.peek(),
+                        &TOO_MANY_ARGUMENTS,
+                        "Can't have more than 255 parameters.",
+                    );
+                    self.errors.push(e);
+                }
+                params.push(
+                    self.consume(&TokenType::Identifier, "Expect parameter name.")?
+                        .clone(),
+                );
+                if !self.advance_if_match(&[&TokenType::Comma]) {
+                    break;
+                }
+            }
         }
-        expr
+        self.consume(&TokenType::RightParen, "Expect ')' after parameters.")?;
+        self.consume(
+            &TokenType::LeftBrace,
+            &format!("Expect '{{' before {} body.", kind),
+        )?;
+        let body = self.block()?;
+        Ok((params, body))
+    }
+
+    // varDecl --> "var" IDENTIFIER ( "=" expression )? ";" ;
+    fn var_declaration(&mut self) -> Result<Stmt, RloxError> {
+        let name = self
+            .consume(&TokenType::Identifier, "Expect variable name.")?
+            .clone();
+        let initializer = match self.advance_if_match(&[&TokenType::Equal]) {
+            true => Some(self.expression()?),
+            false => None,
+        };
+        self.consume(
+            &TokenType::Semicolon,
+            "Expect ';' after variable declaration.",
+        )?;
+        Ok(VarStmt::new(name, initializer))
+    }
+
+    // statement --> exprStmt | breakStmt | continueStmt | forStmt | ifStmt | printStmt
+    //   | returnStmt | whileStmt | block ;
+    fn statement(&mut self) -> Result<Stmt, RloxError> {
+        self.nested(|parser| {
+            if parser.advance_if_match(&[&TokenType::Break]) {
+                parser.break_statement()
+            } else if parser.advance_if_match(&[&TokenType::Continue]) {
+                parser.continue_statement()
+            } else if parser.advance_if_match(&[&TokenType::For]) {
+                parser.for_statement()
+            } else if parser.advance_if_match(&[&TokenType::If]) {
+                parser.if_statement()
+            } else if parser.advance_if_match(&[&TokenType::Print]) {
+                parser.print_statement()
+            } else if parser.advance_if_match(&[&TokenType::Return]) {
+                parser.return_statement()
+            } else if parser.advance_if_match(&[&TokenType::While]) {
+                parser.while_statement()
+            } else if parser.advance_if_match(&[&TokenType::LeftBrace]) {
+                Ok(BlockStmt::new(parser.block()?))
+            } else {
+                parser.expression_statement()
+            }
+        })
+    }
+
+    // breakStmt --> "break" ";" ;
+    //   Whether there is a loop to break out of is checked by the resolver, not here.
+    fn break_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous().clone();
+        self.consume(&TokenType::Semicolon, "Expect ';' after 'break'.")?;
+        Ok(BreakStmt::new(keyword))
+    }
+
+    // continueStmt --> "continue" ";" ;
+    fn continue_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous().clone();
+        self.consume(&TokenType::Semicolon, "Expect ';' after 'continue'.")?;
+        Ok(ContinueStmt::new(keyword))
+    }
+
+    // forStmt --> "for" "(" ( varDecl | exprStmt | ";" ) expression? ";" expression? ")" statement ;
+    //   There is no for node in the AST: the loop is desugared into a while loop
+    //   wrapped in a block that scopes the initializer.
+    //   The increment is kept on the while node rather than appended to the body, where a
+    //   `continue` in the body would skip it.
+    //   The synthesized while node keeps the `for` token, and the clauses keep their own tokens, so
+    //   anything reported against them points at the user's source rather than at the desugaring.
+    fn for_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous().clone();
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'for'.")?;
+        let initializer = if self.advance_if_match(&[&TokenType::Semicolon]) {
+            None
+        } else if self.advance_if_match(&[&TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+        let condition = match self.is_current_token_type(&TokenType::Semicolon) {
+            true => LiteralExpr::new(LiteralExpr::Bool(true)),
+            false => self.expression()?,
+        };
+        self.consume(&TokenType::Semicolon, "Expect ';' after loop condition.")?;
+        let increment = match self.is_current_token_type(&TokenType::RightParen) {
+            true => None,
+            false => Some(self.expression()?),
+        };
+        self.consume(&TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        let mut body = WhileStmt::new(keyword, condition, self.statement()?, increment);
+        if let Some(initializer) = initializer {
+            body = BlockStmt::new(vec![initializer, body]);
+        }
+        Ok(body)
+    }
+
+    // ifStmt --> "if" "(" expression ")" statement ( "else" statement )? ;
+    fn if_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous().clone();
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen, "Expect ')' after if condition.")?;
+        let then_branch = self.statement()?;
+        let else_branch = match self.advance_if_match(&[&TokenType::Else]) {
+            true => Some(self.statement()?),
+            false => None,
+        };
+        Ok(IfStmt::new(keyword, condition, then_branch, else_branch))
+    }
+
+    // printStmt --> "print" expression ";" ;
+    fn print_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous().clone();
+        let value = self.expression()?;
+        self.consume(&TokenType::Semicolon, "Expect ';' after value.")?;
+        Ok(PrintStmt::new(keyword, value))
+    }
+
+    // returnStmt --> "return" expression? ";" ;
+    fn return_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous().clone();
+        let value = match self.is_current_token_type(&TokenType::Semicolon) {
+            true => None,
+            false => Some(self.expression()?),
+        };
+        self.consume(&TokenType::Semicolon, "Expect ';' after return value.")?;
+        Ok(ReturnStmt::new(keyword, value))
+    }
+
+    // whileStmt --> "while" "(" expression ")" statement ;
+    fn while_statement(&mut self) -> Result<Stmt, RloxError> {
+        let keyword = self.previous().clone();
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        self.consume(&TokenType::RightParen, "Expect ')' after condition.")?;
+        let body = self.statement()?;
+        Ok(WhileStmt::new(keyword, condition, body, None))
+    }
+
+    // block --> "{" declaration* "}" ;
+    fn block(&mut self) -> Result<Vec<Stmt>, RloxError> {
+        let mut statements = Vec::new();
+        while !self.is_current_token_type(&TokenType::RightBrace) && !self.is_at_end() {
+            if let Some(stmt) = self.declaration() {
+                statements.push(stmt);
+            }
+        }
+        self.consume(&TokenType::RightBrace, "Expect '}' after block.")?;
+        Ok(statements)
+    }
+
+    // exprStmt --> expression ";" ;
+    fn expression_statement(&mut self) -> Result<Stmt, RloxError> {
+        let expr = self.expression()?;
+        self.consume(&TokenType::Semicolon, "Expect ';' after expression.")?;
+        Ok(ExpressionStmt::new(expr))
+    }
+
+    // expression --> comma ;
+    fn expression(&mut self) -> Result<Expr, RloxError> {
+        self.parse_precedence(Precedence::Comma)
+    }
+
+    /**
+     * Pratt parsing: the current token's prefix rule starts the expression, then infix rules
+     *   keep extending it for as long as the next operator binds at least as tightly as
+     *   `precedence`. An operator is a row in `rule()` plus its entry in `TokenType::info`.
+     */
+    fn parse_precedence(&mut self, precedence: Precedence) -> Result<Expr, RloxError> {
+        self.nested(|parser| {
+            let prefix = match rule(parser.peek().token_type()).prefix {
+                Some(prefix) => prefix,
+                None => {
+                    return Err(parser.error(
+                        parser.peek(),
+                        &EXPECTED_EXPRESSION,
+                        "Expect expression.",
+                    ))
+                }
+            };
+            parser.advance();
+            let mut expr = prefix(parser)?;
+            loop {
+                let next = rule(parser.peek().token_type());
+                match next.infix {
+                    Some(infix) if precedence <= next.precedence => {
+                        parser.advance();
+                        expr = infix(parser, expr)?;
+                    }
+                    _ => break Ok(expr),
+                }
+            }
+        })
+    }
+
+    // comma --> assignment ( "," assignment )* ;
+    //   Evaluates operands left to right and yields the last; kept as a binary expression.
+    // equality, comparison, term, factor --> operand ( operator operand )* ;
+    fn binary(&mut self, lhs: Expr) -> Result<Expr, RloxError> {
+        let operator = self.previous().clone();
+        let rhs = self.parse_precedence(rule(operator.token_type()).precedence.next())?;
+        Ok(BinaryExpr::new(operator, lhs, rhs))
+    }
+
+    // logic_or --> logic_and ( "or" logic_and )* ;
+    // logic_and --> equality ( "and" equality )* ;
+    fn logical(&mut self, lhs: Expr) -> Result<Expr, RloxError> {
+        let operator = self.previous().clone();
+        let rhs = self.parse_precedence(rule(operator.token_type()).precedence.next())?;
+        Ok(LogicalExpr::new(operator, lhs, rhs))
+    }
+
+    // assignment --> ( call "." )? IDENTIFIER "=" assignment | logic_or ;
+    //   Right-associative, so the value is parsed at assignment's own precedence.
+    fn assignment(&mut self, target: Expr) -> Result<Expr, RloxError> {
+        let equals = self.previous().clone();
+        let value = self.parse_precedence(Precedence::Assignment)?;
+        match target {
+            Expr::Variable(variable) => Ok(AssignExpr::new(variable.name().clone(), value)),
+            Expr::Get(get) => Ok(SetExpr::new(
+                get.object().clone(),
+                get.name().clone(),
+                value,
+            )),
+            _ => {
+                // reported without unwinding: the parser is still in a known state
+                self.errors.push(self.error(
+                    &equals,
+                    &INVALID_ASSIGNMENT_TARGET,
+                    "Invalid assignment target.",
+                ));
+                Ok(target)
+            }
+        }
+    }
+
+    // unary --> ( "!" | "-" ) unary | call ;
+    fn unary(&mut self) -> Result<Expr, RloxError> {
+        let operator = self.previous().clone();
+        let rhs = self.parse_precedence(Precedence::Unary)?;
+        Ok(UnaryExpr::new(operator, rhs))
+    }
+
+    // call --> primary ( "(" arguments? ")" | "." IDENTIFIER )* ;
+    // arguments --> assignment ( "," assignment )* ;
+    //   Arguments start below the comma operator so that "," separates them instead.
+    fn call(&mut self, callee: Expr) -> Result<Expr, RloxError> {
+        let mut arguments = Vec::new();
+        if !self.is_current_token_type(&TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    let e = self.error(
+                        self.peek(),
+                        &TOO_MANY_ARGUMENTS,
+                        "Can't have more than 255 arguments.",
+                    );
+                    self.errors.push(e);
+                }
+                arguments.push(self.parse_precedence(Precedence::Assignment)?);
+                if !self.advance_if_match(&[&TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        let paren = self
+            .consume(&TokenType::RightParen, "Expect ')' after arguments.")?
+            .clone();
+        Ok(CallExpr::new(callee, paren, arguments))
+    }
+
+    fn get(&mut self, object: Expr) -> Result<Expr, RloxError> {
+        let name = self
+            .consume(&TokenType::Identifier, "Expect property name after '.'.")?
+            .clone();
+        Ok(GetExpr::new(object, name))
+    }
+
+    // primary --> NUMBER | STRING+ | "true" | "false" | "nil" | "this"
+    //             | "(" expression ")" | IDENTIFIER | "super" "." IDENTIFIER
+    //             | "fun" "(" functionBody ;
+    fn literal(&mut self) -> Result<Expr, RloxError> {
+        let prev = self.previous();
+        match (prev.token_type(), prev.literal()) {
+            (TokenType::False, _) => Ok(LiteralExpr::new(LiteralExpr::Bool(false))),
+            (TokenType::True, _) => Ok(LiteralExpr::new(LiteralExpr::Bool(true))),
+            (TokenType::Nil, _) => Ok(LiteralExpr::new(LiteralExpr::Nil)),
+            (_, Some(Literal::String(s))) => {
+                let s = s.to_string();
+                Ok(LiteralExpr::new(LiteralExpr::String(
+                    self.adjacent_strings(s),
+                )))
+            }
+            (_, Some(Literal::Float(f))) => Ok(LiteralExpr::new(LiteralExpr::Float(*f))),
+            (_, None) => Err(self.error(prev, &EXPECTED_EXPRESSION, "Expect literal value.")),
+        }
+    }
+
+    // adjacent string literals, e.g. `"foo" "bar"`, are one constant so long text can span lines
+    fn adjacent_strings(&mut self, mut s: String) -> String {
+        while self.advance_if_match(&[&TokenType::String]) {
+            if let Some(Literal::String(next)) = self.previous().literal() {
+                s.push_str(next);
+            }
+        }
+        s
+    }
+
+    fn grouping(&mut self) -> Result<Expr, RloxError> {
+        let expr = self.expression()?;
+        self.consume(&TokenType::RightParen, "Expect ')' after expression.")?;
+        Ok(GroupingExpr::new(expr))
+    }
+
+    fn variable(&mut self) -> Result<Expr, RloxError> {
+        Ok(VariableExpr::new(self.previous().clone()))
+    }
+
+    fn this(&mut self) -> Result<Expr, RloxError> {
+        Ok(ThisExpr::new(self.previous().clone()))
+    }
+
+    fn super_(&mut self) -> Result<Expr, RloxError> {
+        let keyword = self.previous().clone();
+        self.consume(&TokenType::Dot, "Expect '.' after 'super'.")?;
+        let method = self
+            .consume(&TokenType::Identifier, "Expect superclass method name.")?
+            .clone();
+        Ok(SuperExpr::new(keyword, method))
+    }
+
+    fn lambda(&mut self) -> Result<Expr, RloxError> {
+        let keyword = self.previous().clone();
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'fun'.")?;
+        let (params, body) = self.function_body("function")?;
+        Ok(FunctionExpr::new(keyword, params, body))
+    }
+
+    /**
+     * Error production for a binary operator in prefix position, e.g. `* 3` or `== 5`.
+     *   The operator is reported, then its right operand is parsed at the operator's own precedence
+     *   and stands in for the broken expression, so parsing continues and later errors are found.
+     *   `-` is not covered here since it is also a valid unary operator.
+     */
+    fn binary_operator_without_lhs(&mut self) -> Result<Expr, RloxError> {
+        let operator = self.previous().clone();
+        let e = self.error(
+            &operator,
+            &EXPECTED_EXPRESSION,
+            "Binary operator without left-hand operand.",
+        );
+        self.errors.push(e);
+        self.parse_precedence(rule(operator.token_type()).precedence.next())
+    }
+
+    // runs one level of a recursive rule, failing once the nesting limit is reached; the rest of
+    //   the input is then skipped, since recovering inside it could fail the same way at every level
+    fn nested<T>(
+        &mut self,
+        rule: impl FnOnce(&mut Self) -> Result<T, RloxError>,
+    ) -> Result<T, RloxError> {
+        if self.depth >= self.max_depth {
+            let msg = format!("Nesting exceeds maximum depth of {}.", self.max_depth);
+            let e = self.error(self.peek(), &NESTING_TOO_DEEP, &msg);
+            self.current = self.tokens.len() - 1;
+            return Err(e);
+        }
+        self.depth += 1;
+        let result = rule(self);
+        self.depth -= 1;
+        result
     }
 
     fn advance_if_match(&mut self, token_types: &[&TokenType]) -> bool {
-        for token in &self.tokens {
-            if self.is_current_token_type(&token.token_type()) {
+        for token_type in token_types {
+            if self.is_current_token_type(token_type) {
                 self.advance();
                 return true;
             }
@@ -64,7 +654,7 @@ impl Parser {
     }
 
     fn advance(&mut self) -> &Token {
-        if self.is_at_end() {
+        if !self.is_at_end() {
             self.current += 1;
         }
         self.previous()
@@ -77,6 +667,12 @@ impl Parser {
         self.peek().token_type() == token_type
     }
 
+    fn is_next_token_type(&self, token_type: &TokenType) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|t| t.token_type() == token_type)
+    }
+
     fn is_at_end(&self) -> bool {
         self.peek().token_type() == &TokenType::Eof
     }
@@ -85,107 +681,332 @@ impl Parser {
         &self.tokens[self.current]
     }
 
-    fn term(&mut self) -> Expr {
-        let mut expr = self.factor();
-        while self.advance_if_match(&[&TokenType::Minus, &TokenType::Plus]) {
-            let operator = self.previous().clone();
-            let lhs = expr.clone();
-            let rhs = self.factor().clone();
-            expr = BinaryExpr::new(operator, lhs, rhs);
+    fn consume(&mut self, token_type: &TokenType, msg: &str) -> Result<&Token, RloxError> {
+        if self.is_current_token_type(token_type) {
+            Ok(self.advance())
+        } else {
+            let code = match token_type {
+                TokenType::Identifier => &EXPECTED_NAME,
+                _ => &MISSING_PUNCTUATION,
+            };
+            Err(self.error(self.peek(), code, msg))
+        }
+    }
+
+    fn error(&self, token: &Token, code: &'static ErrorCode, msg: &str) -> RloxError {
+        let description = match token.token_type() {
+            TokenType::Eof => format!("at end: {}", msg),
+            _ => format!("at '{}': {}", token.lexeme(), msg),
+        };
+        let mut error = RloxSyntaxError::at(token, code, description);
+        // the end is shown just after the last token, rather than on any blank lines that follow
+        if let (TokenType::Eof, Some(last)) = (
+            token.token_type(),
+            self.current.checked_sub(1).and_then(|i| self.tokens.get(i)),
+        ) {
+            if last.column() > 0 && !last.lexeme().contains('\n') {
+                error.line_number = *last.line_number();
+                error.column = last.column() + last.lexeme().chars().count();
+                error.length = 1;
+            }
         }
-        expr
+        RloxError::SyntaxError(error)
     }
 
-    fn factor(&mut self) -> Expr {
-        let mut expr = self.unary();
-        while self.advance_if_match(&[&TokenType::Slash, &TokenType::Star]) {
-            let operator = self.previous().clone();
-            let lhs = expr.clone();
-            let rhs = self.unary().clone();
-            expr = BinaryExpr::new(operator, lhs, rhs);
+    // discards tokens until the start of what is probably the next statement
+    fn synchronize(&mut self) {
+        self.advance();
+
+        while !self.is_at_end() {
+            if self.previous().token_type() == &TokenType::Semicolon {
+                return;
+            }
+
+            match self.peek().token_type() {
+                TokenType::Class
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Break
+                | TokenType::Continue => return,
+                _ => (),
+            }
+
+            self.advance();
         }
-        expr
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast_printer::AstPrinter;
+    use crate::error::RloxError;
+    use crate::expr::Expr;
+    use crate::scanner::Scanner;
+    use crate::stmt::Stmt;
 
-    fn unary(&mut self) -> Expr {
-        if self.advance_if_match(&[&TokenType::Bang, &TokenType::Minus]) {
-            let operator = self.previous().clone();
-            let rhs = self.unary().clone();
-            return UnaryExpr::new(operator, rhs);
+    use super::Parser;
+
+    fn parse(source: &str) -> Result<Vec<Stmt>, Vec<RloxError>> {
+        let scanner = Scanner::new(source.to_string()).map_err(|e| vec![e])?;
+        Parser::new(scanner.tokens().clone()).parse()
+    }
+
+    fn syntax_errors(source: &str) -> Vec<(usize, String)> {
+        match parse(source) {
+            Ok(_) => panic!("expected syntax errors"),
+            Err(errors) => errors
+                .into_iter()
+                .map(|e| match e {
+                    RloxError::SyntaxError(e) => (e.line_number, e.description),
+                    _ => panic!("expected only syntax errors"),
+                })
+                .collect(),
         }
-        self.primary()
     }
 
-    // primary --> NUMBER | STRING | "true" | "false" | "nil"
-    //             | "(" expression ")" ;
-    fn primary(&mut self) -> Expr {
-        if self.advance_if_match(&[&TokenType::False]) {
-            LiteralExpr::new(LiteralExpr::Bool(false))
-        } else if self.advance_if_match(&[&TokenType::True]) {
-            LiteralExpr::new(LiteralExpr::Bool(true))
-        } else if self.advance_if_match(&[&TokenType::Nil]) {
-            LiteralExpr::new(LiteralExpr::Nil)
-        } else if self.advance_if_match(&[&TokenType::Number, &TokenType::String]) {
-            let prev = self.previous();
-            match prev.literal() {
-                Some(v) => match v {
-                    Literal::String(s) => LiteralExpr::new(LiteralExpr::String(s.to_string())),
-                    Literal::Float(f) => LiteralExpr::new(LiteralExpr::Float(*f)),
-                },
-                None => panic!("Something went wrong. Literal should exist for any Number or String token"), // TODO: design this potential bug away
+    #[test]
+    fn return_records_keyword_and_optional_value() {
+        let statements = parse("fun f() {\n  return 1;\n}\nfun g() { return; }").unwrap();
+        let bodies = statements
+            .iter()
+            .map(|stmt| match stmt {
+                Stmt::Function(function) => function.body().to_vec(),
+                _ => panic!("expected function declaration"),
+            })
+            .collect::<Vec<_>>();
+        match (&bodies[0][0], &bodies[1][0]) {
+            (Stmt::Return(with_value), Stmt::Return(bare)) => {
+                assert_eq!(with_value.keyword().lexeme(), "return");
+                assert_eq!(*with_value.keyword().line_number(), 2);
+                assert!(with_value.value().is_some());
+                assert!(bare.value().is_none());
             }
-        } else if self.advance_if_match(&[&TokenType::LeftParen]) {
-            let expr = self.expression();
-            self.consume(&TokenType::RightParen, "Expect ')' after expression.");
-            GroupingExpr::new(expr)
-        }
-        else {
-            self.error(self.peek(), "Expect expression.");
-            panic!("Something went wrong. Unable to parse Primary expression.")
+            _ => panic!("expected return statements"),
         }
     }
 
-    fn consume(&mut self, token_type: &TokenType, msg: &str) -> &Token {
-        if self.is_current_token_type(token_type) {
-            return self.advance()
+    #[test]
+    fn return_at_top_level_still_parses() {
+        // rejecting this is the job of a later pass, which needs the keyword token to report it
+        let statements = parse("return \"early\";").unwrap();
+        assert!(matches!(statements[0], Stmt::Return(_)));
+    }
+
+    #[test]
+    fn class_declaration_with_superclass_and_methods() {
+        let source = "class B < A { init(x) { this.x = x; } get() { return super.get(); } }";
+        let statements = parse(source).unwrap();
+        let class = match &statements[0] {
+            Stmt::Class(class) => class,
+            _ => panic!("expected class declaration"),
+        };
+        assert_eq!(class.name().lexeme(), "B");
+        match class.superclass() {
+            Some(Expr::Variable(superclass)) => assert_eq!(superclass.name().lexeme(), "A"),
+            _ => panic!("expected superclass variable"),
+        }
+        let methods = class.methods();
+        assert_eq!(methods.len(), 2);
+        assert_eq!(methods[0].name().lexeme(), "init");
+        assert_eq!(methods[0].params().len(), 1);
+
+        let printer = AstPrinter::default();
+        match (&methods[0].body()[0], &methods[1].body()[0]) {
+            (Stmt::Expression(set), Stmt::Return(ret)) => {
+                assert_eq!(printer.print(set.expression().clone()), "(= this x x)");
+                let value = ret.value().clone().unwrap();
+                assert_eq!(printer.print(value), "(call (super get))");
+            }
+            _ => panic!("expected method bodies"),
         }
-        else {
-            self.error(self.peek(), msg);
-            panic!("{} {}", self.peek(), msg);
+    }
+
+    #[test]
+    fn super_requires_method_access() {
+        assert_eq!(
+            syntax_errors("class B < A { f() { super; } }"),
+            vec![(1, "at ';': Expect '.' after 'super'.".to_string())]
+        );
+    }
+
+    #[test]
+    fn return_requires_semicolon() {
+        assert_eq!(
+            syntax_errors("fun f() { return 1 }")[0],
+            (1, "at '}': Expect ';' after return value.".to_string())
+        );
+    }
+
+    #[test]
+    fn comma_operator_is_lowest_precedence_and_left_associative() {
+        let printer = AstPrinter::default();
+        let expression = |source: &str| match &parse(source).unwrap()[0] {
+            Stmt::Expression(stmt) => printer.print(stmt.expression().clone()),
+            _ => panic!("expected expression statement"),
+        };
+        assert_eq!(expression("a = 1, b = 2, c;"), "(, (, (= a 1) (= b 2)) c)");
+        assert_eq!(expression("f(a, b);"), "(call f a b)");
+        assert_eq!(expression("f((a, b), c);"), "(call f (group (, a b)) c)");
+    }
+
+    #[test]
+    fn operators_bind_by_precedence_and_associativity() {
+        let printer = AstPrinter::default();
+        let expression = |source: &str| match &parse(source).unwrap()[0] {
+            Stmt::Expression(stmt) => printer.print(stmt.expression().clone()),
+            _ => panic!("expected expression statement"),
+        };
+        assert_eq!(
+            expression("a = b or c and !d == e < f + g * -h.i();"),
+            "(= a (or b (and c (== (! d) (< e (+ f (* g (- (call (. h i))))))))))"
+        );
+        assert_eq!(expression("a - b - c;"), "(- (- a b) c)");
+        assert_eq!(expression("a = b = c;"), "(= a (= b c))");
+    }
+
+    #[test]
+    fn binary_operator_without_lhs_is_reported_and_rhs_is_parsed() {
+        assert_eq!(
+            syntax_errors("print * 3;\nprint == 5 + );\nvar a = and b;"),
+            vec![
+                (
+                    1,
+                    "at '*': Binary operator without left-hand operand.".to_string()
+                ),
+                (
+                    2,
+                    "at '==': Binary operator without left-hand operand.".to_string()
+                ),
+                (2, "at ')': Expect expression.".to_string()),
+                (
+                    3,
+                    "at 'and': Binary operator without left-hand operand.".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn pathological_nesting_is_a_syntax_error() {
+        let parens = format!("print {}1{};", "(".repeat(10_000), ")".repeat(10_000));
+        let braces = "{".repeat(10_000);
+        let unary = format!("print {}1;", "!".repeat(10_000));
+        let operators = format!("print {}1;", "* ".repeat(10_000));
+        let functions = format!("{}{}", "fun f() {".repeat(10_000), "}".repeat(10_000));
+        for source in &[parens, braces, unary, operators, functions] {
+            let errors = syntax_errors(source);
+            assert!(errors
+                .iter()
+                .any(|(_, e)| e.ends_with("Nesting exceeds maximum depth of 256.")));
         }
     }
 
-    fn error(&self, token: &Token, msg: &str) {
-        if token.token_type() == &TokenType::Eof {
-            println!("{} at end. {}", token.line_number(), msg)
+    #[test]
+    fn nesting_depth_is_configurable() {
+        let parse_with_max_depth = |source: &str, max_depth| {
+            let scanner = Scanner::new(source.to_string()).unwrap();
+            Parser::new(scanner.tokens().clone())
+                .with_max_depth(max_depth)
+                .parse()
+        };
+        // the print statement is one level, each expression inside it another
+        assert!(parse_with_max_depth("print ((1));", 4).is_ok());
+        assert!(parse_with_max_depth("print (((1)));", 4).is_err());
+    }
+
+    #[test]
+    fn desugared_for_loop_keeps_source_tokens() {
+        let statements =
+            parse("for (var i = 0;\n     i < 3;\n     i = i + 1)\n  print i;").unwrap();
+        let block = match &statements[0] {
+            Stmt::Block(block) => block,
+            _ => panic!("expected the initializer's block"),
+        };
+        let while_stmt = match &block.statements()[1] {
+            Stmt::While(while_stmt) => while_stmt,
+            _ => panic!("expected while loop"),
+        };
+        assert_eq!(while_stmt.keyword().lexeme(), "for");
+        assert_eq!(*while_stmt.keyword().line_number(), 1);
+        match while_stmt.condition() {
+            Expr::Binary(condition) => assert_eq!(*condition.operator().line_number(), 2),
+            _ => panic!("expected comparison"),
         }
-        else {
-            println!("{} at '{}' {}", token.line_number(), token.lexeme(), msg)
+        assert!(matches!(while_stmt.body(), Stmt::Print(_)));
+        match while_stmt.increment() {
+            Some(Expr::Assign(assign)) => assert_eq!(*assign.name().line_number(), 3),
+            _ => panic!("expected assignment"),
         }
     }
 
-    fn synchronize(&mut self) {
-        self.advance();
+    #[test]
+    fn adjacent_string_literals_are_concatenated() {
+        let printer = AstPrinter::default();
+        let statements =
+            parse("print \"foo\" \"bar\"\n  \"baz\";\nprint \"a\" + \"b\" \"c\";").unwrap();
+        let printed = statements
+            .iter()
+            .map(|stmt| match stmt {
+                Stmt::Print(stmt) => printer.print(stmt.expression().clone()),
+                _ => panic!("expected print statement"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(printed, vec!["foobarbaz", "(+ a bc)"]);
+    }
 
-        while !self.is_at_end() {
-            if self.previous().token_type() == &TokenType::Semicolon {
-                return;
+    #[test]
+    fn anonymous_functions_are_expressions() {
+        let printer = AstPrinter::default();
+        let statements =
+            parse("var add = fun (a, b) { return a + b; };\nfun () {};\nf(fun () {});").unwrap();
+        match (&statements[0], &statements[1], &statements[2]) {
+            (Stmt::Var(var), Stmt::Expression(bare), Stmt::Expression(call)) => {
+                match var.initializer() {
+                    Some(Expr::Function(function)) => {
+                        assert_eq!(function.params().len(), 2);
+                        assert!(matches!(function.body()[0], Stmt::Return(_)));
+                    }
+                    _ => panic!("expected function expression"),
+                }
+                assert_eq!(printer.print(bare.expression().clone()), "(fun ())");
+                assert_eq!(
+                    printer.print(call.expression().clone()),
+                    "(call f (fun ()))"
+                );
             }
+            _ => panic!("expected a declaration and two expression statements"),
+        }
+    }
 
-            match self.peek().token_type() {
-                TokenType::Class
-                    | TokenType::Fun
-                    | TokenType::Var
-                    | TokenType::For
-                    | TokenType::If
-                    | TokenType::While
-                    | TokenType::Print
-                    | TokenType::Return
-                        => return,
-                _ => ()
-            }
+    #[test]
+    fn every_statement_error_is_reported() {
+        let source = "var = 1;\nprint 1;\nprint (2;\nfun f() {\n  var x = ;\n  print x;\n}\n1 = 2;";
+        assert_eq!(
+            syntax_errors(source),
+            vec![
+                (1, "at '=': Expect variable name.".to_string()),
+                (3, "at ';': Expect ')' after expression.".to_string()),
+                (5, "at ';': Expect expression.".to_string()),
+                (8, "at '=': Invalid assignment target.".to_string()),
+            ]
+        );
+    }
 
-            self.advance();
-        }
+    #[test]
+    fn recovery_stops_at_break_and_continue() {
+        let source = "print 1 2\nbreak x\ncontinue y\nprint 3;";
+        assert_eq!(
+            syntax_errors(source),
+            vec![
+                (1, "at '2': Expect ';' after value.".to_string()),
+                (2, "at 'x': Expect ';' after 'break'.".to_string()),
+                (3, "at 'y': Expect ';' after 'continue'.".to_string()),
+            ]
+        );
     }
 }
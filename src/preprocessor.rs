@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use crate::error::{RloxError, RloxSyntaxError};
+use crate::error_codes::{ErrorCode, MALFORMED_IF, UNBALANCED_CONDITIONAL, UNKNOWN_DIRECTIVE};
+
+pub type Defines = HashMap<String, String>;
+
+/**
+ * Strips conditional sections out of a script before it is scanned:
+ *   #if NAME
+ *   ... kept only when NAME is defined and not "0", "false" or empty ...
+ *   #else
+ *   ... kept otherwise ...
+ *   #end
+ * Directives must start their line (after indentation) and may nest. Every removed line,
+ *   directives included, is left blank so line numbers in later errors still match the script.
+ */
+pub fn preprocess(source: &str, defines: &Defines) -> Result<String, RloxError> {
+    // one entry per open #if: (line it was opened on, whether its current branch is kept)
+    let mut open: Vec<(usize, bool)> = Vec::new();
+    let mut output = String::with_capacity(source.len());
+    for (i, line) in source.split_inclusive('\n').enumerate() {
+        let line_number = i + 1;
+        let newline = match line.ends_with('\n') {
+            true => "\n",
+            false => "",
+        };
+        let enclosing = open.iter().all(|(_, kept)| *kept);
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("#if") => {
+                let name = match (words.next(), words.next()) {
+                    (Some(name), None) => name,
+                    _ => {
+                        return Err(error(
+                            line_number,
+                            &MALFORMED_IF,
+                            "Expect a single name after '#if'.",
+                        ))
+                    }
+                };
+                open.push((line_number, is_set(defines, name)));
+            }
+            Some("#else") => match open.last_mut() {
+                Some((_, kept)) => *kept = !*kept,
+                None => {
+                    return Err(error(
+                        line_number,
+                        &UNBALANCED_CONDITIONAL,
+                        "'#else' without a matching '#if'.",
+                    ))
+                }
+            },
+            Some("#end") => match open.pop() {
+                Some(_) => {}
+                None => {
+                    return Err(error(
+                        line_number,
+                        &UNBALANCED_CONDITIONAL,
+                        "'#end' without a matching '#if'.",
+                    ))
+                }
+            },
+            Some(directive) if directive.starts_with('#') => {
+                let description = format!("Unknown directive '{}'.", directive);
+                return Err(error(line_number, &UNKNOWN_DIRECTIVE, &description));
+            }
+            _ if enclosing => {
+                output.push_str(line);
+                continue;
+            }
+            _ => {}
+        }
+        output.push_str(newline);
+    }
+    match open.last() {
+        Some((line_number, _)) => Err(error(
+            *line_number,
+            &UNBALANCED_CONDITIONAL,
+            "Expect '#end' to close '#if'.",
+        )),
+        None => Ok(output),
+    }
+}
+
+/**
+ * Parses a `--define` argument: `NAME=value`, or just `NAME` which defines it as "1".
+ */
+pub fn parse_define(definition: &str) -> Option<(String, String)> {
+    let (name, value) = match definition.split_once('=') {
+        Some((name, value)) => (name, value),
+        None => (definition, "1"),
+    };
+    match name.is_empty() {
+        true => None,
+        false => Some((name.to_string(), value.to_string())),
+    }
+}
+
+fn is_set(defines: &Defines, name: &str) -> bool {
+    match defines.get(name) {
+        Some(value) => !matches!(value.as_str(), "" | "0" | "false"),
+        None => false,
+    }
+}
+
+fn error(line_number: usize, code: &'static ErrorCode, description: &str) -> RloxError {
+    RloxError::SyntaxError(RloxSyntaxError {
+        code,
+        line_number,
+        column: 0,
+        length: 0,
+        description: description.to_string(),
+        origin: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_define, preprocess, Defines};
+    use crate::error::RloxError;
+
+    fn defines(pairs: &[(&str, &str)]) -> Defines {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn sections_are_kept_or_blanked_by_definition() {
+        let source = "print 1;\n#if DEBUG\nprint 2;\n#else\nprint 3;\n#end\nprint 4;";
+        assert_eq!(
+            preprocess(source, &defines(&[("DEBUG", "1")])).unwrap(),
+            "print 1;\n\nprint 2;\n\n\n\nprint 4;"
+        );
+        assert_eq!(
+            preprocess(source, &defines(&[("DEBUG", "false")])).unwrap(),
+            "print 1;\n\n\n\nprint 3;\n\nprint 4;"
+        );
+    }
+
+    #[test]
+    fn nested_sections_inside_a_stripped_one_stay_stripped() {
+        let source = "#if A\n  #if B\n  print 1;\n  #else\n  print 2;\n  #end\n#end\n";
+        assert_eq!(
+            preprocess(source, &defines(&[("B", "1")])).unwrap(),
+            "\n\n\n\n\n\n\n"
+        );
+    }
+
+    #[test]
+    fn unbalanced_directives_report_their_line() {
+        let line_of = |source: &str| match preprocess(source, &Defines::new()) {
+            Err(RloxError::SyntaxError(e)) => e.line_number(),
+            _ => panic!("expected a syntax error"),
+        };
+        assert_eq!(line_of("print 1;\n#if DEBUG\nprint 2;\n"), 2);
+        assert_eq!(line_of("print 1;\n#end\n"), 2);
+        assert_eq!(line_of("#else\n"), 1);
+        assert_eq!(line_of("#ifdef DEBUG\n#end\n"), 1);
+    }
+
+    #[test]
+    fn parse_define_defaults_the_value() {
+        assert_eq!(
+            parse_define("LEVEL=3"),
+            Some(("LEVEL".to_string(), "3".to_string()))
+        );
+        assert_eq!(
+            parse_define("DEBUG"),
+            Some(("DEBUG".to_string(), "1".to_string()))
+        );
+        assert_eq!(parse_define("=1"), None);
+    }
+}
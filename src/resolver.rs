@@ -0,0 +1,555 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::{RloxError, RloxSyntaxError};
+use crate::error_codes::{
+    ErrorCode, ALREADY_DECLARED, INHERITS_FROM_ITSELF, JUMP_OUTSIDE_LOOP, READ_IN_OWN_INITIALIZER,
+    RETURN_FROM_INITIALIZER, RETURN_OUTSIDE_FUNCTION, SUPER_OUTSIDE_CLASS,
+    SUPER_WITHOUT_SUPERCLASS, THIS_OUTSIDE_CLASS,
+};
+use crate::expr::{Expr, ExprVisitor};
+use crate::stmt::{FunctionStmt, Stmt, StmtVisitor};
+use crate::token::Token;
+
+/**
+ * The result of resolving a program: for every local variable reference, how many scopes out
+ *   from the reference its declaration lives, and its slot there. A scope's variables are
+ *   numbered in the order they are declared, which is the order they are defined when it runs.
+ *   References missing from the table are globals.
+ *   Entries are keyed by node identity, so they stay valid for clones of the resolved AST.
+ */
+#[derive(Default)]
+pub struct Locals {
+    slots: HashMap<*const (), (usize, usize)>,
+}
+
+impl Locals {
+    // the scope distance for a Variable, Assign, This or Super expression; None for globals
+    pub fn depth(&self, expr: &Expr) -> Option<usize> {
+        self.resolution(expr).map(|(depth, _)| depth)
+    }
+
+    // the scope distance and the slot in that scope, together
+    pub fn resolution(&self, expr: &Expr) -> Option<(usize, usize)> {
+        identity(expr).and_then(|key| self.slots.get(&key).copied())
+    }
+
+    // adds the locals of another resolved program, e.g. the next line typed into the REPL
+    pub fn extend(&mut self, other: Locals) {
+        self.slots.extend(other.slots);
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+/**
+ * Walks the AST once before execution and binds every variable reference to its declaration,
+ *   so closures capture the variable they lexically see and lookups never search environments.
+ *   Mistakes that are visible without running the program, like `return` at top level or `this`
+ *   outside a class, are reported here; like syntax errors, all of them are returned together.
+ */
+pub fn resolve(statements: &[Stmt]) -> Result<Locals, Vec<RloxError>> {
+    let resolver = Resolver {
+        scopes: RefCell::new(Vec::new()),
+        locals: RefCell::new(Locals::default()),
+        errors: RefCell::new(Vec::new()),
+        function: Cell::new(FunctionType::None),
+        class: Cell::new(ClassType::None),
+        in_loop: Cell::new(false),
+    };
+    resolver.stmts(statements);
+    let errors = resolver.errors.into_inner();
+    match errors.is_empty() {
+        true => Ok(resolver.locals.into_inner()),
+        false => Err(errors),
+    }
+}
+
+// what kind of function body, if any, the resolver is currently inside
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+// a variable declared in a local scope
+struct Local {
+    slot: usize,
+    defined: bool, // false while its initializer is being resolved
+}
+
+struct Resolver {
+    // one map per enclosing local scope, innermost last
+    scopes: RefCell<Vec<HashMap<String, Local>>>,
+    locals: RefCell<Locals>,
+    errors: RefCell<Vec<RloxError>>,
+    function: Cell<FunctionType>,
+    class: Cell<ClassType>,
+    // whether a `break` or `continue` here would have a loop to act on; a function body starts outside any loop
+    in_loop: Cell<bool>,
+}
+
+impl ExprVisitor<()> for Resolver {
+    fn visit_expr(&self, expr: &Expr) {
+        match expr {
+            Expr::Assign(assign) => {
+                self.expr(assign.value());
+                self.resolve_local(expr, assign.name());
+            }
+            Expr::Binary(expr) => {
+                self.expr(expr.lhs());
+                self.expr(expr.rhs());
+            }
+            Expr::Call(expr) => {
+                self.expr(expr.callee());
+                expr.arguments()
+                    .iter()
+                    .for_each(|argument| self.expr(argument));
+            }
+            Expr::Function(function) => {
+                self.function(function.params(), function.body(), FunctionType::Function)
+            }
+            Expr::Get(expr) => self.expr(expr.object()),
+            Expr::Grouping(expr) => self.expr(expr.expression()),
+            Expr::Literal(_) => {}
+            Expr::Logical(expr) => {
+                self.expr(expr.lhs());
+                self.expr(expr.rhs());
+            }
+            Expr::Set(expr) => {
+                self.expr(expr.value());
+                self.expr(expr.object());
+            }
+            Expr::Super(sup) => match self.class.get() {
+                ClassType::None => self.error(
+                    sup.keyword(),
+                    &SUPER_OUTSIDE_CLASS,
+                    "Can't use 'super' outside of a class.",
+                ),
+                ClassType::Class => self.error(
+                    sup.keyword(),
+                    &SUPER_WITHOUT_SUPERCLASS,
+                    "Can't use 'super' in a class with no superclass.",
+                ),
+                ClassType::Subclass => self.resolve_local(expr, sup.keyword()),
+            },
+            Expr::This(this) => match self.class.get() {
+                ClassType::None => self.error(
+                    this.keyword(),
+                    &THIS_OUTSIDE_CLASS,
+                    "Can't use 'this' outside of a class.",
+                ),
+                _ => self.resolve_local(expr, this.keyword()),
+            },
+            Expr::Unary(expr) => self.expr(expr.rhs()),
+            Expr::Variable(variable) => {
+                let name = variable.name();
+                let declared_only = self
+                    .scopes
+                    .borrow()
+                    .last()
+                    .and_then(|scope| scope.get(name.lexeme()).map(|local| local.defined))
+                    == Some(false);
+                if declared_only {
+                    self.error(
+                        name,
+                        &READ_IN_OWN_INITIALIZER,
+                        "Can't read local variable in its own initializer.",
+                    );
+                }
+                self.resolve_local(expr, name);
+            }
+        }
+    }
+}
+
+impl StmtVisitor<()> for Resolver {
+    fn visit_stmt(&self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block(stmt) => {
+                self.begin_scope();
+                self.stmts(stmt.statements());
+                self.end_scope();
+            }
+            Stmt::Break(stmt) => {
+                if !self.in_loop.get() {
+                    self.error(
+                        stmt.keyword(),
+                        &JUMP_OUTSIDE_LOOP,
+                        "Can't use 'break' outside of a loop.",
+                    );
+                }
+            }
+            Stmt::Continue(stmt) => {
+                if !self.in_loop.get() {
+                    self.error(
+                        stmt.keyword(),
+                        &JUMP_OUTSIDE_LOOP,
+                        "Can't use 'continue' outside of a loop.",
+                    );
+                }
+            }
+            Stmt::Class(stmt) => {
+                let enclosing = self.class.replace(ClassType::Class);
+                self.declare(stmt.name());
+                self.define(stmt.name());
+                if let Some(superclass) = stmt.superclass() {
+                    if let Expr::Variable(variable) = superclass {
+                        if variable.name().lexeme() == stmt.name().lexeme() {
+                            self.error(
+                                variable.name(),
+                                &INHERITS_FROM_ITSELF,
+                                "A class can't inherit from itself.",
+                            );
+                        }
+                    }
+                    self.class.set(ClassType::Subclass);
+                    self.expr(superclass);
+                    self.begin_scope();
+                    self.define_name("super");
+                }
+                self.begin_scope();
+                self.define_name("this");
+                for method in stmt.methods() {
+                    let kind = match method.name().lexeme() {
+                        "init" => FunctionType::Initializer,
+                        _ => FunctionType::Method,
+                    };
+                    self.function(method.params(), method.body(), kind);
+                }
+                self.end_scope();
+                if stmt.superclass().is_some() {
+                    self.end_scope();
+                }
+                self.class.set(enclosing);
+            }
+            Stmt::Expression(stmt) => self.expr(stmt.expression()),
+            Stmt::Function(stmt) => self.function_declaration(stmt),
+            Stmt::If(stmt) => {
+                self.expr(stmt.condition());
+                self.stmt(stmt.then_branch());
+                if let Some(else_branch) = stmt.else_branch() {
+                    self.stmt(else_branch);
+                }
+            }
+            Stmt::Print(stmt) => self.expr(stmt.expression()),
+            Stmt::Return(stmt) => {
+                if self.function.get() == FunctionType::None {
+                    self.error(
+                        stmt.keyword(),
+                        &RETURN_OUTSIDE_FUNCTION,
+                        "Can't return from top-level code.",
+                    );
+                }
+                if let Some(value) = stmt.value() {
+                    if self.function.get() == FunctionType::Initializer {
+                        self.error(
+                            stmt.keyword(),
+                            &RETURN_FROM_INITIALIZER,
+                            "Can't return a value from an initializer.",
+                        );
+                    }
+                    self.expr(value);
+                }
+            }
+            Stmt::Var(stmt) => {
+                self.declare(stmt.name());
+                if let Some(initializer) = stmt.initializer() {
+                    self.expr(initializer);
+                }
+                self.define(stmt.name());
+            }
+            Stmt::While(stmt) => {
+                self.expr(stmt.condition());
+                let enclosing = self.in_loop.replace(true);
+                self.stmt(stmt.body());
+                self.in_loop.set(enclosing);
+                if let Some(increment) = stmt.increment() {
+                    self.expr(increment);
+                }
+            }
+        }
+    }
+}
+
+impl Resolver {
+    fn stmts(&self, statements: &[Stmt]) {
+        statements.iter().for_each(|stmt| self.stmt(stmt));
+    }
+
+    fn stmt(&self, stmt: &Stmt) {
+        stmt.accept::<()>(self)
+    }
+
+    // the name is bound before the body is resolved so the function can call itself
+    fn function_declaration(&self, stmt: &FunctionStmt) {
+        self.declare(stmt.name());
+        self.define(stmt.name());
+        self.function(stmt.params(), stmt.body(), FunctionType::Function);
+    }
+
+    fn function(&self, params: &[Token], body: &[Stmt], kind: FunctionType) {
+        let enclosing = self.function.replace(kind);
+        let enclosing_loop = self.in_loop.replace(false);
+        self.begin_scope();
+        for param in params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.stmts(body);
+        self.end_scope();
+        self.in_loop.set(enclosing_loop);
+        self.function.set(enclosing);
+    }
+
+    fn expr(&self, expr: &Expr) {
+        expr.accept::<()>(self)
+    }
+
+    fn resolve_local(&self, expr: &Expr, name: &Token) {
+        let scopes = self.scopes.borrow();
+        let found =
+            scopes.iter().rev().enumerate().find_map(|(depth, scope)| {
+                scope.get(name.lexeme()).map(|local| (depth, local.slot))
+            });
+        if let (Some(resolution), Some(key)) = (found, identity(expr)) {
+            self.locals.borrow_mut().slots.insert(key, resolution);
+        }
+    }
+
+    fn begin_scope(&self) {
+        self.scopes.borrow_mut().push(HashMap::new());
+    }
+
+    fn end_scope(&self) {
+        self.scopes.borrow_mut().pop();
+    }
+
+    // globals are not tracked: only names declared inside some scope resolve
+    fn declare(&self, name: &Token) {
+        let redeclared = match self.scopes.borrow_mut().last_mut() {
+            Some(scope) if scope.contains_key(name.lexeme()) => true,
+            Some(scope) => {
+                let local = Local {
+                    slot: scope.len(),
+                    defined: false,
+                };
+                scope.insert(name.lexeme().to_string(), local);
+                false
+            }
+            None => false,
+        };
+        if redeclared {
+            self.error(
+                name,
+                &ALREADY_DECLARED,
+                "Already a variable with this name in this scope.",
+            );
+        }
+    }
+
+    fn define(&self, name: &Token) {
+        if let Some(local) = self
+            .scopes
+            .borrow_mut()
+            .last_mut()
+            .and_then(|scope| scope.get_mut(name.lexeme()))
+        {
+            local.defined = true;
+        }
+    }
+
+    // declares and defines a name the interpreter binds itself, i.e. `this` or `super`
+    fn define_name(&self, name: &str) {
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            let local = Local {
+                slot: scope.len(),
+                defined: true,
+            };
+            scope.insert(name.to_string(), local);
+        }
+    }
+
+    fn error(&self, token: &Token, code: &'static ErrorCode, msg: &str) {
+        self.errors
+            .borrow_mut()
+            .push(RloxError::SyntaxError(RloxSyntaxError::at(
+                token,
+                code,
+                format!("at '{}': {}", token.lexeme(), msg),
+            )));
+    }
+}
+
+fn identity(expr: &Expr) -> Option<*const ()> {
+    match expr {
+        Expr::Assign(expr) => Some(Rc::as_ptr(expr) as *const ()),
+        Expr::Super(expr) => Some(Rc::as_ptr(expr) as *const ()),
+        Expr::This(expr) => Some(Rc::as_ptr(expr) as *const ()),
+        Expr::Variable(expr) => Some(Rc::as_ptr(expr) as *const ()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve;
+    use crate::error::RloxError;
+    use crate::expr::Expr;
+    use crate::parse_program;
+    use crate::stmt::Stmt;
+
+    // the expression printed by each print statement, in source order
+    fn printed(statements: &[Stmt]) -> Vec<Expr> {
+        let mut exprs = Vec::new();
+        for stmt in statements {
+            match stmt {
+                Stmt::Print(stmt) => exprs.push(stmt.expression().clone()),
+                Stmt::Block(stmt) => exprs.extend(printed(stmt.statements())),
+                Stmt::Function(stmt) => exprs.extend(printed(stmt.body())),
+                _ => {}
+            }
+        }
+        exprs
+    }
+
+    #[test]
+    fn locals_resolve_to_scope_distance_and_globals_are_unresolved() {
+        let source = "var a = 1;\n{\n  var b = 2;\n  {\n    var c = 3;\n    print a;\n    print b;\n    print c;\n  }\n}";
+        let ast = parse_program(source).ast;
+        let locals = resolve(&ast).unwrap();
+        let depths = printed(&ast)
+            .iter()
+            .map(|expr| locals.depth(expr))
+            .collect::<Vec<_>>();
+        assert_eq!(depths, vec![None, Some(1), Some(0)]);
+    }
+
+    #[test]
+    fn locals_are_numbered_in_declaration_order_within_their_scope() {
+        let source = "{\n  var a = 1;\n  var b = 2;\n  {\n    var c = 3;\n    print b;\n    print c;\n    print a;\n  }\n}";
+        let ast = parse_program(source).ast;
+        let locals = resolve(&ast).unwrap();
+        let resolutions = printed(&ast)
+            .iter()
+            .map(|expr| locals.resolution(expr))
+            .collect::<Vec<_>>();
+        assert_eq!(resolutions, vec![Some((1, 1)), Some((0, 0)), Some((1, 0))]);
+    }
+
+    #[test]
+    fn closures_bind_the_variable_they_lexically_see() {
+        // the book's example: both prints must see the outer `a`, not the later shadowing one
+        let source = "{\n  var a = \"global\";\n  {\n    fun show() {\n      print a;\n    }\n    show();\n    var a = \"block\";\n    show();\n  }\n}";
+        let ast = parse_program(source).ast;
+        let locals = resolve(&ast).unwrap();
+        let a = &printed(&ast)[0];
+        // function scope, then the inner block, then the block declaring `a`
+        assert_eq!(locals.depth(a), Some(2));
+        assert_eq!(locals.depth(&a.clone()), Some(2));
+    }
+
+    #[test]
+    fn parameters_and_this_are_locals() {
+        let source = "fun f(x) {\n  print x;\n}\nclass A {\n  m() {\n    return this;\n  }\n}";
+        let ast = parse_program(source).ast;
+        let locals = resolve(&ast).unwrap();
+        assert_eq!(locals.depth(&printed(&ast)[0]), Some(0));
+        match &ast[1] {
+            Stmt::Class(class) => match &class.methods()[0].body()[0] {
+                Stmt::Return(ret) => {
+                    assert_eq!(locals.depth(ret.value().as_ref().unwrap()), Some(1))
+                }
+                _ => panic!("expected return statement"),
+            },
+            _ => panic!("expected class declaration"),
+        }
+        assert_eq!(locals.len(), 2);
+    }
+
+    fn resolve_errors(source: &str) -> Vec<(usize, String)> {
+        match resolve(&parse_program(source).ast) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors
+                .iter()
+                .map(|e| match e {
+                    RloxError::SyntaxError(e) => (e.line_number(), e.description().to_string()),
+                    _ => panic!("expected syntax error"),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn semantic_errors_are_all_reported() {
+        let source = "{\n  var a = a;\n  var b;\n  var b;\n}\nreturn 1;\nprint this;\nprint super.m;\nclass A {\n  init() {\n    return 1;\n  }\n  m() {\n    super.m();\n  }\n}\nclass B < B {}\nbreak;\nwhile (true) {\n  fun f() {\n    break;\n  }\n}\ncontinue;";
+        assert_eq!(
+            resolve_errors(source),
+            vec![
+                (
+                    2,
+                    "at 'a': Can't read local variable in its own initializer.".to_string()
+                ),
+                (
+                    4,
+                    "at 'b': Already a variable with this name in this scope.".to_string()
+                ),
+                (
+                    6,
+                    "at 'return': Can't return from top-level code.".to_string()
+                ),
+                (
+                    7,
+                    "at 'this': Can't use 'this' outside of a class.".to_string()
+                ),
+                (
+                    8,
+                    "at 'super': Can't use 'super' outside of a class.".to_string()
+                ),
+                (
+                    11,
+                    "at 'return': Can't return a value from an initializer.".to_string()
+                ),
+                (
+                    14,
+                    "at 'super': Can't use 'super' in a class with no superclass.".to_string()
+                ),
+                (17, "at 'B': A class can't inherit from itself.".to_string()),
+                (
+                    18,
+                    "at 'break': Can't use 'break' outside of a loop.".to_string()
+                ),
+                (
+                    21,
+                    "at 'break': Can't use 'break' outside of a loop.".to_string()
+                ),
+                (
+                    24,
+                    "at 'continue': Can't use 'continue' outside of a loop.".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn valid_uses_are_not_errors() {
+        let source = "var a = 1;\nvar a = a;\nfun f() {\n  return;\n}\nclass A {\n  init() {\n    return;\n  }\n}\nclass B < A {\n  m() {\n    return super.m(this);\n  }\n}\nvar g = fun () { return 1; };\nwhile (true) {\n  if (a) break;\n  for (;;) { { break; } }\n  continue;\n}";
+        assert_eq!(resolve_errors(source), Vec::new());
+    }
+}
@@ -1,51 +1,90 @@
 use crate::error::{RloxError, RloxSyntaxError};
+use crate::error_codes::{
+    ErrorCode, INVALID_NUMBER_LITERAL, UNEXPECTED_CHARACTER, UNTERMINATED_STRING,
+};
 use crate::token::{get_keyword_token_type, Literal, Token, TokenType};
 
-pub(crate) struct Scanner {
+/**
+ * Scripts saved on Windows may start with a UTF-8 byte order mark and end lines with "\r\n".
+ *   Strip the former and rewrite the latter as "\n" so that line numbers and multi-line string
+ *   literals come out the same as for the script saved with Unix line endings.
+ */
+pub fn normalize_source(data: &str) -> String {
+    let data = data.strip_prefix('\u{feff}').unwrap_or(data);
+    data.replace("\r\n", "\n")
+}
+
+/**
+ * Positions are byte offsets into the source, which is scanned in place rather than copied into
+ *   a Vec<char> that would take four bytes per character.
+ */
+pub struct Scanner {
     source: String,
     tokens: Vec<Token>,
+    errors: Vec<RloxError>,
     start: usize,
     current: usize,
     line: usize,
+    start_line: usize, // the line `start` is on, which a string spanning lines begins on
+    column: usize,     // the column `start` is at, counted in characters from 1
+    current_column: usize, // the column `current` is at, kept as `current` moves
 }
 
 impl Scanner {
-    pub(crate) fn new(source: String) -> Self {
+    // the tokens of a source with no lexical errors, or the first error in it
+    pub fn new(source: String) -> Result<Self, RloxError> {
+        let (scanner, mut errors) = Self::scan(source);
+        match errors.is_empty() {
+            true => Ok(scanner),
+            false => Err(errors.remove(0)),
+        }
+    }
+
+    /**
+     * Scans the whole source. A lexical error does not stop scanning: the bad lexeme is reported
+     *   and left out of the tokens, and scanning carries on after it, so every lexical error is
+     *   returned together along with the tokens around them.
+     */
+    pub fn scan(source: String) -> (Self, Vec<RloxError>) {
         let mut s = Scanner {
             source,
             tokens: Vec::new(),
+            errors: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
+            start_line: 1,
+            column: 1,
+            current_column: 1,
         };
-        match s.scan_tokens() {
-            Ok(_) => (),
-            Err(e) => panic!("{}", e),
-        }
-        s
+        s.scan_tokens();
+        let errors = std::mem::take(&mut s.errors);
+        (s, errors)
     }
 
-    pub(crate) fn tokens(&self) -> &Vec<Token> {
+    pub fn tokens(&self) -> &Vec<Token> {
         &self.tokens
     }
 
-    fn scan_tokens(&mut self) -> Result<(), RloxError> {
+    fn scan_tokens(&mut self) {
         while !self.is_at_end() {
             self.start = self.current;
-            self.scan_token()?;
+            self.start_line = self.line;
+            self.column = self.current_column;
+            if let Err(e) = self.scan_token() {
+                self.errors.push(e);
+            }
+        }
+        match Token::new(TokenType::Eof, "".to_string(), None, self.line) {
+            Ok(token) => self.tokens.push(token.with_column(self.current_column)),
+            Err(e) => self.errors.push(e),
         }
-        let token = Token::new(TokenType::Eof, "".to_string(), None, self.line)?;
-        self.tokens.push(token);
-        Ok(())
     }
 
     fn is_at_end(&self) -> bool {
         self.current >= self.source.len()
     }
 
-    /*
-     * TODO: continue scanning even if error
-     */
     fn scan_token(&mut self) -> Result<(), RloxError> {
         let c: char = self.advance();
         match c {
@@ -61,7 +100,7 @@ impl Scanner {
             '*' => self.add_token(TokenType::Star, None),
             '!' => match self.advance_if_match('=') {
                 true => self.add_token(TokenType::BangEqual, None),
-                false => self.add_token(TokenType::Equal, None),
+                false => self.add_token(TokenType::Bang, None),
             },
             '=' => match self.advance_if_match('=') {
                 true => self.add_token(TokenType::EqualEqual, None),
@@ -85,6 +124,7 @@ impl Scanner {
             ' ' | '\r' | '\t' => Ok(()),
             '\n' => {
                 self.line += 1;
+                self.current_column = 1;
                 Ok(())
             }
             '"' => self.consume_string_literal(),
@@ -92,10 +132,12 @@ impl Scanner {
                 true => self.consume_number_literal(),
                 false => match c == '_' || c.is_alphabetic() {
                     true => self.consume_identifier(),
-                    false => Err(RloxError::SyntaxError(RloxSyntaxError {
-                        line_number: self.line,
-                        description: "Unexpected character.".to_string(),
-                    })),
+                    false => Err(self.error(
+                        self.line,
+                        self.column,
+                        &UNEXPECTED_CHARACTER,
+                        "Unexpected character.",
+                    )),
                 },
             },
         }?;
@@ -103,26 +145,19 @@ impl Scanner {
     }
 
     fn advance(&mut self) -> char {
-        let current_char = *(self
-            .source
-            .chars()
-            .collect::<Vec<_>>()
-            .get(self.current)
-            .unwrap());
-        self.current += 1;
-        return current_char;
+        let current_char = self.peek();
+        self.current += current_char.len_utf8();
+        self.current_column += 1;
+        current_char
     }
 
     fn advance_if_match(&mut self, expected: char) -> bool {
-        if self.is_at_end() {
-            return false;
-        }
-        let c = self.source.chars().nth(self.current).unwrap();
-        if c != expected {
+        if self.is_at_end() || self.peek() != expected {
             return false;
         }
-        self.current += 1;
-        return true;
+        self.current += expected.len_utf8();
+        self.current_column += 1;
+        true
     }
 
     fn add_token(
@@ -130,13 +165,9 @@ impl Scanner {
         token_type: TokenType,
         literal: Option<Literal>,
     ) -> Result<(), RloxError> {
-        let text = &self.source[self.start..self.current];
-        self.tokens.push(Token::new(
-            token_type,
-            text.to_string(),
-            literal,
-            self.line,
-        )?);
+        let text = self.text(self.start, self.current);
+        let token = Token::new(token_type, text, literal, self.start_line)?;
+        self.tokens.push(token.with_column(self.column));
         Ok(())
     }
 
@@ -146,29 +177,34 @@ impl Scanner {
         }
     }
 
-    fn peek(&mut self) -> char {
-        match self.is_at_end() {
-            true => '\0',
-            false => self.source.chars().nth(self.current).unwrap(),
-        }
+    fn text(&self, start: usize, end: usize) -> String {
+        self.source[start..end].to_string()
     }
 
+    fn peek(&self) -> char {
+        self.source[self.current..].chars().next().unwrap_or('\0')
+    }
+
+    // an unterminated string is reported where it starts, at the opening quote
     fn consume_string_literal(&mut self) -> Result<(), RloxError> {
         while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
+            let c = self.advance();
+            if c == '\n' {
                 self.line += 1;
+                self.current_column = 1;
             }
-            self.advance();
         }
         if self.is_at_end() {
-            return Err(RloxError::SyntaxError(RloxSyntaxError {
-                line_number: self.line,
-                description: "Unterminated string.".to_string(),
-            }));
+            return Err(self.error(
+                self.start_line,
+                self.column,
+                &UNTERMINATED_STRING,
+                "Unterminated string.",
+            ));
         }
         self.advance();
-        let value = &self.source[&self.start + 1..&self.current - 1];
-        let literal = Some(Literal::String(value.to_string()));
+        let value = self.text(self.start + 1, self.current - 1);
+        let literal = Some(Literal::String(value));
         self.add_token(TokenType::String, literal)?;
         Ok(())
     }
@@ -183,25 +219,52 @@ impl Scanner {
                 self.advance();
             }
         }
-        let value = &self.source[self.start..self.current]
-            .parse::<f32>()
-            .unwrap();
-        self.add_token(TokenType::Number, Some(Literal::Float(*value)))
+        let value = self
+            .text(self.start, self.current)
+            .parse::<f64>()
+            .map_err(|_| {
+                self.error(
+                    self.line,
+                    self.column,
+                    &INVALID_NUMBER_LITERAL,
+                    "Invalid number literal.",
+                )
+            })?;
+        self.add_token(TokenType::Number, Some(Literal::Float(value)))
+    }
+
+    // an error in the lexeme being scanned, which starts on `line` at `column`
+    fn error(
+        &self,
+        line: usize,
+        column: usize,
+        code: &'static ErrorCode,
+        description: &str,
+    ) -> RloxError {
+        let length = match line == self.line {
+            true => self.text(self.start, self.current).chars().count().max(1),
+            false => 1,
+        };
+        RloxError::SyntaxError(RloxSyntaxError {
+            code,
+            line_number: line,
+            column,
+            length,
+            description: description.to_string(),
+            origin: None,
+        })
     }
 
     fn peek_next(&self) -> char {
-        match self.current + 1 >= self.source.len() {
-            true => '\0',
-            false => self.source.chars().nth(self.current + 1).unwrap(),
-        }
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
     fn consume_identifier(&mut self) -> Result<(), RloxError> {
         while self.peek().is_alphanumeric() || self.peek() == '_' {
             self.advance();
         }
-        let text = &self.source[self.start..self.current];
-        match get_keyword_token_type(text) {
+        let text = self.text(self.start, self.current);
+        match get_keyword_token_type(&text) {
             Some(t) => self.add_token(t, None),
             None => self.add_token(TokenType::Identifier, None),
         }
@@ -212,31 +275,113 @@ impl Scanner {
 mod tests {
     use crate::token::{Literal, Token, TokenType};
 
-    use super::Scanner;
+    use super::{normalize_source, Scanner};
 
     #[test]
     fn given_valid_input() {
         let source = String::from("if(example_var){ print \"hi!\"; }");
-        let scanner = Scanner::new(source);
+        let scanner = Scanner::new(source).unwrap();
         let received_tokens = scanner.tokens();
+        let token = |token_type, lexeme: &str, column| {
+            Token::new(token_type, lexeme.to_string(), None, 1)
+                .unwrap()
+                .with_column(column)
+        };
         let expected_tokens = &vec![
-            Token::new(TokenType::If, "if".to_string(), None, 1).unwrap(),
-            Token::new(TokenType::LeftParen, "(".to_string(), None, 1).unwrap(),
-            Token::new(TokenType::Identifier, "example_var".to_string(), None, 1).unwrap(),
-            Token::new(TokenType::RightParen, ")".to_string(), None, 1).unwrap(),
-            Token::new(TokenType::LeftBrace, "{".to_string(), None, 1).unwrap(),
-            Token::new(TokenType::Print, "print".to_string(), None, 1).unwrap(),
+            token(TokenType::If, "if", 1),
+            token(TokenType::LeftParen, "(", 3),
+            token(TokenType::Identifier, "example_var", 4),
+            token(TokenType::RightParen, ")", 15),
+            token(TokenType::LeftBrace, "{", 16),
+            token(TokenType::Print, "print", 18),
             Token::new(
                 TokenType::String,
                 "\"hi!\"".to_string(),
                 Some(Literal::String("hi!".to_string())),
                 1,
             )
-            .unwrap(),
-            Token::new(TokenType::Semicolon, ";".to_string(), None, 1).unwrap(),
-            Token::new(TokenType::RightBrace, "}".to_string(), None, 1).unwrap(),
-            Token::new(TokenType::Eof, "".to_string(), None, 1).unwrap(),
+            .unwrap()
+            .with_column(24),
+            token(TokenType::Semicolon, ";", 29),
+            token(TokenType::RightBrace, "}", 31),
+            token(TokenType::Eof, "", 32),
         ];
         assert_eq!(expected_tokens, received_tokens);
     }
+
+    #[test]
+    fn columns_count_characters_from_the_start_of_each_line() {
+        let source = String::from("var s = \"a\nb\";\n  print \"世界\" + s;");
+        let scanner = Scanner::new(source).unwrap();
+        let positions = scanner
+            .tokens()
+            .iter()
+            .map(|t| (*t.line_number(), t.column()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            positions,
+            vec![
+                (1, 1),
+                (1, 5),
+                (1, 7),
+                // the string spanning lines starts at its opening quote
+                (1, 9),
+                (2, 3),
+                (3, 3),
+                (3, 9),
+                (3, 14),
+                (3, 16),
+                (3, 17),
+                (3, 18)
+            ]
+        );
+    }
+
+    #[test]
+    fn given_non_ascii_input() {
+        let source = String::from("print \"héllo, 世界\"; // ✓\nvar naïve;");
+        let scanner = Scanner::new(source).unwrap();
+        let received_tokens = scanner.tokens();
+        assert_eq!(
+            received_tokens[1].literal(),
+            &Some(Literal::String("héllo, 世界".to_string()))
+        );
+        assert_eq!(received_tokens[4].lexeme(), "naïve");
+        assert_eq!(*received_tokens[4].line_number(), 2);
+    }
+
+    #[test]
+    fn given_unterminated_string() {
+        let source = String::from("print \"hi!;");
+        assert!(Scanner::new(source).is_err());
+    }
+
+    #[test]
+    fn scanning_continues_after_an_error() {
+        let (scanner, errors) = Scanner::scan(String::from("var a = @;\nprint a # 1;"));
+        let lexemes = scanner
+            .tokens()
+            .iter()
+            .map(|t| t.lexeme())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lexemes,
+            vec!["var", "a", "=", ";", "print", "a", "1", ";", ""]
+        );
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn normalize_source_strips_bom_and_crlf() {
+        let source = normalize_source("\u{feff}print \"a\r\nb\";\r\nprint 1;\r\n");
+        assert_eq!(source, "print \"a\nb\";\nprint 1;\n");
+    }
+
+    #[test]
+    fn normalize_source_keeps_unix_source_and_inner_bom() {
+        assert_eq!(
+            normalize_source("print \"\u{feff}\";\n"),
+            "print \"\u{feff}\";\n"
+        );
+    }
 }
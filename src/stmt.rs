@@ -0,0 +1,293 @@
+#![allow(clippy::new_ret_no_self)] // constructors hand back the wrapping Stmt variant
+
+use std::rc::Rc;
+
+use crate::expr::Expr;
+use crate::token::Token;
+
+#[derive(Clone)]
+pub enum Stmt {
+    Block(Rc<BlockStmt>),
+    Break(Rc<BreakStmt>),
+    Class(Rc<ClassStmt>),
+    Continue(Rc<ContinueStmt>),
+    Expression(Rc<ExpressionStmt>),
+    Function(Rc<FunctionStmt>),
+    If(Rc<IfStmt>),
+    Print(Rc<PrintStmt>),
+    Return(Rc<ReturnStmt>),
+    Var(Rc<VarStmt>),
+    While(Rc<WhileStmt>),
+}
+
+impl Stmt {
+    pub fn accept<T>(&self, visitor: &dyn StmtVisitor<T>) -> T {
+        visitor.visit_stmt(self)
+    }
+
+    // the line the statement starts on; None for an empty block or e.g. `1;`, with no token to go by
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Stmt::Block(block) => block.statements().iter().find_map(Stmt::line),
+            Stmt::Break(stmt) => Some(*stmt.keyword().line_number()),
+            Stmt::Class(stmt) => Some(*stmt.name().line_number()),
+            Stmt::Continue(stmt) => Some(*stmt.keyword().line_number()),
+            Stmt::Expression(stmt) => stmt.expression().line(),
+            Stmt::Function(stmt) => Some(*stmt.name().line_number()),
+            Stmt::If(stmt) => Some(*stmt.keyword().line_number()),
+            Stmt::Print(stmt) => Some(*stmt.keyword().line_number()),
+            Stmt::Return(stmt) => Some(*stmt.keyword().line_number()),
+            Stmt::Var(stmt) => Some(*stmt.name().line_number()),
+            Stmt::While(stmt) => Some(*stmt.keyword().line_number()),
+        }
+    }
+}
+
+/**
+ * The statement counterpart of ExprVisitor: one match over every Stmt variant, so a new
+ *   statement kind fails to compile until every pass handles it.
+ */
+pub trait StmtVisitor<T> {
+    fn visit_stmt(&self, stmt: &Stmt) -> T;
+}
+
+pub struct BlockStmt {
+    statements: Vec<Stmt>,
+}
+
+impl BlockStmt {
+    pub fn new(statements: Vec<Stmt>) -> Stmt {
+        Stmt::Block(Rc::new(BlockStmt { statements }))
+    }
+
+    pub fn statements(&self) -> &[Stmt] {
+        &self.statements
+    }
+}
+
+pub struct BreakStmt {
+    keyword: Token, // the `break` token, kept for reporting a break outside of any loop
+}
+
+impl BreakStmt {
+    pub fn new(keyword: Token) -> Stmt {
+        Stmt::Break(Rc::new(BreakStmt { keyword }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+}
+
+pub struct ClassStmt {
+    name: Token,
+    superclass: Option<Expr>, // always an Expr::Variable naming the superclass
+    methods: Vec<Rc<FunctionStmt>>,
+}
+
+impl ClassStmt {
+    pub fn new(name: Token, superclass: Option<Expr>, methods: Vec<Rc<FunctionStmt>>) -> Stmt {
+        Stmt::Class(Rc::new(ClassStmt {
+            name,
+            superclass,
+            methods,
+        }))
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+
+    pub fn superclass(&self) -> &Option<Expr> {
+        &self.superclass
+    }
+
+    pub fn methods(&self) -> &[Rc<FunctionStmt>] {
+        &self.methods
+    }
+}
+
+pub struct ContinueStmt {
+    keyword: Token, // the `continue` token, kept for reporting a continue outside of any loop
+}
+
+impl ContinueStmt {
+    pub fn new(keyword: Token) -> Stmt {
+        Stmt::Continue(Rc::new(ContinueStmt { keyword }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+}
+
+pub struct ExpressionStmt {
+    expression: Expr,
+}
+
+impl ExpressionStmt {
+    pub fn new(expression: Expr) -> Stmt {
+        Stmt::Expression(Rc::new(ExpressionStmt { expression }))
+    }
+
+    pub fn expression(&self) -> &Expr {
+        &self.expression
+    }
+}
+
+pub struct FunctionStmt {
+    name: Token,
+    params: Vec<Token>,
+    body: Vec<Stmt>,
+}
+
+impl FunctionStmt {
+    pub fn new(name: Token, params: Vec<Token>, body: Vec<Stmt>) -> Stmt {
+        Stmt::Function(Rc::new(FunctionStmt { name, params, body }))
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+
+    pub fn params(&self) -> &[Token] {
+        &self.params
+    }
+
+    pub fn body(&self) -> &[Stmt] {
+        &self.body
+    }
+}
+
+pub struct IfStmt {
+    keyword: Token,
+    condition: Expr,
+    then_branch: Stmt,
+    else_branch: Option<Stmt>,
+}
+
+impl IfStmt {
+    pub fn new(
+        keyword: Token,
+        condition: Expr,
+        then_branch: Stmt,
+        else_branch: Option<Stmt>,
+    ) -> Stmt {
+        Stmt::If(Rc::new(IfStmt {
+            keyword,
+            condition,
+            then_branch,
+            else_branch,
+        }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+
+    pub fn condition(&self) -> &Expr {
+        &self.condition
+    }
+
+    pub fn then_branch(&self) -> &Stmt {
+        &self.then_branch
+    }
+
+    pub fn else_branch(&self) -> &Option<Stmt> {
+        &self.else_branch
+    }
+}
+
+pub struct PrintStmt {
+    keyword: Token, // the `print` token, kept for the line the statement is on
+    expression: Expr,
+}
+
+impl PrintStmt {
+    pub fn new(keyword: Token, expression: Expr) -> Stmt {
+        Stmt::Print(Rc::new(PrintStmt {
+            keyword,
+            expression,
+        }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+
+    pub fn expression(&self) -> &Expr {
+        &self.expression
+    }
+}
+
+pub struct ReturnStmt {
+    keyword: Token, // the `return` token, kept for reporting e.g. a return at top level
+    value: Option<Expr>,
+}
+
+impl ReturnStmt {
+    pub fn new(keyword: Token, value: Option<Expr>) -> Stmt {
+        Stmt::Return(Rc::new(ReturnStmt { keyword, value }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+
+    pub fn value(&self) -> &Option<Expr> {
+        &self.value
+    }
+}
+
+pub struct VarStmt {
+    name: Token,
+    initializer: Option<Expr>,
+}
+
+impl VarStmt {
+    pub fn new(name: Token, initializer: Option<Expr>) -> Stmt {
+        Stmt::Var(Rc::new(VarStmt { name, initializer }))
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+
+    pub fn initializer(&self) -> &Option<Expr> {
+        &self.initializer
+    }
+}
+
+pub struct WhileStmt {
+    keyword: Token, // `while`, or `for` when the parser desugared a for loop into this node
+    condition: Expr,
+    body: Stmt,
+    increment: Option<Expr>, // a for loop's increment clause, run after every pass through the body
+}
+
+impl WhileStmt {
+    pub fn new(keyword: Token, condition: Expr, body: Stmt, increment: Option<Expr>) -> Stmt {
+        Stmt::While(Rc::new(WhileStmt {
+            keyword,
+            condition,
+            body,
+            increment,
+        }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+
+    pub fn condition(&self) -> &Expr {
+        &self.condition
+    }
+
+    pub fn body(&self) -> &Stmt {
+        &self.body
+    }
+
+    pub fn increment(&self) -> &Option<Expr> {
+        &self.increment
+    }
+}
@@ -1,11 +1,13 @@
+use std::collections::HashMap;
 use std::fmt::{self, Display};
+use std::sync::OnceLock;
 
 use crate::error::RloxError;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Literal {
     String(String),
-    Float(f32),
+    Float(f64),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -14,6 +16,7 @@ pub struct Token {
     lexeme: String,
     literal: Option<Literal>,
     line_number: usize,
+    column: usize,
 }
 
 impl Display for Token {
@@ -34,8 +37,15 @@ impl Token {
             lexeme,
             literal,
             line_number,
+            column: 0,
         })
     }
+    // where the token starts on the line it starts on, counted in characters from 1; a string
+    //   spanning lines has the line_number of the line it starts on, too
+    pub fn with_column(mut self, column: usize) -> Self {
+        self.column = column;
+        self
+    }
     pub fn token_type(&self) -> &TokenType {
         &self.token_type
     }
@@ -48,6 +58,10 @@ impl Token {
     pub fn line_number(&self) -> &usize {
         &self.line_number
     }
+    // 0 for tokens made up by the parser and tests rather than scanned from source
+    pub fn column(&self) -> usize {
+        self.column
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -82,7 +96,9 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    Break,
     Class,
+    Continue,
     Else,
     False,
     Fun,
@@ -101,24 +117,212 @@ pub enum TokenType {
     Eof,
 }
 
+/**
+ * What a token is, for tools that treat tokens by kind rather than one by one, e.g. highlighting.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenCategory {
+    Punctuation,
+    Operator,
+    Literal,
+    Keyword,
+    Eof,
+}
+
+/**
+ * How tightly an operator binds as an infix operator, loosest first.
+ *   Tokens that never appear between two operands have `None`.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub enum Precedence {
+    None,
+    Comma,      // ,
+    Assignment, // =
+    Or,         // or
+    And,        // and
+    Equality,   // == !=
+    Comparison, // < > <= >=
+    Term,       // + -
+    Factor,     // * /
+    Unary,      // ! -
+    Call,       // . ()
+}
+
+impl Precedence {
+    // one level tighter, for the right operand of a left-associative operator
+    pub(crate) fn next(self) -> Self {
+        use Precedence::*;
+        match self {
+            None => Comma,
+            Comma => Assignment,
+            Assignment => Or,
+            Or => And,
+            And => Equality,
+            Equality => Comparison,
+            Comparison => Term,
+            Term => Factor,
+            Factor => Unary,
+            Unary | Call => Call,
+        }
+    }
+}
+
+pub struct TokenInfo {
+    pub lexeme: Option<&'static str>, // the fixed spelling, for punctuation, operators and keywords
+    pub category: TokenCategory,
+    pub precedence: Precedence,
+}
+
+impl TokenType {
+    /**
+     * Every token type, in declaration order.
+     */
+    pub const ALL: [TokenType; 41] = {
+        use TokenType::*;
+        [
+            LeftParen,
+            RightParen,
+            LeftBrace,
+            RightBrace,
+            Comma,
+            Dot,
+            Minus,
+            Plus,
+            Semicolon,
+            Slash,
+            Star,
+            Bang,
+            BangEqual,
+            Equal,
+            EqualEqual,
+            Greater,
+            GreaterEqual,
+            Less,
+            LessEqual,
+            Identifier,
+            String,
+            Number,
+            And,
+            Break,
+            Class,
+            Continue,
+            Else,
+            False,
+            Fun,
+            For,
+            If,
+            Nil,
+            Or,
+            Print,
+            Return,
+            Super,
+            This,
+            True,
+            Var,
+            While,
+            Eof,
+        ]
+    };
+
+    /**
+     * The single table of token metadata: the scanner's keyword lookup and the parser's operator
+     *   precedence both read it, so a token's spelling and binding power are written down once.
+     */
+    pub fn info(&self) -> TokenInfo {
+        use Precedence as P;
+        use TokenCategory as C;
+        use TokenType::*;
+        let (lexeme, category, precedence) = match self {
+            LeftParen => (Some("("), C::Punctuation, P::Call),
+            RightParen => (Some(")"), C::Punctuation, P::None),
+            LeftBrace => (Some("{"), C::Punctuation, P::None),
+            RightBrace => (Some("}"), C::Punctuation, P::None),
+            Comma => (Some(","), C::Operator, P::Comma),
+            Dot => (Some("."), C::Operator, P::Call),
+            Minus => (Some("-"), C::Operator, P::Term),
+            Plus => (Some("+"), C::Operator, P::Term),
+            Semicolon => (Some(";"), C::Punctuation, P::None),
+            Slash => (Some("/"), C::Operator, P::Factor),
+            Star => (Some("*"), C::Operator, P::Factor),
+            Bang => (Some("!"), C::Operator, P::None),
+            BangEqual => (Some("!="), C::Operator, P::Equality),
+            Equal => (Some("="), C::Operator, P::Assignment),
+            EqualEqual => (Some("=="), C::Operator, P::Equality),
+            Greater => (Some(">"), C::Operator, P::Comparison),
+            GreaterEqual => (Some(">="), C::Operator, P::Comparison),
+            Less => (Some("<"), C::Operator, P::Comparison),
+            LessEqual => (Some("<="), C::Operator, P::Comparison),
+            Identifier | String | Number => (None, C::Literal, P::None),
+            And => (Some("and"), C::Keyword, P::And),
+            Break => (Some("break"), C::Keyword, P::None),
+            Class => (Some("class"), C::Keyword, P::None),
+            Continue => (Some("continue"), C::Keyword, P::None),
+            Else => (Some("else"), C::Keyword, P::None),
+            False => (Some("false"), C::Keyword, P::None),
+            Fun => (Some("fun"), C::Keyword, P::None),
+            For => (Some("for"), C::Keyword, P::None),
+            If => (Some("if"), C::Keyword, P::None),
+            Nil => (Some("nil"), C::Keyword, P::None),
+            Or => (Some("or"), C::Keyword, P::Or),
+            Print => (Some("print"), C::Keyword, P::None),
+            Return => (Some("return"), C::Keyword, P::None),
+            Super => (Some("super"), C::Keyword, P::None),
+            This => (Some("this"), C::Keyword, P::None),
+            True => (Some("true"), C::Keyword, P::None),
+            Var => (Some("var"), C::Keyword, P::None),
+            While => (Some("while"), C::Keyword, P::None),
+            Eof => (None, C::Eof, P::None),
+        };
+        TokenInfo {
+            lexeme,
+            category,
+            precedence,
+        }
+    }
+}
+
+// the keywords by spelling, taken from the token table the first time an identifier is scanned
 pub(crate) fn get_keyword_token_type(key: &str) -> Option<TokenType> {
-    match key {
-        "and" => Some(TokenType::And),
-        "class" => Some(TokenType::Class),
-        "else" => Some(TokenType::Else),
-        "false" => Some(TokenType::False),
-        "for" => Some(TokenType::For),
-        "fun" => Some(TokenType::Fun),
-        "if" => Some(TokenType::If),
-        "nil" => Some(TokenType::Nil),
-        "or" => Some(TokenType::Or),
-        "print" => Some(TokenType::Print),
-        "return" => Some(TokenType::Return),
-        "super" => Some(TokenType::Super),
-        "this" => Some(TokenType::This),
-        "true" => Some(TokenType::True),
-        "var" => Some(TokenType::Var),
-        "while" => Some(TokenType::While),
-        _ => None,
+    static KEYWORDS: OnceLock<HashMap<&'static str, TokenType>> = OnceLock::new();
+    let keywords = KEYWORDS.get_or_init(|| {
+        TokenType::ALL
+            .iter()
+            .filter_map(|token_type| {
+                let info = token_type.info();
+                match (info.category, info.lexeme) {
+                    (TokenCategory::Keyword, Some(lexeme)) => Some((lexeme, token_type.clone())),
+                    _ => None,
+                }
+            })
+            .collect()
+    });
+    keywords.get(key).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{get_keyword_token_type, TokenCategory, TokenType};
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn every_fixed_lexeme_scans_back_to_its_token_type() {
+        for token_type in TokenType::ALL.iter() {
+            if let Some(lexeme) = token_type.info().lexeme {
+                let scanner = Scanner::new(lexeme.to_string()).unwrap();
+                assert_eq!(scanner.tokens()[0].token_type(), token_type, "{}", lexeme);
+            }
+        }
+    }
+
+    #[test]
+    fn only_keywords_are_looked_up_as_keywords() {
+        assert_eq!(get_keyword_token_type("while"), Some(TokenType::While));
+        assert_eq!(get_keyword_token_type("=="), None);
+        assert_eq!(get_keyword_token_type("whilst"), None);
+        let keywords = TokenType::ALL
+            .iter()
+            .filter(|t| t.info().category == TokenCategory::Keyword)
+            .count();
+        assert_eq!(keywords, 18);
     }
 }
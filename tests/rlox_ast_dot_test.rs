@@ -0,0 +1,49 @@
+use rlox::ast_dot::AstDotPrinter;
+use rlox::parse_program;
+
+fn dot(source: &str) -> String {
+    let parsed = parse_program(source);
+    assert!(parsed.diagnostics.is_empty());
+    AstDotPrinter::default().print_program(&parsed.ast)
+}
+
+#[test]
+fn ast_dot_printer_prints_expression_tree() {
+    assert_eq!(
+        dot("print 1 + \"two\" * x;"),
+        r#"digraph ast {
+    node [shape=box];
+    n0 [label="Program"];
+    n1 [label="Print"];
+    n2 [label="+"];
+    n3 [label="1"];
+    n2 -> n3 [label="lhs"];
+    n4 [label="*"];
+    n5 [label="\"two\""];
+    n4 -> n5 [label="lhs"];
+    n6 [label="x"];
+    n4 -> n6 [label="rhs"];
+    n2 -> n4 [label="rhs"];
+    n1 -> n2;
+    n0 -> n1;
+}
+"#
+    );
+}
+
+#[test]
+fn ast_dot_printer_labels_statement_edges() {
+    let graph = dot("fun f(a, b) { if (a) return b; else { print a; } }");
+    assert!(graph.contains("n1 [label=\"Function f(a, b)\"];"));
+    assert!(graph.contains("[label=\"condition\"]"));
+    assert!(graph.contains("[label=\"then\"]"));
+    assert!(graph.contains("[label=\"else\"]"));
+}
+
+#[test]
+fn ast_dot_printer_can_be_reused() {
+    let printer = AstDotPrinter::default();
+    let first = printer.print_program(&parse_program("print 1;").ast);
+    let second = printer.print_program(&parse_program("print 1;").ast);
+    assert_eq!(first, second);
+}
@@ -0,0 +1,37 @@
+use rlox::ast_json::AstJsonPrinter;
+use rlox::parse_program;
+
+fn dump(source: &str) -> String {
+    let parsed = parse_program(source);
+    assert!(parsed.diagnostics.is_empty());
+    AstJsonPrinter::default().print_program(&parsed.ast)
+}
+
+#[test]
+fn ast_json_printer_prints_expressions_with_lines_and_literals() {
+    assert_eq!(
+        dump("print -1 + \"a\\b\";\nx = nil;"),
+        concat!(
+            "[{\"kind\":\"Print\",\"expression\":{\"kind\":\"Binary\",\"operator\":\"+\",\"line\":1,",
+            "\"lhs\":{\"kind\":\"Unary\",\"operator\":\"-\",\"line\":1,\"rhs\":{\"kind\":\"Literal\",\"value\":1}},",
+            "\"rhs\":{\"kind\":\"Literal\",\"value\":\"a\\\\b\"}}},",
+            "{\"kind\":\"Expression\",\"expression\":{\"kind\":\"Assign\",\"name\":\"x\",\"line\":2,",
+            "\"value\":{\"kind\":\"Literal\",\"value\":null}}}]"
+        )
+    );
+}
+
+#[test]
+fn ast_json_printer_prints_declarations() {
+    assert_eq!(
+        dump("class A < B { m(x) { return this.x; } }"),
+        concat!(
+            "[{\"kind\":\"Class\",\"name\":\"A\",\"line\":1,",
+            "\"superclass\":{\"kind\":\"Variable\",\"name\":\"B\",\"line\":1},",
+            "\"methods\":[{\"kind\":\"Function\",\"name\":\"m\",\"line\":1,\"params\":[\"x\"],",
+            "\"body\":[{\"kind\":\"Return\",\"keyword\":\"return\",\"line\":1,",
+            "\"value\":{\"kind\":\"Get\",\"name\":\"x\",\"line\":1,",
+            "\"object\":{\"kind\":\"This\",\"keyword\":\"this\",\"line\":1}}}]}]}]"
+        )
+    );
+}
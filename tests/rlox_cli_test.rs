@@ -1,12 +1,15 @@
 use assert_cmd::Command;
-use predicates::str::contains;
+use predicates::prelude::*;
+use predicates::str::{contains, starts_with};
 
 #[test]
-fn cli_too_many_args() {
+fn cli_option_without_its_value() {
     Command::cargo_bin("rlox")
         .unwrap()
-        .args(&["one", "two"])
+        .args(["--prompt"])
         .assert()
+        .stdout("")
+        .stderr(contains("Usage: rlox"))
         .code(64)
         .failure();
 }
@@ -15,7 +18,7 @@ fn cli_too_many_args() {
 fn cli_one_arg_with_invalid_filepath() {
     Command::cargo_bin("rlox")
         .unwrap()
-        .args(&["./tests/i-do-not-exist.txt"])
+        .args(["./tests/i-do-not-exist.txt"])
         .assert()
         .failure();
 }
@@ -24,26 +27,718 @@ fn cli_one_arg_with_invalid_filepath() {
 fn cli_one_arg_with_valid_filepath() {
     Command::cargo_bin("rlox")
         .unwrap()
-        .args(&["./tests/test_script.txt"])
+        .args(["./tests/test_script.txt"])
+        .assert()
+        .stdout("hello, world\n")
+        .success();
+}
+
+#[test]
+fn cli_one_arg_with_bom_and_crlf_filepath() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["./tests/test_script_bom_crlf.txt"])
+        .assert()
+        .stdout("hello, world\n")
+        .success();
+}
+
+#[test]
+fn cli_crlf_line_endings_do_not_leak_into_strings() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["./tests/test_script_crlf_line_numbers.txt"])
+        .assert()
+        .stdout("hello,\nworld\n1\n")
+        .success();
+}
+
+#[test]
+fn cli_dump_ast_prints_json() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--dump-ast", "./tests/test_script.txt"])
         .assert()
         .stdout(contains(
-"Print print
-String \"hello, world\"
-Semicolon ;
-Eof"))
+            "[{\"kind\":\"Print\",\"expression\":{\"kind\":\"Literal\",\"value\":\"hello, world\"}}]",
+        ))
+        .success();
+}
+
+#[test]
+fn cli_dump_ast_with_syntax_error() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--dump-ast", "./tests/test_script_syntax_error.txt"])
+        .assert()
+        .code(65)
+        .failure();
+}
+
+#[test]
+fn cli_ast_dot_prints_digraph() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["ast", "--dot", "./tests/test_script.txt"])
+        .assert()
+        .stdout(contains("digraph ast {"))
+        .stdout(contains("n1 [label=\"Print\"];"))
+        .success();
+}
+
+#[test]
+fn cli_runtime_error_exits_70() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["./tests/test_script_runtime_error.txt"])
+        .assert()
+        .stdout("before\n")
+        .stderr(contains("Operands must be numbers.\n[line 2]"))
+        .code(70)
+        .failure();
+}
+
+#[test]
+fn cli_define_selects_conditional_section() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args([
+            "--define",
+            "DEBUG",
+            "--dump-ast",
+            "./tests/test_script_conditional.txt",
+        ])
+        .assert()
+        .stdout(contains("\"value\":\"debug\""))
+        .stdout(contains("release").not())
         .success();
 }
 
 #[test]
-#[ignore]
 fn cli_no_arg() {
     Command::cargo_bin("rlox")
         .unwrap()
+        .env("RLOX_HISTORY_SIZE", "0")
+        .args(["repl"])
         .write_stdin("print \"hello, world\";")
         .assert()
+        .stdout(contains("> hello, world"));
+}
+
+#[test]
+fn cli_no_arg_runs_piped_input_as_a_script() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .write_stdin("var a = 1 +\n  2;\nprint a;\nprint -nil;\n")
+        .assert()
+        .stdout("3\n")
+        .stderr(contains("Operand must be a number.\n[line 4]"))
+        .code(70)
+        .failure();
+}
+
+#[test]
+fn cli_repl_exit_command_ends_the_session() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .env("RLOX_HISTORY_SIZE", "0")
+        .args(["repl"])
+        .write_stdin("print 1;\nexit\nprint 2;\n")
+        .assert()
+        .stdout("> 1\n> ")
+        .success();
+}
+
+#[test]
+fn cli_repl_load_runs_a_script_in_the_session() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .env("RLOX_HISTORY_SIZE", "0")
+        .args(["repl"])
+        .write_stdin(
+            ":load ./tests/test_script_closures.txt\n:load ./tests/missing.txt\ncounter()\n",
+        )
+        .assert()
+        .stdout("> 1\n2\n> > 3\nnil\n> \n")
+        .stderr(contains("cannot read script"))
+        .success();
+}
+
+#[test]
+fn cli_repl_reports_errors_and_keeps_its_state() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .env("RLOX_HISTORY_SIZE", "0")
+        .args(["repl"])
+        .write_stdin(&b"var a = 1;\nprint -\"x\";\n{ var b = b; }\n\xff\nprint a;\n"[..])
+        .assert()
+        .stdout("> > > > > 1\n> \n")
+        .stderr(contains("Operand must be a number."))
+        .stderr(contains(
+            "Can't read local variable in its own initializer.",
+        ))
+        .stderr(contains("valid UTF-8"))
+        .success();
+}
+
+#[test]
+fn cli_repl_reports_errors_in_expressions_instead_of_evaluating_them() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .env("RLOX_HISTORY_SIZE", "0")
+        .args(["repl"])
+        .write_stdin("1 = 2\n* 3\n4\n")
+        .assert()
+        .stdout("> > > 4\n> \n")
+        .stderr(contains("error[E0203]: at '=': Invalid assignment target."))
+        .stderr(contains("--> <repl>:1:3"))
+        .stderr(contains(
+            "error[E0201]: at '*': Binary operator without left-hand operand.",
+        ))
+        .success();
+}
+
+#[test]
+fn cli_repl_reset_forgets_the_session_definitions() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .env("RLOX_HISTORY_SIZE", "0")
+        .args(["repl"])
+        .write_stdin("var a = 1;\n:reset\na\nclock() > 0\n")
+        .assert()
+        .stdout("> > > > true\n> \n")
+        .stderr(contains("Undefined variable 'a'."))
+        .success();
+}
+
+#[test]
+fn cli_repl_keeps_the_last_result_in_underscore() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .env("RLOX_HISTORY_SIZE", "0")
+        .args(["repl"])
+        .write_stdin("fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }\nfib(10)\n_ * 2\n_\n")
+        .assert()
+        .stdout("> > 55\n> 110\n> 110\n> \n")
+        .success();
+}
+
+#[test]
+fn cli_repl_prompt_can_number_the_inputs() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .env("RLOX_HISTORY_SIZE", "0")
+        .args(["repl", "--prompt", "lox[{line}]> "])
+        .write_stdin("1\n2\n")
+        .assert()
+        .stdout("lox[1]> 1\nlox[2]> 2\nlox[3]> \n")
+        .success();
+}
+
+#[test]
+fn cli_repl_prints_the_value_of_a_bare_expression() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .env("RLOX_HISTORY_SIZE", "0")
+        .args(["repl"])
+        .write_stdin("1 + 2\nprint \"hello, world\";")
+        .assert()
+        .stdout(contains("> 3\n> hello, world"));
+}
+
+#[test]
+fn cli_print_config_reports_the_build_as_json() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--print-config"])
+        .assert()
+        .stdout(contains("\"version\":\"0.1.0\""))
+        .stdout(contains("\"max_script_bytes\":67108864"))
+        .success();
+}
+
+#[test]
+fn cli_blocks_shadow_and_restore_outer_variables() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["./tests/test_script_scoping.txt"])
+        .assert()
+        .stdout(
+            "inner a\nouter b\nglobal c\n\
+             outer a\nouter b\nglobal c\n\
+             global a\nglobal b\nglobal c\n",
+        )
+        .success();
+}
+
+#[test]
+fn cli_for_loop_prints_fibonacci_numbers() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["./tests/test_script_fibonacci.txt"])
+        .assert()
+        .stdout(
+            "0\n1\n1\n2\n3\n5\n8\n13\n21\n34\n55\n89\n144\n233\n377\n610\n987\n\
+             1597\n2584\n4181\n6765\n",
+        )
+        .success();
+}
+
+#[test]
+fn cli_closures_keep_their_counter_between_calls() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["./tests/test_script_closures.txt"])
+        .assert()
+        .stdout("1\n2\n")
+        .success();
+}
+
+#[test]
+fn cli_division_by_zero_is_infinity_by_default() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["./tests/test_script_division_by_zero.txt"])
+        .assert()
+        .stdout("Infinity\nafter\n")
+        .success();
+}
+
+#[test]
+fn cli_division_by_zero_error_flag_makes_it_a_runtime_error() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args([
+            "--division-by-zero-error",
+            "./tests/test_script_division_by_zero.txt",
+        ])
+        .assert()
+        .stdout("")
+        .stderr("error[E0503]: Division by zero.\n[line 1]\n")
+        .code(70);
+}
+
+#[test]
+fn cli_unbounded_recursion_is_a_runtime_error_not_a_crash() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["./tests/test_script_stack_overflow.txt"])
+        .assert()
+        .stdout("before\n")
+        .stderr(contains("Stack overflow.\n[line 2]"))
+        .code(70)
+        .failure();
+}
+
+#[test]
+fn cli_runtime_errors_in_calls_print_a_stack_trace() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["./tests/test_script_stack_trace.txt"])
+        .assert()
+        .stdout("before\n")
+        .stderr(contains(
+            "Operands must be two numbers or two strings.\n\
+             [line 2] in inner()\n\
+             [line 5] in outer()\n\
+             [line 8] in script",
+        ))
+        .code(70)
+        .failure();
+}
+
+#[test]
+fn cli_test_runner_passes_scripts_that_match_their_expectations() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["test", "./tests/lox/passing"])
+        .assert()
+        .stdout("3 passed, 0 failed\n")
+        .success();
+}
+
+#[test]
+fn cli_test_runner_reports_mismatched_output() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["test", "./tests/lox"])
+        .assert()
+        .stdout(contains(
+            "FAIL ./tests/lox/failing/wrong_output.lox\n    \
+             output line 1: expected \"3\", got \"2\"\n",
+        ))
+        .stdout(contains("3 passed, 1 failed\n"))
+        .code(1)
+        .failure();
+}
+
+#[test]
+fn cli_resolver_errors_exit_like_syntax_errors() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["./tests/test_script_resolve_error.txt"])
+        .assert()
+        .stdout("")
+        .stderr(contains("Can't return from top-level code."))
+        .code(65)
+        .failure();
+}
+
+#[test]
+fn cli_dash_reads_the_script_from_stdin() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg("-")
+        .write_stdin("var greeting = \"hello\";\nprint greeting;\nprint 1 - nil;\n")
+        .assert()
+        .stdout("hello\n")
+        .stderr(contains("Operands must be numbers.\n[line 3]"))
+        .code(70)
+        .failure();
+}
+
+#[test]
+fn cli_eval_runs_a_snippet_from_the_command_line() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["-e", "print 1 + 2;"])
+        .assert()
+        .stdout("3\n")
+        .success();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--eval", "print (;"])
+        .assert()
+        .stdout("")
+        .code(65)
+        .failure();
+}
+
+#[test]
+fn cli_check_reports_errors_without_running_the_script() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--check", "./tests/test_script_runtime_error.txt"])
+        .assert()
+        .stdout("")
+        .stderr("")
+        .success();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--check", "./tests/test_script_resolve_error.txt"])
+        .assert()
+        .stdout("")
+        .stderr(contains("Can't return from top-level code."))
+        .code(65)
+        .failure();
+}
+
+#[test]
+fn cli_dump_tokens_prints_one_token_per_line_with_its_position() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--dump-tokens", "-"])
+        .write_stdin("print \"hi\";\n  x = 2;")
+        .assert()
+        .stdout(
+            "Print \"print\" - 1:1\n\
+             String \"\\\"hi\\\"\" \"hi\" 1:7\n\
+             Semicolon \";\" - 1:11\n\
+             Identifier \"x\" - 2:3\n\
+             Equal \"=\" - 2:5\n\
+             Number \"2\" 2 2:7\n\
+             Semicolon \";\" - 2:8\n\
+             Eof \"\" - 2:9\n",
+        )
+        .success();
+}
+
+#[test]
+fn cli_passes_arguments_after_the_script_to_it() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["./tests/test_script_arguments.txt", "a b", "c"])
+        .assert()
+        .stdout("2\na b\nc\nnil\n")
+        .success();
+}
+
+#[test]
+fn cli_passes_option_like_arguments_after_the_script_to_it() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args([
+            "--seed",
+            "1",
+            "./tests/test_script_arguments.txt",
+            "-v",
+            "--seed",
+        ])
+        .assert()
+        .stdout("2\n-v\n--seed\nnil\n")
+        .stderr("")
+        .success();
+}
+
+#[test]
+fn cli_run_subcommand_passes_options_before_and_arguments_after_the_script() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args([
+            "run",
+            "--division-by-zero-error",
+            "./tests/test_script_arguments.txt",
+            "--define",
+            "X",
+        ])
+        .assert()
+        .stdout("2\n--define\nX\nnil\n")
+        .success();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["run", "-e", "print 1 + 2;"])
+        .assert()
+        .stdout("3\n")
+        .success();
+}
+
+#[test]
+fn cli_subcommands_reject_options_they_do_not_take() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["check", "--prompt", "$ ", "./tests/test_script.txt"])
+        .assert()
+        .stderr(contains(
+            "Usage: rlox [--color=WHEN] [--error-format=FORMAT] <command>",
+        ))
+        .code(64)
+        .failure();
+}
+
+#[test]
+fn cli_check_ast_tokens_and_config_subcommands() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["check", "./tests/test_script_resolve_error.txt"])
+        .assert()
+        .code(65)
+        .failure();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["ast", "./tests/test_script.txt"])
+        .assert()
+        .stdout(contains("\"kind\":\"Print\""))
+        .success();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["tokens", "-"])
+        .write_stdin("nil")
+        .assert()
+        .stdout("Nil \"nil\" - 1:1\nEof \"\" - 1:4\n")
+        .success();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["config"])
+        .assert()
+        .stdout(contains("\"version\""))
+        .success();
+}
+
+#[test]
+fn cli_help_lists_the_subcommands_on_stdout() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["help"])
+        .assert()
+        .stdout(contains("Commands:\n  run"))
+        .stderr("")
+        .success();
+}
+
+#[test]
+fn cli_preludes_run_into_the_globals_first() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["run", "--prelude", "./tests/test_script_prelude.txt"])
+        .args(["-e", "print square(3); print greeting;"])
+        .assert()
+        .stdout("9\nhi\n")
+        .success();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .env("RLOX_PRELUDE", "./tests/test_script_prelude.txt")
+        .env("RLOX_HISTORY_SIZE", "0")
+        .args(["repl"])
+        .write_stdin("print square(4);\n")
+        .assert()
+        .stdout(contains("16\n"))
+        .success();
+}
+
+#[test]
+fn cli_project_config_sets_defaults_and_preludes() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .current_dir("./tests/project")
+        .args(["script.lox"])
+        .assert()
+        .stdout("hello, project\n")
+        .stderr(contains("Division by zero.\n[line 2]"))
+        .code(70)
+        .failure();
+}
+
+#[test]
+fn cli_verbose_flags_trace_phases_and_calls_on_stderr() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["run", "-v", "-e", "print 1;"])
+        .assert()
+        .stdout("1\n")
+        .stderr(contains(
+            "[rlox] scanned 4 tokens\n\
+             [rlox] parsed 1 statements, 2 AST nodes\n\
+             [rlox] resolved 0 local variable references\n",
+        ))
+        .success();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["run", "-vv", "-e", "fun f(n) { return n; } f(2);"])
+        .assert()
+        .stderr(contains(
+            "[rlox] call f() with (2)\n\
+             [rlox] create environment\n\
+             [rlox] return 2 from f()\n",
+        ))
+        .success();
+}
+
+#[test]
+fn cli_deterministic_runs_print_the_same_every_time() {
+    let run = || {
+        let output = Command::cargo_bin("rlox")
+            .unwrap()
+            .args(["run", "--deterministic", "-e"])
+            .arg("print clock(); print random(); print random();")
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let first = run();
+    assert!(first.starts_with("0\n"));
+    assert_eq!(first, run());
+}
+
+#[test]
+fn cli_exit_native_ends_the_script_with_its_code() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["-e", "print 1; exit(3); print 2;"])
+        .assert()
+        .stdout("1\n")
+        .stderr("")
+        .code(3)
+        .failure();
+}
+
+#[test]
+fn cli_debugger_stops_at_breakpoints_and_shows_closed_over_variables() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["debug", "tests/test_script_closures.txt"])
+        .write_stdin("break 5\ncontinue\nprint i\nbacktrace\nquit\n")
+        .assert()
         .stdout(contains(
-"> Print print
-String \"hello, world\"
-Semicolon ;
-Eof"));
+            "(rlox) line 5 in count()\n   5 |     print i;\n\
+             (rlox) i = 1\n\
+             (rlox) [line 5] in count()\n[line 12] in script\n",
+        ))
+        .stderr("")
+        .success();
+}
+
+#[test]
+fn cli_syntax_errors_show_the_source_line_with_a_caret() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["check", "./tests/test_script_syntax_error.txt"])
+        .assert()
+        .stderr(
+            "error[E0202]: at end: Expect ';' after value.\n \
+             --> ./tests/test_script_syntax_error.txt:1:26\n  \
+             |\n\
+             1 | print \"missing semicolon\"\n  \
+             |                          ^\n",
+        )
+        .code(65)
+        .failure();
+}
+
+#[test]
+fn cli_color_always_colors_errors_red_and_their_trace_blue() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--color=always", "-e", "print -nil;"])
+        .assert()
+        .stderr("\x1b[31merror[E0501]: Operand must be a number.\x1b[0m\n\x1b[34m[line 1]\x1b[0m\n")
+        .code(70)
+        .failure();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["run", "--color=never", "-e", "print -nil;"])
+        .env_remove("NO_COLOR")
+        .assert()
+        .stderr("error[E0501]: Operand must be a number.\n[line 1]\n")
+        .code(70)
+        .failure();
+}
+
+#[test]
+fn cli_error_format_json_writes_each_diagnostic_as_an_object() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args([
+            "--error-format=json",
+            "check",
+            "./tests/test_script_syntax_error.txt",
+        ])
+        .assert()
+        .stderr(
+            "{\"code\":\"E0202\",\"severity\":\"error\",\
+             \"message\":\"at end: Expect ';' after value.\",\
+             \"file\":\"./tests/test_script_syntax_error.txt\",\
+             \"span\":{\"line\":1,\"column\":26,\"length\":1},\"notes\":[]}\n",
+        )
+        .code(65)
+        .failure();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["run", "--error-format=json", "-e", "print -nil;"])
+        .assert()
+        .stderr(
+            "{\"code\":\"E0501\",\"severity\":\"error\",\
+             \"message\":\"Operand must be a number.\",\"file\":\"<eval>\",\
+             \"span\":{\"line\":1,\"column\":7,\"length\":1},\"notes\":[]}\n",
+        )
+        .code(70)
+        .failure();
+}
+
+#[test]
+fn cli_explain_describes_an_error_code() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--explain", "E0203"])
+        .assert()
+        .stdout(starts_with(
+            "E0203: invalid assignment target\n\nThe left-hand side of `=`",
+        ))
+        .success();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--explain", "E9999"])
+        .assert()
+        .stderr("E9999 is not an rlox error code\n")
+        .code(64)
+        .failure();
 }
@@ -0,0 +1,123 @@
+use std::panic;
+
+use rlox::parser::Parser;
+use rlox::scanner::Scanner;
+
+const SAMPLE_SCRIPTS: &[&str] = &[
+    "print \"hello, world\";",
+    "var a = 1;\nvar b = \"two\";\nprint a + -b * (3 / 4) >= 5 == !true;",
+    "fun fib(n) {\n  if (n <= 1) return n;\n  return fib(n - 2) + fib(n - 1);\n}\n\
+     for (var i = 0; i < 20; i = i + 1) {\n  print fib(i);\n}",
+    "class Doughnut < Pastry {\n  init(filling) { this.filling = filling; }\n  \
+     cook() { super.cook(); print \"Fry until golden brown.\"; }\n}\n\
+     Doughnut(\"jam\").cook();",
+    "var i = 0; while (i < 3 and !false or nil) { i = i + 1; } // trailing comment",
+    "print \"héllo, 世界 ✓\"; var naïve = 1.5;",
+];
+
+const MALFORMED_INPUTS: &[&str] = &[
+    "\"",
+    "\"unterminated",
+    "print \"unterminated;\n",
+    "@",
+    "var # = 1;",
+    "*",
+    "+",
+    "==",
+    "!",
+    "-",
+    "(",
+    ")",
+    "{",
+    "}",
+    ";",
+    ".",
+    ",",
+    "print",
+    "print ;",
+    "var",
+    "var 1 = 2;",
+    "var a =",
+    "a = ",
+    "1 = 2;",
+    "(1 + 2",
+    "1 + 2)",
+    "fun",
+    "fun f(",
+    "fun f(a, ) {}",
+    "fun f() {",
+    "class",
+    "class A <",
+    "class A { 1 }",
+    "super",
+    "super.",
+    "this.",
+    "a.;",
+    "f(1, 2",
+    "if",
+    "if (",
+    "if (true",
+    "if (true)",
+    "while (",
+    "for (",
+    "for (;;",
+    "for (var i = 0; i < 1; i = i + 1",
+    "return",
+    "{ var a = 1;",
+];
+
+/**
+ * Runs the whole front end over `source`, turning a panic into a test failure that names the input.
+ *   Returns whether the source was accepted.
+ */
+fn assert_front_end_does_not_panic(source: &str) -> bool {
+    let owned = source.to_string();
+    let result = panic::catch_unwind(move || {
+        let scanner = match Scanner::new(owned) {
+            Ok(scanner) => scanner,
+            Err(_) => return false,
+        };
+        Parser::new(scanner.tokens().clone()).parse().is_ok()
+    });
+    match result {
+        Ok(accepted) => accepted,
+        Err(_) => panic!("front end panicked on input {:?}", source),
+    }
+}
+
+#[test]
+fn sample_scripts_are_accepted() {
+    for source in SAMPLE_SCRIPTS {
+        assert!(
+            assert_front_end_does_not_panic(source),
+            "expected {:?} to parse",
+            source
+        );
+    }
+}
+
+#[test]
+fn malformed_inputs_report_diagnostics() {
+    for source in MALFORMED_INPUTS {
+        assert!(
+            !assert_front_end_does_not_panic(source),
+            "expected a diagnostic for {:?}",
+            source
+        );
+    }
+}
+
+#[test]
+fn truncated_sample_scripts_never_panic() {
+    for source in SAMPLE_SCRIPTS {
+        for end in (0..=source.len()).filter(|&i| source.is_char_boundary(i)) {
+            assert_front_end_does_not_panic(&source[..end]);
+            assert_front_end_does_not_panic(&source[end..]);
+        }
+    }
+}
+
+#[test]
+fn empty_token_stream_never_panics() {
+    assert!(Parser::new(Vec::new()).parse().unwrap().is_empty());
+}
@@ -0,0 +1,81 @@
+use rlox::ast_printer::AstPrinter;
+use rlox::error::RloxError;
+use rlox::stmt::Stmt;
+use rlox::{parse_expression, parse_program};
+
+fn descriptions(diagnostics: &[RloxError]) -> Vec<String> {
+    diagnostics
+        .iter()
+        .map(|e| match e {
+            RloxError::SyntaxError(e) => e.description().to_string(),
+            _ => panic!("expected only syntax errors"),
+        })
+        .collect()
+}
+
+#[test]
+fn parse_expression_returns_the_expression() {
+    let parsed = parse_expression("-123 * (45.67)");
+    assert!(parsed.diagnostics.is_empty());
+    let printer = AstPrinter::default();
+    assert_eq!(
+        printer.print(parsed.ast.unwrap()),
+        "(* (- 123) (group 45.67))"
+    );
+}
+
+#[test]
+fn parse_expression_rejects_trailing_tokens() {
+    let parsed = parse_expression("1 + 2 3");
+    assert!(parsed.ast.is_none());
+    assert_eq!(
+        descriptions(&parsed.diagnostics),
+        vec!["at '3': Expect end of expression."]
+    );
+}
+
+#[test]
+fn parse_expression_reports_scan_errors() {
+    let parsed = parse_expression("\"unterminated");
+    assert!(parsed.ast.is_none());
+    assert_eq!(
+        descriptions(&parsed.diagnostics),
+        vec!["Unterminated string.", "at end: Expect expression."]
+    );
+}
+
+#[test]
+fn parse_program_reports_scan_and_parse_errors_together() {
+    let parsed = parse_program("print 1 # 2;\nprint ;\nprint \"end");
+    assert_eq!(
+        descriptions(&parsed.diagnostics),
+        vec![
+            "Unexpected character.",
+            "Unterminated string.",
+            "at '2': Expect ';' after value.",
+            "at ';': Expect expression.",
+            "at end: Expect expression.",
+        ]
+    );
+}
+
+#[test]
+fn parse_program_returns_statements() {
+    let parsed = parse_program("\u{feff}var a = 1;\r\nprint a;\r\n");
+    assert!(parsed.diagnostics.is_empty());
+    assert!(matches!(parsed.ast[0], Stmt::Var(_)));
+    assert!(matches!(parsed.ast[1], Stmt::Print(_)));
+}
+
+#[test]
+fn parse_program_keeps_recovered_statements_alongside_diagnostics() {
+    let parsed = parse_program("print ;\nprint 1;\nvar = 2;\nprint 3;");
+    assert_eq!(parsed.ast.len(), 2);
+    assert_eq!(
+        descriptions(&parsed.diagnostics),
+        vec![
+            "at ';': Expect expression.",
+            "at '=': Expect variable name."
+        ]
+    );
+}
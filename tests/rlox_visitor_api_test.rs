@@ -0,0 +1,60 @@
+use std::cell::RefCell;
+
+use rlox::expr::{Expr, ExprVisitor};
+use rlox::parse_program;
+use rlox::stmt::{Stmt, StmtVisitor};
+
+// an out-of-crate pass: collects every variable name read, in source order
+struct VariableReads {
+    names: RefCell<Vec<String>>,
+}
+
+impl ExprVisitor<()> for VariableReads {
+    fn visit_expr(&self, expr: &Expr) {
+        match expr {
+            Expr::Variable(expr) => self
+                .names
+                .borrow_mut()
+                .push(expr.name().lexeme().to_string()),
+            Expr::Assign(expr) => expr.value().accept(self),
+            Expr::Binary(expr) => {
+                expr.lhs().accept(self);
+                expr.rhs().accept(self);
+            }
+            Expr::Call(expr) => {
+                expr.callee().accept(self);
+                expr.arguments().iter().for_each(|a| a.accept(self));
+            }
+            Expr::Grouping(expr) => expr.expression().accept(self),
+            Expr::Unary(expr) => expr.rhs().accept(self),
+            _ => {}
+        }
+    }
+}
+
+impl StmtVisitor<()> for VariableReads {
+    fn visit_stmt(&self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block(stmt) => stmt.statements().iter().for_each(|s| s.accept(self)),
+            Stmt::Expression(stmt) => stmt.expression().accept(self),
+            Stmt::Print(stmt) => stmt.expression().accept(self),
+            Stmt::Var(stmt) => {
+                if let Some(initializer) = stmt.initializer() {
+                    initializer.accept(self);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[test]
+fn external_visitors_can_walk_statements_and_expressions() {
+    let parsed = parse_program("var a = b + c;\n{\n  print -a;\n  f(d, (e));\n}");
+    assert!(parsed.diagnostics.is_empty());
+    let reads = VariableReads {
+        names: RefCell::new(Vec::new()),
+    };
+    parsed.ast.iter().for_each(|stmt| stmt.accept(&reads));
+    assert_eq!(reads.names.into_inner(), vec!["b", "c", "a", "f", "d", "e"]);
+}